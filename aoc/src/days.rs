@@ -0,0 +1,20 @@
+use solution::Solution;
+
+pub fn get_solution(day: u32) -> Option<&'static dyn Solution> {
+    match day {
+        1 => Some(&day_1::Day1),
+        2 => Some(&day_2::Day2),
+        3 => Some(&day_3::Day3),
+        4 => Some(&day_4::Day4),
+        5 => Some(&day_5::Day5),
+        6 => Some(&day_6::Day6),
+        7 => Some(&day_7::Day7),
+        8 => Some(&day_8::Day8),
+        9 => Some(&day_9::Day9),
+        10 => Some(&day_10::Day10),
+        11 => Some(&day_11::Day11),
+        12 => Some(&day_12::Day12),
+        13 => Some(&day_13::Day13),
+        _ => None,
+    }
+}
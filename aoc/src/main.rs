@@ -0,0 +1,345 @@
+// Single entry point for every day, e.g.:
+// aoc run --day 11 --part 2 --input day-11/input.txt
+// aoc disassemble --day 13
+// aoc debug --input program.txt
+// aoc ascii --day 25 --script moves.txt --transcript session.txt
+// aoc fuzz --iterations 100000
+mod days;
+
+use solution::Answer;
+use std::env;
+use std::error;
+use std::fs;
+use std::io;
+use std::process;
+
+const USAGE: &str = "usage: aoc run --day <day> [--part <1|2>] [--input <path>]
+       aoc disassemble (--day <day> | --input <path>)
+       aoc debug (--day <day> | --input <path>)
+       aoc ascii (--day <day> | --input <path>) [--script <path>] [--transcript <path>]
+       aoc fuzz [--seed <seed>] [--iterations <count>]";
+
+// instructions the debugger can step back through
+const DEBUGGER_HISTORY: usize = 1_000_000;
+const DEBUGGER_CHECKPOINT_INTERVAL: u64 = 10_000;
+
+const DEFAULT_FUZZ_ITERATIONS: usize = 10_000;
+
+#[derive(PartialEq, Debug)]
+enum Command {
+    Run {
+        day: u32,
+        // both parts are run when no part is given
+        part: Option<u32>,
+        // defaults to day-<day>/input.txt
+        input: Option<String>,
+    },
+    // prints the listing of an Intcode program
+    Disassemble {
+        input: String,
+    },
+    // starts the Intcode debugger on a program
+    Debug {
+        input: String,
+    },
+    // runs an Intcode program that reads and writes text
+    Ascii {
+        input: String,
+        // lines sent before the ones typed
+        script: Option<String>,
+        // where to save the session
+        transcript: Option<String>,
+    },
+    // compares the Intcode backends on random programs
+    Fuzz {
+        seed: u64,
+        iterations: usize,
+    },
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = parse_command(&args).and_then(|command| run_command(&command));
+    if let Err(err) = result {
+        eprintln!("error: {}", err);
+        eprintln!("{}", USAGE);
+        process::exit(1);
+    }
+}
+
+fn print_answer(part: u8, answer: Answer) {
+    match answer {
+        Answer::Unsolved => println!("part {} is not solved", part),
+        answer => println!("{}", answer),
+    }
+}
+
+fn run_command(command: &Command) -> Result<(), Box<dyn error::Error>> {
+    match command {
+        Command::Run { day, part, input } => {
+            let solution =
+                days::get_solution(*day).ok_or_else(|| format!("day {} is not solved", day))?;
+            let input_path = match input {
+                Some(path) => path.clone(),
+                None => format!("day-{}/input.txt", day),
+            };
+            let input = fs::read_to_string(&input_path)
+                .map_err(|err| format!("can not read {}: {}", input_path, err))?;
+            if *part != Some(2) {
+                print_answer(1, solution.part_1(&input));
+            }
+            if *part != Some(1) {
+                print_answer(2, solution.part_2(&input));
+            }
+            Ok(())
+        }
+        Command::Disassemble { input } => {
+            let code = fs::read_to_string(input)
+                .map_err(|err| format!("can not read {}: {}", input, err))?;
+            let program: Vec<intcode::Value> = intcode::parse_code(&code)?;
+            print!("{}", intcode::disassemble(&program));
+            Ok(())
+        }
+        Command::Debug { input } => {
+            let mut interpreter: intcode::Interpreter = intcode::Interpreter::from_file(input)?;
+            interpreter.enable_history(DEBUGGER_HISTORY, DEBUGGER_CHECKPOINT_INTERVAL);
+            let stdin = io::stdin();
+            intcode::Debugger::new(interpreter).run_repl(stdin.lock(), io::stdout())?;
+            Ok(())
+        }
+        Command::Ascii {
+            input,
+            script,
+            transcript,
+        } => {
+            let mut interpreter: intcode::Interpreter = intcode::Interpreter::from_file(input)?;
+            let stdin = io::stdin();
+            let mut console = intcode::AsciiConsole::new(stdin.lock(), io::stdout());
+            if let Some(script) = script {
+                console
+                    .load_script_file(script)
+                    .map_err(|err| format!("can not read {}: {}", script, err))?;
+            }
+            if transcript.is_some() {
+                console.record_transcript();
+            }
+            let result = interpreter.run_ascii(&mut console);
+            // the transcript helps to understand what went wrong, too
+            if let Some(transcript) = transcript {
+                console.save_transcript(transcript)?;
+            }
+            result?;
+            Ok(())
+        }
+        Command::Fuzz { seed, iterations } => match intcode::Fuzzer::new(*seed).run(*iterations) {
+            Some(divergence) => {
+                print!("{}", divergence);
+                if let Some(test) = divergence.to_regression_test("test_divergence") {
+                    print!("\n{}", test);
+                }
+                // not a usage error
+                process::exit(1);
+            }
+            None => {
+                println!("no divergence in {} programs", iterations);
+                Ok(())
+            }
+        },
+    }
+}
+
+fn parse_command(args: &[String]) -> Result<Command, Box<dyn error::Error>> {
+    match args.first().map(String::as_str) {
+        Some("run") => parse_run_command(&args[1..]),
+        Some("disassemble") => Ok(Command::Disassemble {
+            input: parse_program_path(&args[1..])?,
+        }),
+        Some("debug") => Ok(Command::Debug {
+            input: parse_program_path(&args[1..])?,
+        }),
+        Some("ascii") => parse_ascii_command(&args[1..]),
+        Some("fuzz") => parse_fuzz_command(&args[1..]),
+        Some(other) => Err(format!("unknown command: {}", other).into()),
+        None => Err("missing command".into()),
+    }
+}
+
+fn parse_run_command(args: &[String]) -> Result<Command, Box<dyn error::Error>> {
+    let mut day = None;
+    let mut part = None;
+    let mut input = None;
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("missing value for {}", flag))?;
+        match flag.as_str() {
+            "--day" => day = Some(value.parse::<u32>()?),
+            "--part" => match value.parse::<u32>()? {
+                part_number @ 1..=2 => part = Some(part_number),
+                _ => return Err(format!("invalid part: {}", value).into()),
+            },
+            "--input" => input = Some(value.clone()),
+            _ => return Err(format!("unknown option: {}", flag).into()),
+        }
+    }
+    Ok(Command::Run {
+        day: day.ok_or("missing --day")?,
+        part,
+        input,
+    })
+}
+
+fn parse_ascii_command(args: &[String]) -> Result<Command, Box<dyn error::Error>> {
+    let mut script = None;
+    let mut transcript = None;
+    let mut program_args = Vec::new();
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("missing value for {}", flag))?;
+        match flag.as_str() {
+            "--script" => script = Some(value.clone()),
+            "--transcript" => transcript = Some(value.clone()),
+            _ => program_args.extend_from_slice(&[flag.clone(), value.clone()]),
+        }
+    }
+    Ok(Command::Ascii {
+        input: parse_program_path(&program_args)?,
+        script,
+        transcript,
+    })
+}
+
+fn parse_fuzz_command(args: &[String]) -> Result<Command, Box<dyn error::Error>> {
+    let mut seed = 0;
+    let mut iterations = DEFAULT_FUZZ_ITERATIONS;
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("missing value for {}", flag))?;
+        match flag.as_str() {
+            "--seed" => seed = value.parse::<u64>()?,
+            "--iterations" => iterations = value.parse::<usize>()?,
+            _ => return Err(format!("unknown option: {}", flag).into()),
+        }
+    }
+    Ok(Command::Fuzz { seed, iterations })
+}
+
+// the program of a day (--day) or any file (--input)
+fn parse_program_path(args: &[String]) -> Result<String, Box<dyn error::Error>> {
+    match args {
+        [flag, day] if flag == "--day" => Ok(format!("day-{}/input.txt", day.parse::<u32>()?)),
+        [flag, input] if flag == "--input" => Ok(input.clone()),
+        _ => Err("expected either --day or --input".into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_args(args: &str) -> Vec<String> {
+        args.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_parse_run_command() {
+        assert_eq!(
+            parse_command(&to_args("run --day 11 --part 2 --input foo.txt")).unwrap(),
+            Command::Run {
+                day: 11,
+                part: Some(2),
+                input: Some(String::from("foo.txt"))
+            }
+        );
+        assert_eq!(
+            parse_command(&to_args("run --day 3")).unwrap(),
+            Command::Run {
+                day: 3,
+                part: None,
+                input: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_disassemble_command() {
+        assert_eq!(
+            parse_command(&to_args("disassemble --day 13")).unwrap(),
+            Command::Disassemble {
+                input: String::from("day-13/input.txt")
+            }
+        );
+        assert_eq!(
+            parse_command(&to_args("disassemble --input foo.txt")).unwrap(),
+            Command::Disassemble {
+                input: String::from("foo.txt")
+            }
+        );
+        assert!(parse_command(&to_args("disassemble")).is_err());
+        assert!(parse_command(&to_args("disassemble --day 13 --input foo.txt")).is_err());
+        assert_eq!(
+            parse_command(&to_args("debug --day 9")).unwrap(),
+            Command::Debug {
+                input: String::from("day-9/input.txt")
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_ascii_command() {
+        assert_eq!(
+            parse_command(&to_args(
+                "ascii --script moves.txt --day 25 --transcript out.txt"
+            ))
+            .unwrap(),
+            Command::Ascii {
+                input: String::from("day-25/input.txt"),
+                script: Some(String::from("moves.txt")),
+                transcript: Some(String::from("out.txt")),
+            }
+        );
+        assert_eq!(
+            parse_command(&to_args("ascii --input foo.txt")).unwrap(),
+            Command::Ascii {
+                input: String::from("foo.txt"),
+                script: None,
+                transcript: None,
+            }
+        );
+        assert!(parse_command(&to_args("ascii --script moves.txt")).is_err());
+        assert!(parse_command(&to_args("ascii --day 25 --script")).is_err());
+    }
+
+    #[test]
+    fn test_parse_fuzz_command() {
+        assert_eq!(
+            parse_command(&to_args("fuzz")).unwrap(),
+            Command::Fuzz {
+                seed: 0,
+                iterations: DEFAULT_FUZZ_ITERATIONS
+            }
+        );
+        assert_eq!(
+            parse_command(&to_args("fuzz --iterations 5 --seed 42")).unwrap(),
+            Command::Fuzz {
+                seed: 42,
+                iterations: 5
+            }
+        );
+        assert!(parse_command(&to_args("fuzz --seed -1")).is_err());
+    }
+
+    #[test]
+    fn test_parse_invalid_command() {
+        assert!(parse_command(&to_args("")).is_err());
+        assert!(parse_command(&to_args("walk --day 3")).is_err());
+        assert!(parse_command(&to_args("run --part 1")).is_err());
+        assert!(parse_command(&to_args("run --day 3 --part 3")).is_err());
+        assert!(parse_command(&to_args("run --day")).is_err());
+    }
+}
@@ -1,22 +1,26 @@
-use std::error;
+use solution::{Answer, Solution};
 use std::cmp;
-use std::fs::File;
-use std::io::{BufReader, BufRead};
 
-fn main() -> Result<(), Box<dyn error::Error>> {
-    let file = File::open("input.txt")?;
-    let reader = BufReader::new(file);
+pub struct Day1;
 
-    let mut total_fuel = 0;
-    for line in reader.lines() {
-        let module_mass = line?.parse::<i32>()?;
+impl Solution for Day1 {
+    fn part_1(&self, input: &str) -> Answer {
         // let's ignore overflows
-        // change this to just `compute_fuel_for_mass` for part 1
-        total_fuel += compute_total_fuel_for_mass(module_mass);
+        parse_masses(input).map(compute_fuel_for_mass).sum::<i32>().into()
     }
-    println!("{}", total_fuel);
 
-    Ok(())
+    fn part_2(&self, input: &str) -> Answer {
+        parse_masses(input)
+            .map(compute_total_fuel_for_mass)
+            .sum::<i32>()
+            .into()
+    }
+}
+
+fn parse_masses(input: &str) -> impl Iterator<Item = i32> + '_ {
+    input
+        .lines()
+        .map(|line| line.parse::<i32>().expect("invalid module mass"))
 }
 
 // there's probably a nice formula for that, but why do the machine job?
@@ -36,6 +40,8 @@ fn compute_fuel_for_mass(mass: i32) -> i32 {
 mod tests {
     use super::*;
 
+    const INPUT: &str = include_str!("../input.txt");
+
     #[test]
     fn test_compute_fuel_for_mass() {
         assert_eq!(compute_fuel_for_mass(12), 2);
@@ -55,4 +61,13 @@ mod tests {
         assert_eq!(compute_total_fuel_for_mass(100756), 50346);
     }
 
+    #[test]
+    fn test_part_1_answer() {
+        assert_eq!(Day1.part_1(INPUT), Answer::Integer(3256114));
+    }
+
+    #[test]
+    fn test_part_2_answer() {
+        assert_eq!(Day1.part_2(INPUT), Answer::Integer(4881302));
+    }
 }
@@ -1,49 +1,41 @@
+use solution::{Answer, Solution};
 use std::cmp::Ordering;
 use std::collections::BTreeSet;
-use std::error;
-use std::fs::File;
-use std::io::Read;
-
-fn main() {
-    // load data
-    let map = load_from_file("input.txt").unwrap();
-    // part 1
-    let (station_location, number_of_visible_asteroids) = get_best_station_location(&map);
-    println!("{}", number_of_visible_asteroids);
-    println!(
-        "best_x:{} best_y:{}",
-        station_location.x, station_location.y
-    );
-
-    // part 2
-    let mut asteroid_list = map.iter().collect::<Vec<&Point>>();
-
-    // sort asteroids according the order they will be destroyed by the laser
-    // the order is defined by:
-    // first, the number of other asteroids between the asteroid and the laser source
-    // for two asteroids with the same "distance" (defined in number of asteroids),
-    // we compare their position clockwise
-    asteroid_list
-        .sort_by_key(|asteroid| asteroid.get_relative_coordinates_from(station_location, &map));
-
-    // first asteroid in the list is our laser station, so there's no off-by-one error here
-    let best_asteroid = asteroid_list.get(200).unwrap();
-    println!(
-        "asteroid 200: ({}, {}) -> {}",
-        best_asteroid.x,
-        best_asteroid.y,
-        best_asteroid.x * 100 + best_asteroid.y
-    );
+
+pub struct Day10;
+
+impl Solution for Day10 {
+    fn part_1(&self, input: &str) -> Answer {
+        let map = load_from_string(input);
+        let (_, number_of_visible_asteroids) = get_best_station_location(&map);
+        number_of_visible_asteroids.into()
+    }
+
+    fn part_2(&self, input: &str) -> Answer {
+        let map = load_from_string(input);
+        let (station_location, _) = get_best_station_location(&map);
+        let mut asteroid_list = map.iter().collect::<Vec<&Point>>();
+
+        // sort asteroids according the order they will be destroyed by the laser
+        // the order is defined by:
+        // first, the number of other asteroids between the asteroid and the laser source
+        // for two asteroids with the same "distance" (defined in number of asteroids),
+        // we compare their position clockwise
+        asteroid_list.sort_by_key(|asteroid| {
+            asteroid.get_relative_coordinates_from(station_location, &map)
+        });
+
+        // first asteroid in the list is our laser station, so there's no off-by-one error here
+        let best_asteroid = asteroid_list.get(200).unwrap();
+        (best_asteroid.x * 100 + best_asteroid.y).into()
+    }
 }
 
-fn load_from_file(path: &str) -> Result<AsteroidMap, Box<dyn error::Error>> {
-    let mut file = File::open(path)?;
-    let mut buffer = String::new();
-    file.read_to_string(&mut buffer)?;
+fn load_from_string(input: &str) -> AsteroidMap {
     let mut x = 0;
     let mut y = 0;
     let mut asteroids = BTreeSet::new();
-    for c in buffer.chars() {
+    for c in input.chars() {
         if c == '#' {
             asteroids.insert(Point { x, y });
         }
@@ -53,7 +45,7 @@ fn load_from_file(path: &str) -> Result<AsteroidMap, Box<dyn error::Error>> {
             x = 0;
         }
     }
-    Ok(asteroids)
+    asteroids
 }
 
 fn get_best_station_location(map: &AsteroidMap) -> (&Point, isize) {
@@ -63,7 +55,7 @@ fn get_best_station_location(map: &AsteroidMap) -> (&Point, isize) {
         .map(|asteroid| {
             (
                 asteroid,
-                compute_number_of_visible_asteroids_from(asteroid, &map),
+                compute_number_of_visible_asteroids_from(asteroid, map),
             )
         })
         .max_by_key(|element| element.1)
@@ -103,6 +95,7 @@ struct RelativeCoordinates {
 
 // hack incoming - ignore NaN floats
 impl Eq for RelativeCoordinates {}
+#[allow(clippy::derive_ord_xor_partial_ord)]
 impl Ord for RelativeCoordinates {
     fn cmp(&self, other: &Self) -> Ordering {
         self.partial_cmp(other).unwrap_or(Ordering::Equal)
@@ -146,7 +139,7 @@ impl Point {
         asteroid: &Point,
         map: &AsteroidMap,
     ) -> RelativeCoordinates {
-        let distance = get_number_of_asteroids_between(&self, asteroid, map);
+        let distance = get_number_of_asteroids_between(self, asteroid, map);
         //  -> x
         // |  . . .dx . .
         // v  . . +-- # .
@@ -196,3 +189,20 @@ fn get_number_of_asteroids_between(
     // remove asteroid_1 from the count
     count - 1
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = include_str!("../input.txt");
+
+    #[test]
+    fn test_part_1_answer() {
+        assert_eq!(Day10.part_1(INPUT), Answer::Integer(282));
+    }
+
+    #[test]
+    fn test_part_2_answer() {
+        assert_eq!(Day10.part_2(INPUT), Answer::Integer(1008));
+    }
+}
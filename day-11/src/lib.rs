@@ -0,0 +1,169 @@
+use intcode::{Exit, FrameDevice, Framed, InputDevice};
+use solution::{Answer, Solution};
+use std::collections::BTreeSet;
+
+pub struct Day11;
+
+impl Solution for Day11 {
+    fn part_1(&self, input: &str) -> Answer {
+        part_1(input).into()
+    }
+
+    fn part_2(&self, input: &str) -> Answer {
+        part_2(input).into()
+    }
+}
+
+enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+#[derive(PartialOrd, Ord, PartialEq, Eq, Clone, Copy)]
+struct Point {
+    x: isize,
+    y: isize,
+}
+
+// Reads the color of the panel under it, then receives (color to paint, rotation) pairs
+struct Robot {
+    coordinates: Point,
+    direction: Direction,
+    white_panels: BTreeSet<Point>,
+    painted_panels: BTreeSet<Point>,
+}
+
+impl Robot {
+    fn new(starting_panel_color: intcode::Value) -> Robot {
+        let mut white_panels = BTreeSet::new();
+        if starting_panel_color == 1 {
+            white_panels.insert(Point { x: 0, y: 0 });
+        }
+        Robot {
+            coordinates: Point { x: 0, y: 0 },
+            direction: Direction::Up,
+            white_panels,
+            painted_panels: BTreeSet::new(),
+        }
+    }
+}
+
+impl InputDevice<intcode::Value> for Robot {
+    fn read(&mut self) -> Option<intcode::Value> {
+        if self.white_panels.contains(&self.coordinates) {
+            Some(1)
+        } else {
+            Some(0)
+        }
+    }
+}
+
+impl FrameDevice<intcode::Value> for Robot {
+    fn write_frame(&mut self, frame: &[intcode::Value]) {
+        match frame[0] {
+            0 => self.white_panels.remove(&self.coordinates),
+            1 => self.white_panels.insert(self.coordinates),
+            _ => panic!("unexpected color {}", frame[0]),
+        };
+        self.painted_panels.insert(self.coordinates);
+        self.direction = match frame[1] {
+            0 => turn_left(&self.direction),
+            1 => turn_right(&self.direction),
+            _ => panic!("unexpected rotation {}", frame[1]),
+        };
+        self.coordinates = move_robot(&self.coordinates, &self.direction);
+    }
+}
+
+fn run_robot(input: &str, starting_panel_color: intcode::Value) -> Robot {
+    let mut source_interpreter: intcode::Interpreter =
+        intcode::Interpreter::from_code(input).unwrap();
+    let mut robot = Framed::new(Robot::new(starting_panel_color), 2);
+    match source_interpreter.run_with(&mut robot).unwrap() {
+        Exit::Halt => robot.into_inner(),
+        _ => panic!("unexpected program behaviour"),
+    }
+}
+
+fn part_2(input: &str) -> String {
+    render_panels(&run_robot(input, 1).white_panels)
+}
+
+fn render_panels(white_panels: &BTreeSet<Point>) -> String {
+    let min_x = white_panels.iter().map(|point| point.x).min().unwrap();
+    let max_x = white_panels.iter().map(|point| point.x).max().unwrap();
+    let min_y = white_panels.iter().map(|point| point.y).min().unwrap();
+    let max_y = white_panels.iter().map(|point| point.y).max().unwrap();
+    let mut rendered = String::new();
+    for x in min_x..=max_x {
+        for y in min_y..=max_y {
+            if white_panels.contains(&Point { x, y }) {
+                rendered.push('+');
+            } else {
+                rendered.push(' ');
+            }
+        }
+        rendered.push('\n');
+    }
+    rendered
+}
+
+fn part_1(input: &str) -> usize {
+    run_robot(input, 0).painted_panels.len()
+}
+
+fn move_robot(robot_coordinates: &Point, robot_direction: &Direction) -> Point {
+    let x = robot_coordinates.x;
+    let y = robot_coordinates.y;
+    match robot_direction {
+        Direction::Up => Point { x: x - 1, y },
+        Direction::Left => Point { x, y: y - 1 },
+        Direction::Down => Point { x: x + 1, y },
+        Direction::Right => Point { x, y: y + 1 },
+    }
+}
+
+fn turn_left(direction: &Direction) -> Direction {
+    match direction {
+        Direction::Up => Direction::Left,
+        Direction::Left => Direction::Down,
+        Direction::Down => Direction::Right,
+        Direction::Right => Direction::Up,
+    }
+}
+
+fn turn_right(direction: &Direction) -> Direction {
+    match direction {
+        Direction::Up => Direction::Right,
+        Direction::Right => Direction::Down,
+        Direction::Down => Direction::Left,
+        Direction::Left => Direction::Up,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = include_str!("../input.txt");
+
+    #[test]
+    fn test_part_1_answer() {
+        assert_eq!(Day11.part_1(INPUT), Answer::Integer(1883));
+    }
+
+    #[test]
+    fn test_part_2_answer() {
+        let expected = concat!(
+            " ++  +++  +  +  ++  +  + +++  ++++ +  +\n",
+            "+  + +  + +  + +  + +  + +  + +    +  +\n",
+            "+  + +  + +  + +    +  + +  + +++  ++++\n",
+            "++++ +++  +  + + ++ +  + +++  +    +  +\n",
+            "+  + +    +  + +  + +  + + +  +    +  +\n",
+            "+  + +     ++   +++  ++  +  + +    +  +\n",
+        );
+        assert_eq!(Day11.part_2(INPUT), Answer::Text(String::from(expected)));
+    }
+}
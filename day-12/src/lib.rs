@@ -1,4 +1,4 @@
-// use std::fs::read_to_string;
+use solution::{Answer, Solution};
 use std::ops::Add;
 use std::thread;
 
@@ -92,7 +92,7 @@ impl Body {
     }
 }
 
-fn run_simulation_step(bodies: &mut Vec<Body>) {
+fn run_simulation_step(bodies: &mut [Body]) {
     // https://docs.rs/itertools/0.8.0/itertools/trait.Itertools.html#method.combinations
     // could come in handy, but let's do it manually
     for i in 0..(bodies.len() - 1) {
@@ -110,32 +110,44 @@ fn run_simulation_step(bodies: &mut Vec<Body>) {
     }
 }
 
-fn get_hardcoded_input() -> Vec<Body> {
-    // let input = read_to_string("input.txt").expect("could not read input");
-    // save the parsing, hardcode what we want! (we could use a regexp to parse this)
-    // <x=-2, y=9, z=-5>
-    // <x=16, y=19, z=9>
-    // <x=0, y=3, z=6>
-    // <x=11, y=0, z=11>
-    let mut bodies = Vec::new();
-    bodies.push(Body::new(
-        &Position::new(-2, 9, -5),
-        &Velocity::new(0, 0, 0),
-    ));
-    bodies.push(Body::new(
-        &Position::new(16, 19, 9),
-        &Velocity::new(0, 0, 0),
-    ));
-    bodies.push(Body::new(&Position::new(0, 3, 6), &Velocity::new(0, 0, 0)));
-    bodies.push(Body::new(
-        &Position::new(11, 0, 11),
-        &Velocity::new(0, 0, 0),
-    ));
-    bodies
-}
-
-fn part_1() {
-    let mut bodies = get_hardcoded_input();
+// each line looks like "<x=-2, y=9, z=-5>"
+// (we could use a regexp to parse this)
+fn parse_input(input: &str) -> Vec<Body> {
+    input
+        .lines()
+        .map(|line| {
+            let coordinates: Vec<isize> = line
+                .trim()
+                .trim_start_matches('<')
+                .trim_end_matches('>')
+                .split(',')
+                .map(|coordinate| {
+                    let value = coordinate.split('=').nth(1).expect("invalid coordinate");
+                    value.parse::<isize>().unwrap()
+                })
+                .collect();
+            Body::new(
+                &Position::new(coordinates[0], coordinates[1], coordinates[2]),
+                &Velocity::new(0, 0, 0),
+            )
+        })
+        .collect()
+}
+
+pub struct Day12;
+
+impl Solution for Day12 {
+    fn part_1(&self, input: &str) -> Answer {
+        part_1(input).into()
+    }
+
+    fn part_2(&self, input: &str) -> Answer {
+        part_2_optimized_but_ugly(input).into()
+    }
+}
+
+fn part_1(input: &str) -> isize {
+    let mut bodies = parse_input(input);
     let steps = 1000;
 
     for _n in 0..steps {
@@ -146,23 +158,24 @@ fn part_1() {
     for body in bodies {
         total_energy += body.energy();
     }
-    println!("{}", total_energy);
-}
-
-fn main() {
-    part_1();
-    part_2_optimized_but_ugly();
+    total_energy
 }
 
-fn part_2_optimized_but_ugly() {
+fn part_2_optimized_but_ugly(input: &str) -> usize {
     // this simulation is independant accross x, y and z axis
     // so we can parallelize these axis on separate threads, and have each child thread exits
     // when it got back into the initial conditions.
     // then, compute the lowest common multiples between these number of steps.
     // (note: we could build a clean solution upon the part 1 code, without even spawning threads, but...)
-    let initial_xs = [-2, 16, 0, 11];
-    let initial_ys = [9, 19, 3, 0];
-    let initial_zs = [-5, 9, 6, 11];
+    let bodies = parse_input(input);
+    let mut initial_xs = [0; 4];
+    let mut initial_ys = [0; 4];
+    let mut initial_zs = [0; 4];
+    for (i, body) in bodies.iter().enumerate() {
+        initial_xs[i] = body.position.x;
+        initial_ys[i] = body.position.y;
+        initial_zs[i] = body.position.z;
+    }
 
     let mut children = Vec::new();
     let child_x = thread::spawn(move || run_simulation_1d(initial_xs));
@@ -172,8 +185,7 @@ fn part_2_optimized_but_ugly() {
     children.push(child_y);
     children.push(child_z);
     let results: Vec<usize> = children.into_iter().map(|t| t.join().unwrap()).collect();
-    let lcm_step = results.into_iter().fold(1, |acc, x| lcm(x, acc));
-    println!("{}", lcm_step);
+    results.into_iter().fold(1, |acc, x| lcm(x, acc))
 }
 
 fn run_simulation_1d(initial_xs: [isize; 4]) -> usize {
@@ -222,3 +234,20 @@ fn gcd(a: usize, b: usize) -> usize {
         min = res;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = include_str!("../input.txt");
+
+    #[test]
+    fn test_part_1_answer() {
+        assert_eq!(Day12.part_1(INPUT), Answer::Integer(12053));
+    }
+
+    #[test]
+    fn test_part_2_answer() {
+        assert_eq!(Day12.part_2(INPUT), Answer::Integer(320380285873116));
+    }
+}
@@ -0,0 +1,46 @@
+use intcode::{Exit, FnOutput, Framed, Io};
+use solution::{Answer, Solution};
+use std::collections::HashMap;
+
+pub struct Day13;
+
+impl Solution for Day13 {
+    fn part_1(&self, input: &str) -> Answer {
+        part_1(input).into()
+    }
+
+    fn part_2(&self, _input: &str) -> Answer {
+        Answer::Unsolved
+    }
+}
+
+fn part_1(input: &str) -> usize {
+    let mut source_interpreter: intcode::Interpreter =
+        intcode::Interpreter::from_code(input).unwrap();
+    let mut screen: HashMap<(intcode::Value, intcode::Value), intcode::Value> = HashMap::new();
+    let screen_device = FnOutput(|frame: &[intcode::Value]| {
+        screen.insert((frame[0], frame[1]), frame[2]);
+    });
+    match source_interpreter.run_with(Io::new((), Framed::new(screen_device, 3))) {
+        Ok(Exit::Halt) => {}
+        result => panic!("unexpected program behaviour: {:?}", result),
+    }
+    screen.values().filter(|v| **v == 2).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = include_str!("../input.txt");
+
+    #[test]
+    fn test_part_1_answer() {
+        assert_eq!(Day13.part_1(INPUT), Answer::Integer(320));
+    }
+
+    #[test]
+    fn test_part_2_unsolved() {
+        assert_eq!(Day13.part_2(INPUT), Answer::Unsolved);
+    }
+}
@@ -0,0 +1,49 @@
+use intcode::{InstructionSet, Interpreter, Observation, Solver, Value};
+use solution::{Answer, Solution};
+
+pub struct Day2;
+
+impl Solution for Day2 {
+    fn part_1(&self, input: &str) -> Answer {
+        run_interpreter_on_code(input, 12, 2).into()
+    }
+
+    fn part_2(&self, input: &str) -> Answer {
+        // the output is linear in the noun and the verb, so it is solved for them directly
+        let mut solver = Solver::from_code(input).unwrap();
+        let noun = solver.symbolic_cell(1, 0..=99);
+        let verb = solver.symbolic_cell(2, 0..=99);
+        let solution = solver
+            .solve(Observation::Memory(0), 19_690_720)
+            .unwrap()
+            .expect("no solution found to part 2");
+        (100 * solution[noun] + solution[verb]).into()
+    }
+}
+
+// this lacks error handling, as in everywhere else in this file...
+fn run_interpreter_on_code(code: &str, noun: Value, verb: Value) -> Value {
+    let mut interpreter: Interpreter = Interpreter::from_code(code).unwrap();
+    interpreter.set_instruction_set(InstructionSet::day_2());
+    interpreter.write_memory(1, noun);
+    interpreter.write_memory(2, verb);
+    interpreter.run_until_block().unwrap();
+    interpreter.read_memory(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = include_str!("../input.txt");
+
+    #[test]
+    fn test_part_1_answer() {
+        assert_eq!(Day2.part_1(INPUT), Answer::Integer(4714701));
+    }
+
+    #[test]
+    fn test_part_2_answer() {
+        assert_eq!(Day2.part_2(INPUT), Answer::Integer(5121));
+    }
+}
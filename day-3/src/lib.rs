@@ -1,43 +1,49 @@
 // this time, let's see what a concise, less readable solution would look like
 // no error handling whatsoever!
 
-use std::fs::File;
-use std::io::{BufReader, BufRead};
+use solution::{Answer, Solution};
 use std::collections::{HashSet, HashMap};
 use std::iter::FromIterator;
 
-fn main() {
-    part_1();
-    part_2();
+pub struct Day3;
+
+impl Solution for Day3 {
+    fn part_1(&self, input: &str) -> Answer {
+        part_1(input).into()
+    }
+
+    fn part_2(&self, input: &str) -> Answer {
+        part_2(input).into()
+    }
 }
 
-fn part_1() {
-    let inputs: Vec<String> = BufReader::new(File::open("input.txt").unwrap()).lines().map(|l| l.unwrap()).collect();
-    let first_points = make_set_of_points(&inputs[0]);
-    let second_points = make_set_of_points(&inputs[1]);
+fn part_1(input: &str) -> isize {
+    let inputs: Vec<&str> = input.lines().collect();
+    let first_points = make_set_of_points(inputs[0]);
+    let second_points = make_set_of_points(inputs[1]);
     let intersections = first_points.intersection(&second_points);
     let mut sorted = intersections.copied().collect::<Vec<(isize, isize)>>();
-    sorted.sort_unstable_by(|a, b| (a.0.abs() + a.1.abs()).cmp(&(b.0.abs() + b.1.abs())));
+    sorted.sort_unstable_by_key(|a| a.0.abs() + a.1.abs());
     let result = sorted[0];
-    println!("{}", result.0.abs() + result.1.abs());
+    result.0.abs() + result.1.abs()
 }
 
-fn part_2() {
-    let inputs: Vec<String> = BufReader::new(File::open("input.txt").unwrap()).lines().map(|l| l.unwrap()).collect();
-    let first_points_with_distance = make_set_of_points_with_length(&inputs[0]);
-    let second_points_with_distance = make_set_of_points_with_length(&inputs[1]);
+fn part_2(input: &str) -> isize {
+    let inputs: Vec<&str> = input.lines().collect();
+    let first_points_with_distance = make_set_of_points_with_length(inputs[0]);
+    let second_points_with_distance = make_set_of_points_with_length(inputs[1]);
 
     let first_points: HashSet<&(isize, isize)> = HashSet::from_iter(first_points_with_distance.keys());
     let second_points: HashSet<&(isize, isize)> = HashSet::from_iter(second_points_with_distance.keys());
     let intersections = first_points.intersection(&second_points);
 
-    let mut min_distance_found_so_far = std::isize::MAX;
+    let mut min_distance_found_so_far = isize::MAX;
     for intersection in intersections {
         let distance = first_points_with_distance.get(intersection).unwrap() + second_points_with_distance.get(intersection).unwrap();
         min_distance_found_so_far = std::cmp::min(distance, min_distance_found_so_far);
     }
 
-    println!("{}", min_distance_found_so_far);
+    min_distance_found_so_far
 }
 
 fn make_set_of_points(input: &str) -> HashSet<(isize, isize)> {
@@ -45,8 +51,8 @@ fn make_set_of_points(input: &str) -> HashSet<(isize, isize)> {
     let mut result = HashSet::new();
     let mut location: (isize, isize) = (0, 0);
     for line in lines {
-        let direction = line.chars().nth(0).unwrap();
-        let mut length = *&line[1..].parse::<isize>().unwrap();
+        let direction = line.chars().next().unwrap();
+        let mut length = line[1..].parse::<isize>().unwrap();
         while length > 0 {
             match direction {
                 'U' => location.0 += 1,
@@ -68,8 +74,8 @@ fn make_set_of_points_with_length(input: &str) -> HashMap<(isize, isize), isize>
     let mut location: (isize, isize) = (0, 0);
     let mut distance = 0;
     for line in lines {
-        let direction = line.chars().nth(0).unwrap();
-        let mut length = *&line[1..].parse::<isize>().unwrap();
+        let direction = line.chars().next().unwrap();
+        let mut length = line[1..].parse::<isize>().unwrap();
         while length > 0 {
             match direction {
                 'U' => location.0 += 1,
@@ -85,3 +91,20 @@ fn make_set_of_points_with_length(input: &str) -> HashMap<(isize, isize), isize>
     }
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = include_str!("../input.txt");
+
+    #[test]
+    fn test_part_1_answer() {
+        assert_eq!(Day3.part_1(INPUT), Answer::Integer(221));
+    }
+
+    #[test]
+    fn test_part_2_answer() {
+        assert_eq!(Day3.part_2(INPUT), Answer::Integer(18542));
+    }
+}
@@ -1,13 +1,37 @@
-fn main() {
-    let password_range = PasswordRange::new(382_345, 843_167);
-    println!("{}", password_range.count());
+use solution::{Answer, Solution};
+
+pub struct Day4;
+
+impl Solution for Day4 {
+    fn part_1(&self, input: &str) -> Answer {
+        let (start, end) = parse_range(input);
+        PasswordRange::new(start, end, is_valid_password_part_1)
+            .count()
+            .into()
+    }
+
+    fn part_2(&self, input: &str) -> Answer {
+        let (start, end) = parse_range(input);
+        PasswordRange::new(start, end, is_valid_password_part_2)
+            .count()
+            .into()
+    }
 }
 
-struct PasswordRange(isize, isize, isize);
+// the puzzle input looks like "382345-843167"
+fn parse_range(input: &str) -> (isize, isize) {
+    let mut bounds = input.trim().split('-');
+    let start = bounds.next().unwrap().parse::<isize>().unwrap();
+    let end = bounds.next().unwrap().parse::<isize>().unwrap();
+    (start, end)
+}
+
+// (end, current, validation rule)
+struct PasswordRange(isize, isize, fn(isize) -> bool);
 
 impl PasswordRange {
-    fn new(start: isize, end: isize) -> Self {
-        Self(start, end, start)
+    fn new(start: isize, end: isize, is_valid_password: fn(isize) -> bool) -> Self {
+        Self(end, start, is_valid_password)
     }
 }
 
@@ -16,26 +40,29 @@ impl Iterator for PasswordRange {
 
     fn next(&mut self) -> Option<Self::Item> {
         loop {
-            let current = self.2;
-            if current > self.1 {
+            let current = self.1;
+            if current > self.0 {
                 return None;
             }
-            self.2 += 1;
-            if is_valid_password(current) {
+            self.1 += 1;
+            if self.2(current) {
                 return Some(current);
             }
         }
     }
 }
 
-fn is_valid_password(password: isize) -> bool {
+fn is_valid_password_part_1(password: isize) -> bool {
     // we could reuse this buffer from one invocation to the next, but it's not like
     // we're writing efficient code here
     let base_10_decomposition = &mut [0; 6];
     make_base_10_decomposition(password, base_10_decomposition);
-    //part 1
-    //has_adjacent_digits(base_10_decomposition) && has_only_increasing_digits(base_10_decomposition)
-    //part 2
+    has_adjacent_digits(base_10_decomposition) && has_only_increasing_digits(base_10_decomposition)
+}
+
+fn is_valid_password_part_2(password: isize) -> bool {
+    let base_10_decomposition = &mut [0; 6];
+    make_base_10_decomposition(password, base_10_decomposition);
     has_one_group_of_exactly_two_adjacent_digits(base_10_decomposition) && has_only_increasing_digits(base_10_decomposition)
 }
 
@@ -78,7 +105,6 @@ fn has_one_group_of_exactly_two_adjacent_digits(digits: &[isize]) -> bool {
     current_run_size == 2
 }
 
-// used only for part 1
 fn has_only_increasing_digits(digits: &[isize]) -> bool {
     // our base10 decomposition has low digits at low indices
     for slice in digits.windows(2) {
@@ -93,6 +119,8 @@ fn has_only_increasing_digits(digits: &[isize]) -> bool {
 mod tests {
     use super::*;
 
+    const INPUT: &str = include_str!("../input.txt");
+
     #[test]
     fn test_make_base_10_decomposition() {
         let buffer = &mut [0 ; 6];
@@ -102,11 +130,20 @@ mod tests {
 
     #[test]
     fn test_has_one_group_of_exactly_two_adjacent_digits() {
-        assert_eq!(has_one_group_of_exactly_two_adjacent_digits(&[1, 1, 2, 2, 3, 3]), true);
-        assert_eq!(has_one_group_of_exactly_two_adjacent_digits(&[1, 2, 3, 4, 4, 4]), false);
-        assert_eq!(has_one_group_of_exactly_two_adjacent_digits(&[1, 1, 1, 1, 2, 2]), true);
-        assert_eq!(has_one_group_of_exactly_two_adjacent_digits(&[1, 1, 3, 4, 5, 6]), true);
-        assert_eq!(has_one_group_of_exactly_two_adjacent_digits(&[3, 4, 5, 6, 1, 1]), true);
+        assert!(has_one_group_of_exactly_two_adjacent_digits(&[1, 1, 2, 2, 3, 3]));
+        assert!(!has_one_group_of_exactly_two_adjacent_digits(&[1, 2, 3, 4, 4, 4]));
+        assert!(has_one_group_of_exactly_two_adjacent_digits(&[1, 1, 1, 1, 2, 2]));
+        assert!(has_one_group_of_exactly_two_adjacent_digits(&[1, 1, 3, 4, 5, 6]));
+        assert!(has_one_group_of_exactly_two_adjacent_digits(&[3, 4, 5, 6, 1, 1]));
+    }
+
+    #[test]
+    fn test_part_1_answer() {
+        assert_eq!(Day4.part_1(INPUT), Answer::Integer(460));
     }
 
+    #[test]
+    fn test_part_2_answer() {
+        assert_eq!(Day4.part_2(INPUT), Answer::Integer(290));
+    }
 }
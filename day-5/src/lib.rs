@@ -0,0 +1,52 @@
+use intcode::{IOAction, InstructionSet, Interpreter, Value};
+use solution::{Answer, Solution};
+
+pub struct Day5;
+
+impl Solution for Day5 {
+    fn part_1(&self, input: &str) -> Answer {
+        run_diagnostic(input, 1).into()
+    }
+
+    fn part_2(&self, input: &str) -> Answer {
+        run_diagnostic(input, 5).into()
+    }
+}
+
+// The diagnostic program outputs one 0 per successful test, followed by the diagnostic code
+fn run_diagnostic(code: &str, system_id: Value) -> Value {
+    let mut interpreter: Interpreter = Interpreter::from_code(code).unwrap();
+    interpreter.set_instruction_set(InstructionSet::day_5());
+    interpreter.queue_input(system_id);
+    let mut diagnostic_code = None;
+    loop {
+        match interpreter.run_until_block().unwrap() {
+            IOAction::Halt => break,
+            IOAction::ProduceOutput(output) => {
+                if let Some(previous_output) = diagnostic_code {
+                    assert_eq!(previous_output, 0, "a diagnostic test failed");
+                }
+                diagnostic_code = Some(output);
+            }
+            _ => panic!("unexpected program behaviour"),
+        }
+    }
+    diagnostic_code.expect("the diagnostic program produced no output")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = include_str!("../input.txt");
+
+    #[test]
+    fn test_part_1_answer() {
+        assert_eq!(Day5.part_1(INPUT), Answer::Integer(4511442));
+    }
+
+    #[test]
+    fn test_part_2_answer() {
+        assert_eq!(Day5.part_2(INPUT), Answer::Integer(12648139));
+    }
+}
@@ -1,22 +1,27 @@
+use solution::{Answer, Solution};
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
 
-fn main() {
-    part_1();
-    part_2();
+pub struct Day6;
+
+impl Solution for Day6 {
+    fn part_1(&self, input: &str) -> Answer {
+        part_1(input).into()
+    }
+
+    fn part_2(&self, input: &str) -> Answer {
+        part_2(input).into()
+    }
 }
 
-fn part_1() {
-    let space_objects = load_tree();
+fn part_1(input: &str) -> usize {
+    let space_objects = load_tree(input);
     let mut distances: SpaceObjectsDistances = HashMap::new();
     set_distance_to_root(0, "COM", &space_objects, &mut distances);
-    let result = compute_total_distance(&distances);
-    println!("{}", result);
+    compute_total_distance(&distances)
 }
 
-fn part_2() {
-    let space_objects = load_tree();
+fn part_2(input: &str) -> usize {
+    let space_objects = load_tree(input);
     let mut distances: SpaceObjectsDistances = HashMap::new();
     set_distance_to_root(0, "COM", &space_objects, &mut distances);
 
@@ -32,10 +37,9 @@ fn part_2() {
         last_ancestor = ancestors.0;
     }
 
-    let result = (distances["SAN"] - distances[last_ancestor])
+    (distances["SAN"] - distances[last_ancestor])
         + (distances["YOU"] - distances[last_ancestor])
-        - 2;
-    println!("{}", result);
+        - 2
 }
 
 fn compute_ancestors<'a>(
@@ -44,14 +48,12 @@ fn compute_ancestors<'a>(
     current_node: &'a str,
 ) -> Option<Vec<&'a str>> {
     if final_node == current_node {
-        let mut result: Vec<&str> = Vec::new();
-        result.push(current_node);
-        return Some(result);
+        return Some(vec![current_node]);
     }
     let children = tree.get(current_node).expect(current_node);
     for child in children {
         if let Some(mut ancestors) = compute_ancestors(final_node, tree, child) {
-            ancestors.push(&current_node);
+            ancestors.push(current_node);
             return Some(ancestors);
         }
     }
@@ -81,14 +83,11 @@ type SpaceObject = String;
 type SpaceObjectsTree = HashMap<SpaceObject, Vec<SpaceObject>>;
 type SpaceObjectsDistances = HashMap<SpaceObject, usize>;
 
-fn load_tree() -> SpaceObjectsTree {
-    let file = File::open("input.txt").unwrap();
-    let reader = BufReader::new(file);
+fn load_tree(input: &str) -> SpaceObjectsTree {
     let mut space_objects: HashMap<SpaceObject, Vec<SpaceObject>> = HashMap::new();
 
-    for line in reader.lines() {
-        let line_unwrapped = line.unwrap();
-        let mut splits = line_unwrapped.split(')');
+    for line in input.lines() {
+        let mut splits = line.split(')');
         let center = splits.next().unwrap();
         let orbiter = splits.next().unwrap();
         assert!(splits.next().is_none());
@@ -98,15 +97,30 @@ fn load_tree() -> SpaceObjectsTree {
                 existing_orbiters.push(orbiter.to_string());
             }
             None => {
-                let mut orbiters: Vec<SpaceObject> = Vec::new();
-                orbiters.push(orbiter.to_string());
-                space_objects.insert(center.to_string(), orbiters);
+                space_objects.insert(center.to_string(), vec![orbiter.to_string()]);
             }
         }
-        if space_objects.get(orbiter).is_none() {
+        if !space_objects.contains_key(orbiter) {
             space_objects.insert(orbiter.to_string(), Vec::new());
         }
     }
 
     space_objects
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = include_str!("../input.txt");
+
+    #[test]
+    fn test_part_1_answer() {
+        assert_eq!(Day6.part_1(INPUT), Answer::Integer(271151));
+    }
+
+    #[test]
+    fn test_part_2_answer() {
+        assert_eq!(Day6.part_2(INPUT), Answer::Integer(388));
+    }
+}
@@ -0,0 +1,73 @@
+use intcode::{Quiescence, Scheduler, Topology};
+// Is this cheating? Maybe, but since https://doc.rust-lang.org/1.1.0/std/slice/struct.Permutations.html is available in unstable...
+use permutohedron::Heap;
+use solution::{Answer, Solution};
+
+pub struct Day7;
+
+impl Solution for Day7 {
+    fn part_1(&self, input: &str) -> Answer {
+        part_1(input).into()
+    }
+
+    fn part_2(&self, input: &str) -> Answer {
+        part_2(input).into()
+    }
+}
+
+fn init_interpreters(
+    source_interpreter: &intcode::Interpreter,
+    inputs: &[intcode::Value],
+) -> Vec<intcode::Interpreter> {
+    let mut interpreters: Vec<intcode::Interpreter> = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        let mut interpreter = source_interpreter.clone();
+        interpreter.queue_input(*input);
+        interpreters.push(interpreter);
+    }
+    interpreters
+}
+
+fn part_1(input: &str) -> intcode::Value {
+    max_thruster_signal(input, vec![0, 1, 2, 3, 4], Topology::Chain)
+}
+
+fn part_2(input: &str) -> intcode::Value {
+    max_thruster_signal(input, vec![5, 6, 7, 8, 9], Topology::Ring)
+}
+
+// The thruster signal is the last output of the last amplifier
+fn max_thruster_signal(
+    input: &str,
+    mut possible_inputs: Vec<intcode::Value>,
+    topology: Topology,
+) -> intcode::Value {
+    let source_interpreter: intcode::Interpreter = intcode::Interpreter::from_code(input).unwrap();
+    let heap = Heap::new(&mut possible_inputs);
+    let mut max_output = intcode::Value::MIN;
+    for inputs in heap {
+        let mut interpreters = init_interpreters(&source_interpreter, &inputs);
+        interpreters[0].queue_input(0);
+        let mut amplifiers = Scheduler::new(interpreters, topology);
+        assert_eq!(amplifiers.run().unwrap(), Quiescence::Halted);
+        max_output = std::cmp::max(*amplifiers.outputs().last().unwrap(), max_output);
+    }
+    max_output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = include_str!("../input.txt");
+
+    #[test]
+    fn test_part_1_answer() {
+        assert_eq!(Day7.part_1(INPUT), Answer::Integer(366376));
+    }
+
+    #[test]
+    fn test_part_2_answer() {
+        assert_eq!(Day7.part_2(INPUT), Answer::Integer(21596786));
+    }
+}
@@ -1,19 +1,19 @@
-use std::io::Read;
-use std::fs::File;
+use solution::{Answer, Solution};
 
-fn main() {
-    part_1();
-    part_2();
-}
+pub struct Day8;
 
+impl Solution for Day8 {
+    fn part_1(&self, input: &str) -> Answer {
+        part_1(input).into()
+    }
 
-fn part_1() {
-    let filename = "input.txt";
-    let mut file = File::open(filename).unwrap();
-    let mut buffer = String::new();
-    file.read_to_string(&mut buffer).unwrap();
+    fn part_2(&self, input: &str) -> Answer {
+        part_2(input).into()
+    }
+}
 
-    let mut min_number_of_0s = std::usize::MAX;
+fn part_1(buffer: &str) -> u32 {
+    let mut min_number_of_0s = usize::MAX;
     let mut checksum = 0;
     let mut number_of_1s = 0;
     let mut number_of_2s = 0;
@@ -46,15 +46,10 @@ fn part_1() {
         }
     }
 
-    println!("{}", checksum);
+    checksum
 }
 
-fn part_2() {
-    let filename = "input.txt";
-    let mut file = File::open(filename).unwrap();
-    let mut buffer = String::new();
-    file.read_to_string(&mut buffer).unwrap();
-
+fn part_2(buffer: &str) -> String {
     let mut layer_position = 0;
 
     const LAYER_SIZE: usize = 25 * 6;
@@ -75,15 +70,41 @@ fn part_2() {
         }
     }
 
+    let mut image = String::with_capacity(LAYER_SIZE + 6);
     for i in 0..6 {
         for digit in &colors[(i* 25)..((i+1)*25)] {
             if digit == &1 {
-                print!("{}", digit);
+                image.push('1');
             } else {
-                print!(" ");
+                image.push(' ');
             }
         }
-        println!("");
+        image.push('\n');
     }
+    image
+}
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = include_str!("../input.txt");
+
+    #[test]
+    fn test_part_1_answer() {
+        assert_eq!(Day8.part_1(INPUT), Answer::Integer(1792));
+    }
+
+    #[test]
+    fn test_part_2_answer() {
+        let expected = concat!(
+            "1      11 1111  11  1  1 \n",
+            "1       1 1    1  1 1  1 \n",
+            "1       1 111  1    1111 \n",
+            "1       1 1    1    1  1 \n",
+            "1    1  1 1    1  1 1  1 \n",
+            "1111  11  1111  11  1  1 \n",
+        );
+        assert_eq!(Day8.part_2(INPUT), Answer::Text(String::from(expected)));
+    }
 }
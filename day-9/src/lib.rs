@@ -0,0 +1,56 @@
+use intcode::{IOAction, Interpreter, Value, Word};
+use solution::{Answer, Solution};
+
+pub struct Day9;
+
+impl Solution for Day9 {
+    fn part_1(&self, input: &str) -> Answer {
+        run_boost::<Value>(input, 1).into()
+    }
+
+    fn part_2(&self, input: &str) -> Answer {
+        run_boost::<Value>(input, 2).into()
+    }
+}
+
+// In test mode, BOOST outputs the opcodes it thinks are malfunctioning before the BOOST keycode,
+// so if everything works as expected we get a single output
+fn run_boost<W: Word>(code: &str, mode: i64) -> W {
+    let mut interpreter = Interpreter::<W>::from_code(code).unwrap();
+    interpreter.queue_input(W::from_i64(mode));
+    let mut outputs = Vec::new();
+    loop {
+        match interpreter.run_until_block().unwrap() {
+            IOAction::Halt => break,
+            IOAction::ProduceOutput(output) => outputs.push(output),
+            _ => panic!("unexpected program behaviour"),
+        }
+    }
+    assert_eq!(outputs.len(), 1, "malfunctioning opcodes: {:?}", outputs);
+    outputs.remove(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use intcode::BigInt;
+
+    const INPUT: &str = include_str!("../input.txt");
+
+    #[test]
+    fn test_part_1_answer() {
+        assert_eq!(Day9.part_1(INPUT), Answer::Integer(2890527621));
+    }
+
+    #[test]
+    fn test_part_2_answer() {
+        assert_eq!(Day9.part_2(INPUT), Answer::Integer(66772));
+    }
+
+    #[test]
+    fn test_boost_self_test_with_every_word_type() {
+        assert_eq!(run_boost::<i64>(INPUT, 1), 2890527621);
+        assert_eq!(run_boost::<i128>(INPUT, 1), 2890527621);
+        assert_eq!(run_boost::<BigInt>(INPUT, 1), BigInt::from(2890527621u64));
+    }
+}
@@ -0,0 +1,180 @@
+// Runs days 9, 13 and 7 on the interpreter as it was first shared by the days (the baseline),
+// on the interpreter with and without the decode cache, and compiled:
+// cargo bench -p intcode
+mod baseline;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use intcode::{CompiledInterpreter, IOAction, Interpreter, Value};
+
+const DAY_9: &str = include_str!("../../day-9/input.txt");
+const DAY_13: &str = include_str!("../../day-13/input.txt");
+const DAY_7: &str = include_str!("../../day-7/input.txt");
+
+// what the benchmarks need from every interpreter
+trait Machine: Clone {
+    fn queue_input(&mut self, input: Value);
+    fn run_until_block(&mut self) -> IOAction;
+}
+
+impl Machine for baseline::Interpreter {
+    fn queue_input(&mut self, input: Value) {
+        baseline::Interpreter::queue_input(self, input as baseline::Value)
+    }
+
+    fn run_until_block(&mut self) -> IOAction {
+        match baseline::Interpreter::run_until_block(self).unwrap() {
+            baseline::IOAction::ReadInput => IOAction::ReadInput,
+            baseline::IOAction::ProduceOutput(output) => IOAction::ProduceOutput(output as Value),
+            baseline::IOAction::Halt => IOAction::Halt,
+        }
+    }
+}
+
+impl Machine for Interpreter {
+    fn queue_input(&mut self, input: Value) {
+        Interpreter::queue_input(self, input)
+    }
+
+    fn run_until_block(&mut self) -> IOAction {
+        Interpreter::run_until_block(self).unwrap()
+    }
+}
+
+impl Machine for CompiledInterpreter {
+    fn queue_input(&mut self, input: Value) {
+        CompiledInterpreter::queue_input(self, input)
+    }
+
+    fn run_until_block(&mut self) -> IOAction {
+        CompiledInterpreter::run_until_block(self).unwrap()
+    }
+}
+
+// The machines to compare, running `code` once `writes` are done. The compiled one is compiled
+// once and cloned, so that its blocks are only compiled on the first run.
+struct Machines {
+    baseline: baseline::Interpreter,
+    cached: Interpreter,
+    uncached: Interpreter,
+    compiled: CompiledInterpreter,
+}
+
+impl Machines {
+    fn new(code: &str, writes: &[(intcode::Address, Value)]) -> Machines {
+        let mut baseline = baseline::Interpreter::from_code(code).unwrap();
+        let mut cached: Interpreter = Interpreter::from_code(code).unwrap();
+        for (address, value) in writes {
+            baseline.write_memory(*address, *value as baseline::Value);
+            cached.write_memory(*address, *value);
+        }
+        let mut uncached = cached.clone();
+        uncached.set_decode_cache(false);
+        let compiled = cached.clone().compile();
+        Machines {
+            baseline,
+            cached,
+            uncached,
+            compiled,
+        }
+    }
+}
+
+// Benchmarks a workload on each machine, once they all gave the expected result
+macro_rules! bench_machines {
+    ($c:expr, $name:expr, $machines:expr, $workload:ident, $expected:expr) => {{
+        let machines = $machines;
+        assert_eq!($workload(&machines.baseline), $expected);
+        assert_eq!($workload(&machines.cached), $expected);
+        assert_eq!($workload(&machines.uncached), $expected);
+        assert_eq!($workload(&machines.compiled), $expected);
+        let mut group = $c.benchmark_group($name);
+        group.sample_size(10);
+        group.bench_function("baseline", |b| b.iter(|| $workload(&machines.baseline)));
+        group.bench_function("decode cache", |b| b.iter(|| $workload(&machines.cached)));
+        group.bench_function("no decode cache", |b| {
+            b.iter(|| $workload(&machines.uncached))
+        });
+        group.bench_function("compiled", |b| b.iter(|| $workload(&machines.compiled)));
+        group.finish();
+    }};
+}
+
+// the BOOST keycode of day 9 part 2
+fn boost<M: Machine>(source: &M) -> Value {
+    let mut machine = source.clone();
+    machine.queue_input(2);
+    match machine.run_until_block() {
+        IOAction::ProduceOutput(output) => output,
+        action => panic!("unexpected {:?}", action),
+    }
+}
+
+// plays the game of day 13 with quarters and the joystick at rest, until the ball is lost,
+// and counts the outputs
+fn arcade<M: Machine>(source: &M) -> usize {
+    let mut machine = source.clone();
+    let mut outputs = 0;
+    loop {
+        match machine.run_until_block() {
+            IOAction::ReadInput => machine.queue_input(0),
+            IOAction::ProduceOutput(_) => outputs += 1,
+            _ => return outputs,
+        }
+    }
+}
+
+// every order of `values`
+fn permutations(values: &[Value]) -> Vec<Vec<Value>> {
+    if values.len() <= 1 {
+        return vec![values.to_vec()];
+    }
+    let mut permutations = Vec::new();
+    for (index, first) in values.iter().enumerate() {
+        let mut rest = values.to_vec();
+        rest.remove(index);
+        for mut permutation in self::permutations(&rest) {
+            permutation.insert(0, *first);
+            permutations.push(permutation);
+        }
+    }
+    permutations
+}
+
+// the best thruster signal of day 7 part 2, over every phase setting of the feedback loop
+fn sweep<M: Machine>(source: &M) -> Value {
+    let mut best = Value::MIN;
+    for phases in permutations(&[5, 6, 7, 8, 9]) {
+        let mut amplifiers: Vec<M> = phases
+            .iter()
+            .map(|phase| {
+                let mut amplifier = source.clone();
+                amplifier.queue_input(*phase);
+                amplifier
+            })
+            .collect();
+        let mut signal = 0;
+        'feedback: loop {
+            for amplifier in &mut amplifiers {
+                amplifier.queue_input(signal);
+                match amplifier.run_until_block() {
+                    IOAction::ProduceOutput(output) => signal = output,
+                    _ => break 'feedback,
+                }
+            }
+        }
+        best = best.max(signal);
+    }
+    best
+}
+
+fn bench_interpreter(c: &mut Criterion) {
+    bench_machines!(c, "day-9 boost", Machines::new(DAY_9, &[]), boost, 66_772);
+    // two quarters
+    let machines = Machines::new(DAY_13, &[(0, 2)]);
+    bench_machines!(c, "day-13 arcade", machines, arcade, 2_466);
+    let machines = Machines::new(DAY_7, &[]);
+    bench_machines!(c, "day-7 permutation sweep", machines, sweep, 21_596_786);
+}
+
+criterion_group!(benches, bench_interpreter);
+criterion_main!(benches);
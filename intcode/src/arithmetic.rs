@@ -0,0 +1,83 @@
+use crate::error::ExecutionErrorKind;
+use crate::word::Word;
+
+/// How the interpreter handles additions and multiplications whose result does not fit in a
+/// word. Without this, overflows panic in debug builds and silently wrap in release builds.
+/// For arbitrary precision, run the interpreter with `BigInt` words instead (see `Word`): they
+/// never overflow, so the mode has no effect on them.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum ArithmeticMode {
+    /// Stop the program with an `ArithmeticOverflow` error
+    #[default]
+    Checked,
+    /// Two's complement wrap around, whatever the build profile
+    Wrapping,
+    /// Clamp the result to the smallest or largest word
+    Saturating,
+}
+
+impl ArithmeticMode {
+    pub(crate) fn add<W: Word>(self, left: &W, right: &W) -> Result<W, ExecutionErrorKind<W>> {
+        match self {
+            ArithmeticMode::Checked => left
+                .checked_add(right)
+                .ok_or(ExecutionErrorKind::ArithmeticOverflow),
+            ArithmeticMode::Wrapping => Ok(left.wrapping_add(right)),
+            ArithmeticMode::Saturating => Ok(left.saturating_add(right)),
+        }
+    }
+
+    pub(crate) fn mul<W: Word>(self, left: &W, right: &W) -> Result<W, ExecutionErrorKind<W>> {
+        match self {
+            ArithmeticMode::Checked => left
+                .checked_mul(right)
+                .ok_or(ExecutionErrorKind::ArithmeticOverflow),
+            ArithmeticMode::Wrapping => Ok(left.wrapping_mul(right)),
+            ArithmeticMode::Saturating => Ok(left.saturating_mul(right)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Value;
+
+    #[test]
+    fn test_add() {
+        assert_eq!(ArithmeticMode::Checked.add::<Value>(&2, &3), Ok(5));
+        assert_eq!(
+            ArithmeticMode::Checked.add::<Value>(&Value::MAX, &1),
+            Err(ExecutionErrorKind::ArithmeticOverflow)
+        );
+        assert_eq!(
+            ArithmeticMode::Wrapping.add::<Value>(&Value::MAX, &1),
+            Ok(Value::MIN)
+        );
+        assert_eq!(
+            ArithmeticMode::Saturating.add::<Value>(&Value::MAX, &1),
+            Ok(Value::MAX)
+        );
+        assert_eq!(
+            ArithmeticMode::Saturating.add::<Value>(&Value::MIN, &-1),
+            Ok(Value::MIN)
+        );
+    }
+
+    #[test]
+    fn test_mul() {
+        assert_eq!(ArithmeticMode::Checked.mul::<Value>(&-2, &3), Ok(-6));
+        assert_eq!(
+            ArithmeticMode::Checked.mul::<Value>(&Value::MAX, &2),
+            Err(ExecutionErrorKind::ArithmeticOverflow)
+        );
+        assert_eq!(
+            ArithmeticMode::Wrapping.mul::<Value>(&Value::MAX, &2),
+            Ok(-2)
+        );
+        assert_eq!(
+            ArithmeticMode::Saturating.mul::<Value>(&Value::MAX, &-2),
+            Ok(Value::MIN)
+        );
+    }
+}
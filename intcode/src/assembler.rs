@@ -0,0 +1,391 @@
+use crate::instruction_set::BUILTIN_OPCODES;
+use crate::interpreter::{opcode_number, parameter_count, written_parameter, Address, Opcode};
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+
+/// What is wrong with a line of assembly.
+#[non_exhaustive]
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum AssemblyErrorKind {
+    UnknownMnemonic(String),
+    WrongOperandCount { expected: usize, found: usize },
+    InvalidOperand(String),
+    WriteInImmediateMode,
+    UndefinedLabel(String),
+    DuplicateLabel(String),
+    // a numeric label (as in disassembler listings) that is not the actual address of the line
+    AddressMismatch { expected: Address, found: Address },
+}
+
+impl fmt::Display for AssemblyErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AssemblyErrorKind::UnknownMnemonic(name) => write!(f, "unknown mnemonic {}", name),
+            AssemblyErrorKind::WrongOperandCount { expected, found } => {
+                write!(f, "expected {} operands, found {}", expected, found)
+            }
+            AssemblyErrorKind::InvalidOperand(operand) => {
+                write!(f, "invalid operand {}", operand)
+            }
+            AssemblyErrorKind::WriteInImmediateMode => {
+                write!(f, "the written operand can not be immediate")
+            }
+            AssemblyErrorKind::UndefinedLabel(label) => write!(f, "undefined label {}", label),
+            AssemblyErrorKind::DuplicateLabel(label) => {
+                write!(f, "label {} is defined twice", label)
+            }
+            AssemblyErrorKind::AddressMismatch { expected, found } => write!(
+                f,
+                "line is at address {}, but is labelled {}",
+                expected, found
+            ),
+        }
+    }
+}
+
+/// A line of assembly that could not be assembled.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct AssemblyError {
+    kind: AssemblyErrorKind,
+    // 1-based, like in text editors
+    line: usize,
+}
+
+impl AssemblyError {
+    pub fn kind(&self) -> &AssemblyErrorKind {
+        &self.kind
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+}
+
+impl fmt::Display for AssemblyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.kind)
+    }
+}
+
+impl error::Error for AssemblyError {}
+
+// An integer literal, kept as text so that it fits any word type, or a label
+#[derive(Debug, Clone)]
+enum Operand {
+    Literal(String),
+    Label(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    Position = 0,
+    Immediate = 1,
+    Relative = 2,
+}
+
+#[derive(Debug)]
+enum Statement {
+    Instruction {
+        opcode: Opcode,
+        operands: Vec<(Mode, Operand)>,
+    },
+    Data(Vec<Operand>),
+}
+
+/// Assembles a program into comma-separated Intcode, as accepted by `Interpreter::from_code`.
+///
+/// Every line holds an optional label (`loop:`), then either an instruction or a `.data`
+/// directive, then an optional `;` comment:
+///
+/// ```text
+/// loop:   add [pos counter], #1, [rel -3]   ; position, immediate and relative operands
+///         jt #1, #loop
+/// counter: .data 0, -12
+/// ```
+///
+/// Mnemonics are case insensitive, and numeric labels must match the address of their line, so
+/// that disassembler listings assemble back to the original program.
+pub fn assemble(source: &str) -> Result<String, AssemblyError> {
+    let mut labels = HashMap::new();
+    let mut statements = Vec::new();
+    let mut address = 0;
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let at_line = |kind| AssemblyError {
+            kind,
+            line: line_number,
+        };
+        let (line_labels, statement) = parse_line(line).map_err(at_line)?;
+        for label in line_labels {
+            if let Ok(found) = label.parse::<Address>() {
+                if found != address {
+                    return Err(at_line(AssemblyErrorKind::AddressMismatch {
+                        expected: address,
+                        found,
+                    }));
+                }
+            } else if labels.insert(label.clone(), address).is_some() {
+                return Err(at_line(AssemblyErrorKind::DuplicateLabel(label)));
+            }
+        }
+        if let Some(statement) = statement {
+            address += statement_length(&statement);
+            statements.push((line_number, statement));
+        }
+    }
+
+    let mut words = Vec::with_capacity(address);
+    for (line_number, statement) in statements {
+        let resolve = |value: &Operand| match value {
+            Operand::Literal(literal) => Ok(literal.clone()),
+            Operand::Label(label) => match labels.get(label) {
+                Some(address) => Ok(address.to_string()),
+                None => Err(AssemblyError {
+                    kind: AssemblyErrorKind::UndefinedLabel(label.clone()),
+                    line: line_number,
+                }),
+            },
+        };
+        match statement {
+            Statement::Instruction { opcode, operands } => {
+                let mut instruction = opcode_number(&opcode);
+                let mut mode_factor = 100;
+                for (mode, _) in &operands {
+                    instruction += *mode as i64 * mode_factor;
+                    mode_factor *= 10;
+                }
+                words.push(instruction.to_string());
+                for (_, value) in &operands {
+                    words.push(resolve(value)?);
+                }
+            }
+            Statement::Data(values) => {
+                for value in &values {
+                    words.push(resolve(value)?);
+                }
+            }
+        }
+    }
+    Ok(words.join(","))
+}
+
+fn statement_length(statement: &Statement) -> usize {
+    match statement {
+        Statement::Instruction { operands, .. } => operands.len() + 1,
+        Statement::Data(values) => values.len(),
+    }
+}
+
+fn parse_line(line: &str) -> Result<(Vec<String>, Option<Statement>), AssemblyErrorKind> {
+    let mut rest = match line.find(';') {
+        Some(comment_start) => &line[..comment_start],
+        None => line,
+    }
+    .trim();
+
+    let mut labels = Vec::new();
+    while let Some(colon) = rest.find(':') {
+        let label = rest[..colon].trim();
+        if !is_label(label) && label.parse::<Address>().is_err() {
+            break;
+        }
+        labels.push(label.to_string());
+        rest = rest[colon + 1..].trim();
+    }
+    if rest.is_empty() {
+        return Ok((labels, None));
+    }
+
+    let (name, operands) = match rest.find(char::is_whitespace) {
+        Some(space) => (&rest[..space], rest[space..].trim()),
+        None => (rest, ""),
+    };
+    let operands: Vec<&str> = if operands.is_empty() {
+        Vec::new()
+    } else {
+        operands.split(',').map(str::trim).collect()
+    };
+
+    let name = name.to_lowercase();
+    if name == ".data" || name == "data" {
+        let values = operands
+            .iter()
+            .map(|operand| parse_value(operand))
+            .collect::<Result<_, _>>()?;
+        return Ok((labels, Some(Statement::Data(values))));
+    }
+
+    let opcode = &BUILTIN_OPCODES
+        .iter()
+        .find(|builtin| builtin.mnemonic == name)
+        .ok_or(AssemblyErrorKind::UnknownMnemonic(name))?
+        .opcode;
+    let expected = parameter_count(opcode);
+    if operands.len() != expected {
+        return Err(AssemblyErrorKind::WrongOperandCount {
+            expected,
+            found: operands.len(),
+        });
+    }
+    let operands: Vec<(Mode, Operand)> = operands
+        .iter()
+        .map(|operand| parse_operand(operand))
+        .collect::<Result<_, _>>()?;
+    if let Some(written) = written_parameter(opcode) {
+        if operands[written].0 == Mode::Immediate {
+            return Err(AssemblyErrorKind::WriteInImmediateMode);
+        }
+    }
+    let statement = Statement::Instruction {
+        opcode: *opcode,
+        operands,
+    };
+    Ok((labels, Some(statement)))
+}
+
+// `[pos x]`, `#x` or `[rel x]`
+fn parse_operand(operand: &str) -> Result<(Mode, Operand), AssemblyErrorKind> {
+    let invalid = || AssemblyErrorKind::InvalidOperand(operand.to_string());
+    if let Some(value) = operand.strip_prefix('#') {
+        return Ok((Mode::Immediate, parse_value(value)?));
+    }
+    let inner = operand
+        .strip_prefix('[')
+        .and_then(|operand| operand.strip_suffix(']'))
+        .ok_or_else(invalid)?
+        .trim();
+    let (mode, value) = match inner.find(char::is_whitespace) {
+        Some(space) => (&inner[..space], inner[space..].trim()),
+        None => return Err(invalid()),
+    };
+    match mode {
+        "pos" => Ok((Mode::Position, parse_value(value)?)),
+        "rel" => Ok((Mode::Relative, parse_value(value)?)),
+        _ => Err(invalid()),
+    }
+}
+
+fn parse_value(value: &str) -> Result<Operand, AssemblyErrorKind> {
+    let digits = value.strip_prefix('-').unwrap_or(value);
+    if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) {
+        Ok(Operand::Literal(value.to_string()))
+    } else if is_label(value) {
+        Ok(Operand::Label(value.to_string()))
+    } else {
+        Err(AssemblyErrorKind::InvalidOperand(value.to_string()))
+    }
+}
+
+fn is_label(name: &str) -> bool {
+    match name.chars().next() {
+        Some(first) if first.is_ascii_alphabetic() || first == '_' => {
+            name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disassembler::disassemble;
+    use crate::interpreter::{parse_code, IOAction, Interpreter, Value};
+
+    #[test]
+    fn test_assemble() {
+        let source = "
+            ; adds 10 to a counter until it reaches 30
+                    arb #flag
+            start:  add [pos counter], #10, [pos counter]
+                    LT [pos counter], #30, [rel 0]
+                    jt [rel 0], #start
+                    out [pos counter]
+                    hlt
+            counter: .data 0, -1
+            flag:   .data 0
+        ";
+        let code = assemble(source).unwrap();
+        assert_eq!(
+            code,
+            "109,18,1001,16,10,16,21007,16,30,0,1205,0,2,4,16,99,0,-1,0"
+        );
+        let mut interpreter: Interpreter = Interpreter::from_code(&code).unwrap();
+        assert_eq!(
+            interpreter.run_until_block().unwrap(),
+            IOAction::ProduceOutput(30)
+        );
+    }
+
+    #[test]
+    fn test_assemble_quine() {
+        // same program as `test_interpreter_run_quine`
+        let source = "
+            loop:   arb #1
+                    out [rel -1]
+                    add [pos 100], #1, [pos 100]
+                    eq [pos 100], #16, [pos 101]
+                    jf [pos 101], #loop
+                    hlt
+        ";
+        assert_eq!(
+            assemble(source).unwrap(),
+            "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99"
+        );
+    }
+
+    #[test]
+    fn test_round_trip_with_disassembler() {
+        let code = "21001,9,10,-3,109,1,1,4,11,12,99,7,0";
+        let program: Vec<Value> = parse_code(code).unwrap();
+        let listing = disassemble(&program).to_string();
+        assert_eq!(assemble(&listing).unwrap(), code);
+    }
+
+    #[test]
+    fn test_assembly_errors() {
+        assert_assembly_error(
+            "hlt\nmov [pos 1], [pos 2]",
+            2,
+            AssemblyErrorKind::UnknownMnemonic(String::from("mov")),
+        );
+        assert_assembly_error(
+            "\n\nadd #1, #2",
+            3,
+            AssemblyErrorKind::WrongOperandCount {
+                expected: 3,
+                found: 2,
+            },
+        );
+        assert_assembly_error(
+            "out [abs 3]",
+            1,
+            AssemblyErrorKind::InvalidOperand(String::from("[abs 3]")),
+        );
+        assert_assembly_error("in #3", 1, AssemblyErrorKind::WriteInImmediateMode);
+        assert_assembly_error(
+            "jt #1, #nowhere",
+            1,
+            AssemblyErrorKind::UndefinedLabel(String::from("nowhere")),
+        );
+        assert_assembly_error(
+            "a: hlt\na: hlt",
+            2,
+            AssemblyErrorKind::DuplicateLabel(String::from("a")),
+        );
+        assert_assembly_error(
+            "0: out #1\n1: hlt",
+            2,
+            AssemblyErrorKind::AddressMismatch {
+                expected: 2,
+                found: 1,
+            },
+        );
+    }
+
+    fn assert_assembly_error(source: &str, expected_line: usize, expected_kind: AssemblyErrorKind) {
+        let error = assemble(source).unwrap_err();
+        assert_eq!(error.line(), expected_line);
+        assert_eq!(error.kind(), &expected_kind);
+    }
+}
@@ -0,0 +1,935 @@
+use crate::arithmetic::ArithmeticMode;
+use crate::device::{self, InputDevice, OutputDevice};
+use crate::error::{ExecutionError, ExecutionErrorKind};
+use crate::interpreter::{
+    instruction_length, to_address, written_parameter, Address, BudgetedRun, IOAction,
+    InstructionMode, Interpreter, Memory, Opcode, Value,
+};
+use crate::memory::{DenseMemory, MemoryBackend};
+use crate::word::Word;
+use std::sync::{Arc, OnceLock};
+
+// same bound as the decode cache: code further away runs on the interpreter
+const MAX_COMPILED_ADDRESS: Address = 1 << 20;
+
+// An instruction that does not move the instruction pointer, compiled into a closure which
+// returns the address written, if any
+type Operation<W, M> = Box<
+    dyn Fn(&mut Memory<W, M>, ArithmeticMode) -> Result<Option<Address>, ExecutionErrorKind<W>>
+        + Send
+        + Sync,
+>;
+
+// An input, which writes the value and returns its address
+type Input<W, M> =
+    Box<dyn Fn(&mut Memory<W, M>, W) -> Result<Address, ExecutionErrorKind<W>> + Send + Sync>;
+
+// A conditional jump, which returns its target when it is taken
+type Jump<W, M> =
+    Box<dyn Fn(&mut Memory<W, M>) -> Result<Option<Address>, ExecutionErrorKind<W>> + Send + Sync>;
+
+// An output, which returns the value
+type Output<W, M> =
+    Box<dyn Fn(&mut Memory<W, M>) -> Result<W, ExecutionErrorKind<W>> + Send + Sync>;
+
+// A parameter read by an instruction. Each mode is a type of its own, so that the closure of an
+// instruction is specialized for the modes of its parameters.
+trait Operand<W: Word>: Send + Sync + 'static {
+    fn read<M: MemoryBackend<W>>(
+        &self,
+        memory: &mut Memory<W, M>,
+    ) -> Result<W, ExecutionErrorKind<W>>;
+}
+
+// A parameter written by an instruction
+trait Destination<W: Word>: Send + Sync + 'static {
+    fn address<M: MemoryBackend<W>>(
+        &self,
+        memory: &Memory<W, M>,
+    ) -> Result<Address, ExecutionErrorKind<W>>;
+}
+
+struct Immediate<W>(W);
+
+struct Position(Address);
+
+struct Relative<W>(W);
+
+// A parameter the program overwrites, read from memory when the instruction runs
+struct Parameter {
+    address: Address,
+    mode: InstructionMode,
+}
+
+impl<W: Word + Send + Sync + 'static> Operand<W> for Immediate<W> {
+    #[inline]
+    fn read<M: MemoryBackend<W>>(&self, _: &mut Memory<W, M>) -> Result<W, ExecutionErrorKind<W>> {
+        Ok(self.0.clone())
+    }
+}
+
+impl<W: Word> Operand<W> for Position {
+    #[inline]
+    fn read<M: MemoryBackend<W>>(
+        &self,
+        memory: &mut Memory<W, M>,
+    ) -> Result<W, ExecutionErrorKind<W>> {
+        memory.load(self.0)
+    }
+}
+
+impl<W: Word + Send + Sync + 'static> Operand<W> for Relative<W> {
+    #[inline]
+    fn read<M: MemoryBackend<W>>(
+        &self,
+        memory: &mut Memory<W, M>,
+    ) -> Result<W, ExecutionErrorKind<W>> {
+        let address = memory.relative_address(&self.0)?;
+        memory.load(address)
+    }
+}
+
+impl<W: Word> Destination<W> for Position {
+    #[inline]
+    fn address<M: MemoryBackend<W>>(
+        &self,
+        _: &Memory<W, M>,
+    ) -> Result<Address, ExecutionErrorKind<W>> {
+        Ok(self.0)
+    }
+}
+
+impl<W: Word + Send + Sync + 'static> Destination<W> for Relative<W> {
+    #[inline]
+    fn address<M: MemoryBackend<W>>(
+        &self,
+        memory: &Memory<W, M>,
+    ) -> Result<Address, ExecutionErrorKind<W>> {
+        memory.relative_address(&self.0)
+    }
+}
+
+impl<W: Word> Operand<W> for Parameter {
+    fn read<M: MemoryBackend<W>>(
+        &self,
+        memory: &mut Memory<W, M>,
+    ) -> Result<W, ExecutionErrorKind<W>> {
+        let parameter = memory.get(self.address);
+        memory.read_value(&parameter, &self.mode)
+    }
+}
+
+impl<W: Word> Destination<W> for Parameter {
+    fn address<M: MemoryBackend<W>>(
+        &self,
+        memory: &Memory<W, M>,
+    ) -> Result<Address, ExecutionErrorKind<W>> {
+        let parameter = memory.get(self.address);
+        match self.mode {
+            InstructionMode::Relative => memory.relative_address(&parameter),
+            _ => to_address(&parameter),
+        }
+    }
+}
+
+// An instruction writing `compute(mode, left, right)` to `result`
+fn binary<W, M, L, R, D, F>(left: L, right: R, result: D, compute: F) -> Operation<W, M>
+where
+    W: Word,
+    M: MemoryBackend<W>,
+    L: Operand<W>,
+    R: Operand<W>,
+    D: Destination<W>,
+    F: Fn(ArithmeticMode, W, W) -> Result<W, ExecutionErrorKind<W>> + Send + Sync + 'static,
+{
+    Box::new(move |memory, mode| {
+        let value = compute(mode, left.read(memory)?, right.read(memory)?)?;
+        let address = result.address(memory)?;
+        memory.store(address, value)?;
+        Ok(Some(address))
+    })
+}
+
+fn adjust_relative_base<W: Word, M: MemoryBackend<W>>(offset: impl Operand<W>) -> Operation<W, M> {
+    Box::new(move |memory, _| {
+        let offset = offset.read(memory)?;
+        memory.adjust_relative_base(&offset)?;
+        Ok(None)
+    })
+}
+
+fn input<W: Word, M: MemoryBackend<W>>(destination: impl Destination<W>) -> Input<W, M> {
+    Box::new(move |memory, value| {
+        let address = destination.address(memory)?;
+        memory.store(address, value)?;
+        Ok(address)
+    })
+}
+
+// taken when the condition is not zero, or when it is zero and `jump_if` is false
+fn jump<W: Word, M: MemoryBackend<W>>(
+    condition: impl Operand<W>,
+    destination: impl Operand<W>,
+    jump_if: bool,
+) -> Jump<W, M> {
+    Box::new(move |memory| {
+        if condition.read(memory)?.is_zero() == jump_if {
+            return Ok(None);
+        }
+        Ok(Some(to_address(&destination.read(memory)?)?))
+    })
+}
+
+fn output<W: Word, M: MemoryBackend<W>>(value: impl Operand<W>) -> Output<W, M> {
+    Box::new(move |memory| value.read(memory))
+}
+
+enum Compiled<W, M> {
+    Operation(Operation<W, M>),
+    Input(Input<W, M>),
+    Exit(Exit<W, M>),
+}
+
+// A compiled instruction, with what its block needs to know about it
+struct Instruction<W, M> {
+    compiled: Compiled<W, M>,
+    length: usize,
+    // whether the parameters are read when the instruction runs
+    dynamic: bool,
+    // the address the instruction writes to, when it is known at compile time
+    write: Option<Address>,
+}
+
+// How a block ends, besides running into an instruction that is not compiled
+enum Exit<W, M> {
+    Jump(Jump<W, M>),
+    Output(Output<W, M>),
+    Halt,
+}
+
+// The instructions from `start` up to the first one that is not compiled, or up to a jump, an
+// output or a halt ending the block. Execution continues at `end` unless a jump is taken. A
+// block may start with an input, which only runs from the block when the input queue is not
+// empty.
+struct Block<W, M> {
+    start: Address,
+    input: Option<Input<W, M>>,
+    // each operation with its address
+    operations: Vec<(Address, Operation<W, M>)>,
+    exit: Option<(Address, Exit<W, M>)>,
+    end: Address,
+    // the number of instructions the block executes
+    len: u64,
+    // the parameters read when the block runs, which the program may overwrite
+    dynamic: Vec<Address>,
+}
+
+impl<W, M> Block<W, M> {
+    // whether the block has to be left once `address` was written
+    #[inline]
+    fn compiled_from(&self, address: Address) -> bool {
+        (self.start..self.end).contains(&address) && !self.dynamic.contains(&address)
+    }
+
+    // the address of the instruction after operation `index`
+    fn after(&self, index: usize) -> Address {
+        match (self.operations.get(index + 1), &self.exit) {
+            (Some((address, _)), _) => *address,
+            (None, Some((address, _))) => *address,
+            (None, None) => self.end,
+        }
+    }
+}
+
+// What the clones of a compiled interpreter share: blocks compiled from the memory as it was
+// when the program was compiled
+struct Program<W, M> {
+    // the block starting at each address of the program, compiled the first time it runs
+    blocks: Vec<OnceLock<Block<W, M>>>,
+}
+
+// The words written since the program was compiled, below MAX_COMPILED_ADDRESS, one bit each
+#[derive(Clone, Default)]
+struct Writes(Vec<u64>);
+
+impl Writes {
+    #[inline]
+    fn mark(&mut self, address: Address) {
+        if address < MAX_COMPILED_ADDRESS {
+            let index = address / 64;
+            if index >= self.0.len() {
+                self.0.resize(index + 1, 0);
+            }
+            self.0[index] |= 1 << (address % 64);
+        }
+    }
+
+    fn contains(&self, address: Address) -> bool {
+        match self.0.get(address / 64) {
+            Some(bits) => bits & 1 << (address % 64) != 0,
+            None => false,
+        }
+    }
+
+    // whether a word in start..end was written
+    fn any(&self, start: Address, end: Address) -> bool {
+        if start >= end {
+            return false;
+        }
+        let (first, last) = (start / 64, (end - 1) / 64);
+        if first == last {
+            let bits = match self.0.get(first) {
+                Some(bits) => *bits,
+                None => return false,
+            };
+            let (low, high) = (start % 64, (end - 1) % 64);
+            return bits >> low << (63 - high + low) != 0;
+        }
+        self.0
+            .iter()
+            .enumerate()
+            .take(last + 1)
+            .skip(first)
+            .any(|(index, bits)| {
+                let low = if index == first { start % 64 } else { 0 };
+                let high = if index == last { (end - 1) % 64 } else { 63 };
+                bits >> low << (63 - high + low) != 0
+            })
+    }
+}
+
+/// An interpreter running straight-line code from precompiled blocks, see
+/// `Interpreter::compile`.
+///
+/// Each instruction is compiled, the first time it runs, into a closure specialized for the
+/// modes of its parameters, with the parameters decoded once. Blocks chain these closures up to
+/// a jump, an output or a halt, and run without decoding, and without the bookkeeping the
+/// interpreter does for tracing and reverse stepping. A block can start with an input, which
+/// runs on the interpreter when the input queue is empty so that the input policy applies.
+/// Instructions that would fail to decode, and registered opcodes, run on the interpreter.
+/// Blocks are compiled from the memory as it was when the program was compiled: once the
+/// program overwrites a word of a block, the block runs on the interpreter, and so does code
+/// beyond the program as it was loaded. Parameters that were overwritten when their
+/// instruction is compiled, or that the block overwrites before it, are read when the
+/// instruction runs instead: day 7 patches a jump with the phase setting, and day 13 patches
+/// the address of its reads. Tracing and history need every instruction to go through the
+/// interpreter, so nothing is compiled while they are enabled.
+///
+/// Clones share the blocks, so that a program is only compiled once when it runs on several
+/// machines (e.g. the amplifiers of day 7), from one thread or several. The results are the
+/// same as with the interpreter: errors, instruction counts and memory limits included.
+pub struct CompiledInterpreter<W = Value, M = DenseMemory<W>> {
+    program: Arc<Program<W, M>>,
+    state: State<W, M>,
+}
+
+// What a compiled interpreter does not share with its clones
+#[derive(Clone)]
+struct State<W, M> {
+    interpreter: Interpreter<W, M>,
+    written: Writes,
+}
+
+impl<W: Clone, M: Clone> Clone for CompiledInterpreter<W, M> {
+    fn clone(&self) -> Self {
+        CompiledInterpreter {
+            program: Arc::clone(&self.program),
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<W, M> Interpreter<W, M>
+where
+    W: Word + Send + Sync + 'static,
+    M: MemoryBackend<W> + 'static,
+{
+    /// Runs the program from compiled blocks, see `CompiledInterpreter`. This is worth it for
+    /// programs running many instructions, e.g. the day 7 amplifiers or the day 9 BOOST check:
+    /// blocks are compiled the first time they run, which costs about as much as interpreting
+    /// them a few times.
+    pub fn compile(self) -> CompiledInterpreter<W, M> {
+        // the end of the program as loaded, not counting far writes to a sparse memory
+        let end = self
+            .memory
+            .values
+            .chunks()
+            .iter()
+            .filter(|(start, _)| *start < MAX_COMPILED_ADDRESS)
+            .map(|(start, values)| (start + values.len()).min(MAX_COMPILED_ADDRESS))
+            .max()
+            .unwrap_or(0);
+        CompiledInterpreter {
+            program: Arc::new(Program {
+                blocks: (0..end).map(|_| OnceLock::new()).collect(),
+            }),
+            state: State {
+                interpreter: self,
+                written: Writes::default(),
+            },
+        }
+    }
+}
+
+impl<W, M> CompiledInterpreter<W, M>
+where
+    W: Word + Send + Sync + 'static,
+    M: MemoryBackend<W> + 'static,
+{
+    pub fn from_code(code: &str) -> Result<Self, W::ParseError> {
+        Ok(Interpreter::from_code(code)?.compile())
+    }
+
+    pub fn interpreter(&self) -> &Interpreter<W, M> {
+        &self.state.interpreter
+    }
+
+    pub fn into_interpreter(self) -> Interpreter<W, M> {
+        self.state.interpreter
+    }
+
+    pub fn queue_input(&mut self, input: W) {
+        self.state.interpreter.queue_input(input);
+    }
+
+    pub fn read_memory(&self, address: Address) -> W {
+        self.state.interpreter.read_memory(address)
+    }
+
+    /// Same as `Interpreter::write_memory`. Overwritten blocks run on the interpreter.
+    pub fn write_memory(&mut self, address: Address, value: W) {
+        self.state.interpreter.write_memory(address, value);
+        self.state.written.mark(address);
+    }
+
+    pub fn instruction_count(&self) -> u64 {
+        self.state.interpreter.instruction_count()
+    }
+
+    /// The number of instructions compiled into blocks, by this interpreter and its clones
+    pub fn compiled_instructions(&self) -> usize {
+        let blocks = self.program.blocks.iter().filter_map(OnceLock::get);
+        blocks.map(|block| block.len as usize).sum()
+    }
+    pub fn run_until_block(&mut self) -> Result<IOAction<W>, ExecutionError<W>> {
+        loop {
+            if let Some(io_action) = self.run(u64::MAX)? {
+                return Ok(io_action);
+            }
+        }
+    }
+
+    /// Same as `Interpreter::run_for`: blocks that do not fit in the remaining budget run one
+    /// instruction at a time.
+    pub fn run_for(&mut self, max_instructions: u64) -> Result<BudgetedRun<W>, ExecutionError<W>> {
+        let budget_end = self.instruction_count().saturating_add(max_instructions);
+        match self.run(budget_end)? {
+            Some(io_action) => Ok(BudgetedRun::Action(io_action)),
+            None => Ok(BudgetedRun::BudgetExhausted),
+        }
+    }
+
+    /// Same as `Interpreter::run_with`
+    pub fn run_with(
+        &mut self,
+        mut io: impl InputDevice<W> + OutputDevice<W>,
+    ) -> Result<device::Exit, ExecutionError<W>> {
+        loop {
+            match self.run_until_block()? {
+                IOAction::Halt => return Ok(device::Exit::Halt),
+                IOAction::ReadInput => match io.read() {
+                    Some(input) => self.queue_input(input),
+                    None => return Ok(device::Exit::InputExhausted),
+                },
+                IOAction::ProduceOutput(output) => io.write(output),
+            }
+        }
+    }
+
+    // Runs blocks, and single instructions on the interpreter where no block can run, until
+    // an IO action or until the instruction count reaches `budget_end`
+    fn run(&mut self, budget_end: u64) -> Result<Option<IOAction<W>>, ExecutionError<W>> {
+        let state = &mut self.state;
+        while state.interpreter.instruction_count < budget_end {
+            let address = state.interpreter.instruction_pointer;
+            let cell = self.program.blocks.get(address);
+            if let (Some(cell), false) = (cell, state.interpreter.is_observed()) {
+                let block = cell.get_or_init(|| state.compile_block(address));
+                let budget = budget_end - state.interpreter.instruction_count;
+                if block.len > 0
+                    && block.len <= budget
+                    && (block.input.is_none() || !state.interpreter.input_queue.is_empty())
+                    && state.can_run(block)
+                {
+                    match state.run_block(block)? {
+                        Some(io_action) => return Ok(Some(io_action)),
+                        None => continue,
+                    }
+                }
+            }
+            let io_action = state.interpreter.step()?;
+            if let Some((address, _)) = state.interpreter.memory.last_write {
+                state.written.mark(address);
+            }
+            if io_action.is_some() {
+                return Ok(io_action);
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<W, M> State<W, M>
+where
+    W: Word + Send + Sync + 'static,
+    M: MemoryBackend<W> + 'static,
+{
+    // The instruction count is only updated when the block is left
+    fn run_block(&mut self, block: &Block<W, M>) -> Result<Option<IOAction<W>>, ExecutionError<W>> {
+        let mode = self.interpreter.arithmetic_mode;
+        let mut executed = 0;
+        if let Some(input) = &block.input {
+            let value = self.interpreter.input_queue.front().cloned().unwrap();
+            let address = match input(&mut self.interpreter.memory, value) {
+                Ok(address) => address,
+                Err(kind) => return Err(self.fail(kind, block.start, executed)),
+            };
+            self.interpreter.consume_queued_input();
+            executed += 1;
+            self.written.mark(address);
+            if block.compiled_from(address) {
+                self.leave(block.start + instruction_length(Opcode::Input), executed);
+                return Ok(None);
+            }
+        }
+        let memory = &mut self.interpreter.memory;
+        for (index, (address, operation)) in block.operations.iter().enumerate() {
+            match operation(memory, mode) {
+                Ok(None) => {}
+                Ok(Some(written)) => {
+                    self.written.mark(written);
+                    // the next instructions of the block may be stale
+                    if block.compiled_from(written) {
+                        self.leave(block.after(index), executed + index as u64 + 1);
+                        return Ok(None);
+                    }
+                }
+                Err(kind) => return Err(self.fail(kind, *address, executed + index as u64)),
+            }
+        }
+        executed += block.operations.len() as u64;
+        let (address, exit) = match &block.exit {
+            Some(exit) => exit,
+            None => {
+                self.leave(block.end, executed);
+                return Ok(None);
+            }
+        };
+        let mut next = block.end;
+        let result = match exit {
+            Exit::Jump(jump) => jump(memory).map(|target| {
+                next = target.unwrap_or(next);
+                None
+            }),
+            Exit::Output(output) => output(memory).map(IOAction::ProduceOutput).map(Some),
+            // the instruction pointer stays on the halt, as with the interpreter
+            Exit::Halt => {
+                next = *address;
+                Ok(Some(IOAction::Halt))
+            }
+        };
+        match result {
+            Ok(io_action) => {
+                self.leave(next, executed + 1);
+                Ok(io_action)
+            }
+            Err(kind) => Err(self.fail(kind, *address, executed)),
+        }
+    }
+    // Continues at `next` after `executed` instructions of a block
+    fn leave(&mut self, next: Address, executed: u64) {
+        self.interpreter.instruction_pointer = next;
+        self.interpreter.instruction_count += executed;
+    }
+
+    // Leaves the interpreter pointing at the faulty instruction, as `step` does
+    fn fail(
+        &mut self,
+        kind: ExecutionErrorKind<W>,
+        address: Address,
+        executed: u64,
+    ) -> ExecutionError<W> {
+        self.leave(address, executed);
+        ExecutionError::new(kind, address, self.interpreter.memory.get(address))
+    }
+
+    // whether a word in start..end was written since the program was compiled
+    fn wrote_into(&self, start: Address, end: Address) -> bool {
+        self.written.any(start, end)
+    }
+
+    // whether the words `block` was compiled from are still in memory, besides the parameters
+    // it reads when it runs
+    fn can_run(&self, block: &Block<W, M>) -> bool {
+        !self.wrote_into(block.start, block.end)
+            || (block.start..block.end)
+                .all(|address| !self.written.contains(address) || block.dynamic.contains(&address))
+    }
+
+    // Only compiles words that were not written since the program was compiled, which have
+    // the same value for every clone
+    fn compile_block(&mut self, start: Address) -> Block<W, M> {
+        let mut block = Block {
+            start,
+            input: None,
+            operations: Vec::new(),
+            exit: None,
+            end: start,
+            len: 0,
+            dynamic: Vec::new(),
+        };
+        // the words the block writes to, as far as they are known at compile time
+        let mut writes = Vec::new();
+        while let Some(instruction) = self.compile_instruction(block.end, &writes) {
+            let address = block.end;
+            let exit = match instruction.compiled {
+                Compiled::Input(input) if address == start => {
+                    block.input = Some(input);
+                    false
+                }
+                // the input starts the next block
+                Compiled::Input(_) => break,
+                Compiled::Operation(operation) => {
+                    block.operations.push((address, operation));
+                    false
+                }
+                Compiled::Exit(exit) => {
+                    block.exit = Some((address, exit));
+                    true
+                }
+            };
+            if instruction.dynamic {
+                block
+                    .dynamic
+                    .extend(address + 1..address + instruction.length);
+            }
+            writes.extend(instruction.write);
+            block.end += instruction.length;
+            block.len += 1;
+            if exit {
+                break;
+            }
+        }
+        block
+    }
+
+    // The instruction at `address`, None if it has to run on the interpreter. Parameters
+    // overwritten since the program was compiled, or by the block before the instruction, are
+    // read when it runs.
+    fn compile_instruction(
+        &mut self,
+        address: Address,
+        writes: &[Address],
+    ) -> Option<Instruction<W, M>> {
+        let interpreter = &mut self.interpreter;
+        let definition = interpreter
+            .memory
+            .decode(address, &interpreter.instruction_set)
+            .ok()?;
+        // a halt does not move the instruction pointer, but its word is still part of the block
+        let length = definition.length.max(1);
+        if address + length > MAX_COMPILED_ADDRESS || self.wrote_into(address, address + 1) {
+            return None;
+        }
+        // e.g. jumps patched by the program: the modes are known, not the parameters
+        let dynamic = self.wrote_into(address + 1, address + length)
+            || writes
+                .iter()
+                .any(|write| (address + 1..address + length).contains(write));
+        let memory = &self.interpreter.memory;
+        let modes = definition.instruction_modes;
+        let write = match definition.opcode {
+            Opcode::Registered(_) => None,
+            opcode => match written_parameter(&opcode) {
+                Some(index) if !dynamic && modes[index] == InstructionMode::Position => {
+                    to_address(&memory.get(address + index + 1)).ok()
+                }
+                _ => None,
+            },
+        };
+        // Runs `$body` with `$operand` bound to parameter `$index`, as a type specific to its
+        // mode
+        macro_rules! operand {
+            ($index:expr, |$operand:ident| $body:expr) => {{
+                let parameter = memory.get(address + $index + 1);
+                match modes[$index] {
+                    InstructionMode::Immediate => {
+                        let $operand = Immediate(parameter);
+                        $body
+                    }
+                    // invalid addresses are left to the interpreter, which reports them
+                    InstructionMode::Position => {
+                        let $operand = Position(to_address(&parameter).ok()?);
+                        $body
+                    }
+                    InstructionMode::Relative => {
+                        let $operand = Relative(parameter);
+                        $body
+                    }
+                }
+            }};
+        }
+        macro_rules! destination {
+            ($index:expr, |$destination:ident| $body:expr) => {{
+                let parameter = memory.get(address + $index + 1);
+                match modes[$index] {
+                    InstructionMode::Immediate => return None,
+                    InstructionMode::Position => {
+                        let $destination = Position(to_address(&parameter).ok()?);
+                        $body
+                    }
+                    InstructionMode::Relative => {
+                        let $destination = Relative(parameter);
+                        $body
+                    }
+                }
+            }};
+        }
+        // same as operand and destination, for parameters read when the instruction runs
+        macro_rules! parameter {
+            ($index:expr, |$parameter:ident| $body:expr) => {{
+                let $parameter = Parameter {
+                    address: address + $index + 1,
+                    mode: modes[$index],
+                };
+                $body
+            }};
+        }
+        macro_rules! parameter_destination {
+            ($index:expr, |$destination:ident| $body:expr) => {{
+                if modes[$index] == InstructionMode::Immediate {
+                    return None;
+                }
+                parameter!($index, |$destination| $body)
+            }};
+        }
+        macro_rules! compile {
+            ($operand:ident, $destination:ident) => {{
+                macro_rules! binary {
+                    ($compute:expr) => {
+                        $operand!(0, |left| $operand!(1, |right| $destination!(2, |result| {
+                            binary(left, right, result, $compute)
+                        })))
+                    };
+                }
+                match definition.opcode {
+                    Opcode::Addition => Compiled::Operation(binary!(|mode, left, right| {
+                        mode.add(&left, &right)
+                    })),
+                    Opcode::Multiplication => Compiled::Operation(binary!(|mode, left, right| {
+                        mode.mul(&left, &right)
+                    })),
+                    Opcode::LessThan => Compiled::Operation(binary!(|_, left, right| {
+                        Ok(W::from_i64((left < right) as i64))
+                    })),
+                    Opcode::Equals => Compiled::Operation(binary!(|_, left, right| {
+                        Ok(W::from_i64((left == right) as i64))
+                    })),
+                    Opcode::AdjustRelativeBase => {
+                        Compiled::Operation($operand!(0, |offset| adjust_relative_base(offset)))
+                    }
+                    Opcode::JumpIfTrue | Opcode::JumpIfFalse => {
+                        let jump_if = definition.opcode == Opcode::JumpIfTrue;
+                        let jump = $operand!(0, |condition| $operand!(1, |destination| {
+                            jump(condition, destination, jump_if)
+                        }));
+                        Compiled::Exit(Exit::Jump(jump))
+                    }
+                    Opcode::Input => {
+                        Compiled::Input($destination!(0, |destination| input(destination)))
+                    }
+                    Opcode::Output => {
+                        Compiled::Exit(Exit::Output($operand!(0, |value| output(value))))
+                    }
+                    Opcode::Halt => Compiled::Exit(Exit::Halt),
+                    // registered opcodes have handlers of their own
+                    Opcode::Registered(_) => return None,
+                }
+            }};
+        }
+        let compiled = if dynamic {
+            compile!(parameter, parameter_destination)
+        } else {
+            compile!(operand, destination)
+        };
+        Some(Instruction {
+            compiled,
+            length,
+            dynamic,
+            write,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::Io;
+    use crate::memory::SparseMemory;
+    use std::collections::VecDeque;
+    use std::thread;
+
+    // runs both until they halt, comparing every IO action and the memory
+    fn assert_same_run(code: &str, inputs: &[Value]) -> CompiledInterpreter {
+        let mut interpreter: Interpreter = Interpreter::from_code(code).unwrap();
+        let mut compiled: CompiledInterpreter = CompiledInterpreter::from_code(code).unwrap();
+        for input in inputs {
+            interpreter.queue_input(*input);
+            compiled.queue_input(*input);
+        }
+        loop {
+            let expected = interpreter.run_until_block();
+            let action = compiled.run_until_block();
+            assert_eq!(action, expected);
+            assert_eq!(
+                compiled.instruction_count(),
+                interpreter.instruction_count()
+            );
+            assert_eq!(
+                compiled.interpreter().instruction_pointer(),
+                interpreter.instruction_pointer()
+            );
+            assert_eq!(
+                compiled.interpreter().memory_words(),
+                interpreter.memory_words()
+            );
+            match action {
+                Ok(IOAction::ProduceOutput(_)) => {}
+                _ => return compiled,
+            }
+        }
+    }
+
+    #[test]
+    fn test_quine() {
+        let quine = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+        let compiled = assert_same_run(quine, &[]);
+        assert!(compiled.compiled_instructions() > 0);
+    }
+
+    #[test]
+    fn test_days() {
+        let day_5 = include_str!("../../day-5/input.txt");
+        assert_same_run(day_5, &[1]);
+        assert_same_run(day_5, &[5]);
+        let day_9 = include_str!("../../day-9/input.txt");
+        assert_same_run(day_9, &[1]);
+    }
+
+    #[test]
+    fn test_self_modification() {
+        // outputs 7, then overwrites the parameter of the output with 4 and jumps back
+        let code = "104,7,1101,4,0,1,1105,1,0";
+        let mut compiled: CompiledInterpreter = CompiledInterpreter::from_code(code).unwrap();
+        assert_eq!(compiled.run_until_block(), Ok(IOAction::ProduceOutput(7)));
+        assert_eq!(compiled.run_until_block(), Ok(IOAction::ProduceOutput(4)));
+
+        // the addition overwrites the jump ending its own block with a halt, through the
+        // relative base
+        let code = "109,4,21101,0,99,2,1105,1,0";
+        let compiled = assert_same_run(code, &[]);
+        assert!(compiled.state.wrote_into(6, 7));
+
+        // the second comparison overwrites the halt ending its block
+        assert_same_run("21207,0,0,0,2107,0,0,8,99", &[]);
+    }
+
+    #[test]
+    fn test_overwritten_parameters() {
+        // jumps to the address given as input, which overwrites the destination of the jump
+        let code = "3,4,1105,1,0,104,1,99,104,2,99";
+        let compiled: CompiledInterpreter = CompiledInterpreter::from_code(code).unwrap();
+        for (input, output) in &[(5, 1), (8, 2)] {
+            let mut clone = compiled.clone();
+            clone.queue_input(*input);
+            assert_eq!(
+                clone.run_until_block(),
+                Ok(IOAction::ProduceOutput(*output))
+            );
+        }
+        // the input, and the jump reading the destination it writes, run by both clones from
+        // the same block, and both outputs
+        assert_eq!(compiled.compiled_instructions(), 4);
+    }
+
+    #[test]
+    fn test_clones() {
+        // adds 1 to cell 12 forever, with no IO
+        let code = "1001,12,1,12,1105,1,0,99,0,0,0,0,0";
+        let mut compiled: CompiledInterpreter = CompiledInterpreter::from_code(code).unwrap();
+        assert_eq!(compiled.compiled_instructions(), 0);
+        let mut clone = compiled.clone();
+        assert_eq!(compiled.run_for(10), Ok(BudgetedRun::BudgetExhausted));
+        assert_eq!(compiled.read_memory(12), 5);
+        assert_eq!(clone.compiled_instructions(), 2);
+        assert!(!compiled.state.wrote_into(0, 7));
+
+        // turning the jump of the clone into a halt from outside is seen, and only by the clone
+        clone.write_memory(4, 99);
+        assert_eq!(clone.run_until_block(), Ok(IOAction::Halt));
+        assert_eq!(clone.read_memory(12), 1);
+        assert_eq!(compiled.run_for(10), Ok(BudgetedRun::BudgetExhausted));
+        assert_eq!(compiled.read_memory(12), 10);
+    }
+
+    #[test]
+    fn test_threads() {
+        // clones running on other threads compile blocks for each other
+        let day_5 = include_str!("../../day-5/input.txt");
+        let compiled: CompiledInterpreter = CompiledInterpreter::from_code(day_5).unwrap();
+        let outputs = |input: Value| {
+            let mut io = Io::new(VecDeque::from(vec![input]), Vec::new());
+            let mut interpreter: Interpreter = Interpreter::from_code(day_5).unwrap();
+            interpreter.run_with(&mut io).unwrap();
+            io.output
+        };
+        let threads: Vec<_> = [1, 5]
+            .iter()
+            .map(|input| {
+                let mut clone = compiled.clone();
+                let mut io = Io::new(VecDeque::from(vec![*input]), Vec::new());
+                thread::spawn(move || clone.run_with(&mut io).map(|_| io.output))
+            })
+            .collect();
+        for (thread, input) in threads.into_iter().zip(&[1, 5]) {
+            assert_eq!(thread.join().unwrap(), Ok(outputs(*input)));
+        }
+        assert!(compiled.compiled_instructions() > 0);
+    }
+
+    #[test]
+    fn test_far_writes() {
+        // compiling does not look at the memory, however far the program wrote
+        let far = 1_000_000_000_000;
+        let code = format!("4,{},99", far);
+        let mut interpreter: Interpreter<Value, SparseMemory<Value>> =
+            Interpreter::from_code(&code).unwrap();
+        interpreter.write_memory(far, 5);
+        let mut compiled = interpreter.compile();
+        assert_eq!(compiled.run_until_block(), Ok(IOAction::ProduceOutput(5)));
+        assert_eq!(compiled.run_until_block(), Ok(IOAction::Halt));
+        assert!(!compiled.state.wrote_into(0, 3));
+    }
+
+    #[test]
+    fn test_errors() {
+        // the relative base becomes negative before the last addition reads from it
+        let code = "1101,1,0,11,109,-5,2201,0,0,0,99,0";
+        let interpreter: Interpreter = Interpreter::from_code(code).unwrap();
+        let expected = interpreter.clone().run_until_block().unwrap_err();
+        let mut compiled = interpreter.compile();
+        assert_eq!(compiled.run_until_block(), Err(expected));
+        assert_eq!(compiled.interpreter().instruction_pointer(), 6);
+        assert_eq!(compiled.instruction_count(), 2);
+    }
+}
@@ -0,0 +1,217 @@
+use crate::device::{Exit, InputDevice, OutputDevice};
+use crate::interpreter::Interpreter;
+use crate::memory::MemoryBackend;
+use crate::word::Word;
+use std::collections::VecDeque;
+use std::error;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader, Write};
+
+const NEWLINE: u8 = b'\n';
+
+/// Talks to programs that use ASCII for their IO (days 17 and 25, for instance).
+///
+/// Outputs in 0..=127 are written as characters, and other values as integers on their own line.
+/// When the program wants an input, a line is taken from the script, or read from `input` once
+/// the script is over, and sent as its character codes followed by a newline. Scripted lines are
+/// echoed to `output`, as if they were typed.
+pub struct AsciiConsole<R, O> {
+    input: R,
+    output: O,
+    script: VecDeque<String>,
+    // the codes of the line being sent
+    pending: VecDeque<u8>,
+    transcript: Option<String>,
+    error: Option<io::Error>,
+}
+
+impl<R: BufRead, O: Write> AsciiConsole<R, O> {
+    pub fn new(input: R, output: O) -> Self {
+        AsciiConsole {
+            input,
+            output,
+            script: VecDeque::new(),
+            pending: VecDeque::new(),
+            transcript: None,
+            error: None,
+        }
+    }
+
+    /// Queues every line of `script` as an input line, before the ones read from `input`
+    pub fn load_script(&mut self, script: impl BufRead) -> io::Result<()> {
+        for line in script.lines() {
+            self.script.push_back(line?);
+        }
+        Ok(())
+    }
+
+    pub fn load_script_file(&mut self, filename: &str) -> io::Result<()> {
+        self.load_script(BufReader::new(File::open(filename)?))
+    }
+
+    /// Starts recording the session as it appears on a terminal: the outputs, and the input
+    /// lines whether they were typed or scripted
+    pub fn record_transcript(&mut self) {
+        self.transcript = Some(String::new());
+    }
+
+    pub fn transcript(&self) -> Option<&str> {
+        self.transcript.as_deref()
+    }
+
+    pub fn save_transcript(&self, filename: &str) -> io::Result<()> {
+        let mut file = File::create(filename)?;
+        file.write_all(self.transcript().unwrap_or_default().as_bytes())
+    }
+
+    // Gets the next line to send. `None` at the end of the input, or on an IO error.
+    fn next_line(&mut self) -> Option<String> {
+        if self.error.is_some() {
+            return None;
+        }
+        if let Some(line) = self.script.pop_front() {
+            self.write_text(&format!("{}\n", line));
+            return Some(line);
+        }
+        // the prompt must be visible before blocking
+        if let Err(error) = self.output.flush() {
+            self.error = Some(error);
+            return None;
+        }
+        let mut line = String::new();
+        match self.input.read_line(&mut line) {
+            Ok(0) => None,
+            Ok(_) => {
+                let line = line.trim_end_matches(&['\n', '\r'][..]).to_string();
+                if let Some(transcript) = &mut self.transcript {
+                    transcript.push_str(&line);
+                    transcript.push('\n');
+                }
+                Some(line)
+            }
+            Err(error) => {
+                self.error = Some(error);
+                None
+            }
+        }
+    }
+
+    fn write_text(&mut self, text: &str) {
+        if let Some(transcript) = &mut self.transcript {
+            transcript.push_str(text);
+        }
+        if self.error.is_none() {
+            if let Err(error) = self.output.write_all(text.as_bytes()) {
+                self.error = Some(error);
+            }
+        }
+    }
+}
+
+impl<W: Word, R: BufRead, O: Write> InputDevice<W> for AsciiConsole<R, O> {
+    fn read(&mut self) -> Option<W> {
+        if self.pending.is_empty() {
+            let line = self.next_line()?;
+            self.pending.extend(line.bytes());
+            self.pending.push_back(NEWLINE);
+        }
+        self.pending
+            .pop_front()
+            .map(|code| W::from_i64(i64::from(code)))
+    }
+}
+
+impl<W: Word, R: BufRead, O: Write> OutputDevice<W> for AsciiConsole<R, O> {
+    fn write(&mut self, value: W) {
+        let text = match value.to_i64() {
+            Some(code @ 0..=127) => (code as u8 as char).to_string(),
+            _ => format!("{}\n", value),
+        };
+        self.write_text(&text);
+    }
+}
+
+impl<W: Word + 'static, M: MemoryBackend<W>> Interpreter<W, M> {
+    /// Runs the program on an ASCII console, until it halts or the console has no more input.
+    pub fn run_ascii<R: BufRead, O: Write>(
+        &mut self,
+        console: &mut AsciiConsole<R, O>,
+    ) -> Result<Exit, Box<dyn error::Error>> {
+        let exit = self.run_with(&mut *console)?;
+        if let Some(error) = console.error.take() {
+            return Err(Box::new(error));
+        }
+        console.output.flush()?;
+        Ok(exit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::assemble;
+
+    // prompts for a line, prints it in upper case, and then a number that is not ASCII
+    const SHOUT: &str = "
+                out #62
+                out #32
+        loop:   in [pos char]
+                eq [pos char], #10, [pos end]
+                jt [pos end], #done
+                add [pos char], #-32, [pos char]
+                out [pos char]
+                jt #1, #loop
+        done:   out #10
+                out #1000
+                hlt
+        char:   .data 0
+        end:    .data 0
+    ";
+
+    fn shout() -> Interpreter {
+        Interpreter::from_code(&assemble(SHOUT).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_typed_input() {
+        let mut output = Vec::new();
+        let mut console = AsciiConsole::new(&b"hello\n"[..], &mut output);
+        console.record_transcript();
+        assert_eq!(shout().run_ascii(&mut console).unwrap(), Exit::Halt);
+        assert_eq!(console.transcript(), Some("> hello\nHELLO\n1000\n"));
+        drop(console);
+        // the terminal already shows what was typed
+        assert_eq!(String::from_utf8(output).unwrap(), "> HELLO\n1000\n");
+    }
+
+    #[test]
+    fn test_scripted_input() {
+        let mut output = Vec::new();
+        let mut console = AsciiConsole::new(&b""[..], &mut output);
+        console.load_script(&b"abc\nnot read\n"[..]).unwrap();
+        console.record_transcript();
+        assert_eq!(shout().run_ascii(&mut console).unwrap(), Exit::Halt);
+        assert_eq!(console.transcript(), Some("> abc\nABC\n1000\n"));
+        drop(console);
+        assert_eq!(String::from_utf8(output).unwrap(), "> abc\nABC\n1000\n");
+    }
+
+    #[test]
+    fn test_end_of_input() {
+        let mut output = Vec::new();
+        let mut console = AsciiConsole::new(&b""[..], &mut output);
+        let mut interpreter = shout();
+        assert_eq!(
+            interpreter.run_ascii(&mut console).unwrap(),
+            Exit::InputExhausted
+        );
+        assert_eq!(console.transcript(), None);
+
+        // the program resumes once there is input
+        console.load_script(&b"z"[..]).unwrap();
+        assert_eq!(interpreter.run_ascii(&mut console).unwrap(), Exit::Halt);
+        drop(console);
+        assert_eq!(String::from_utf8(output).unwrap(), "> z\nZ\n1000\n");
+    }
+}
@@ -0,0 +1,485 @@
+use crate::error::ExecutionError;
+use crate::interpreter::{Address, IOAction, Interpreter, Value};
+use crate::memory::{DenseMemory, MemoryBackend};
+use crate::word::Word;
+use std::collections::BTreeSet;
+use std::io;
+use std::io::{BufRead, Write};
+
+/// Why the debugger gave control back.
+#[derive(Debug, PartialEq, Clone)]
+pub enum StopReason<W = Value> {
+    /// A single step ran an instruction that did nothing noteworthy
+    Step,
+    Breakpoint(Address),
+    Watchpoint {
+        address: Address,
+        old: W,
+        new: W,
+    },
+    Output(W),
+    /// The program needs input, the instruction pointer stays on the input instruction
+    Input,
+    Halt,
+}
+
+/// Runs an interpreter instruction by instruction, stopping on breakpoints (the instruction
+/// pointer reaches an address) and watchpoints (the value of a memory cell changes).
+pub struct Debugger<W = Value, M = DenseMemory<W>> {
+    interpreter: Interpreter<W, M>,
+    breakpoints: BTreeSet<Address>,
+    watchpoints: BTreeSet<Address>,
+}
+
+impl<W: Word, M: MemoryBackend<W>> Debugger<W, M> {
+    pub fn new(interpreter: Interpreter<W, M>) -> Self {
+        Debugger {
+            interpreter,
+            breakpoints: BTreeSet::new(),
+            watchpoints: BTreeSet::new(),
+        }
+    }
+
+    pub fn interpreter(&self) -> &Interpreter<W, M> {
+        &self.interpreter
+    }
+
+    pub fn interpreter_mut(&mut self) -> &mut Interpreter<W, M> {
+        &mut self.interpreter
+    }
+
+    pub fn add_breakpoint(&mut self, address: Address) {
+        self.breakpoints.insert(address);
+    }
+
+    /// Returns false if there was no breakpoint at this address
+    pub fn remove_breakpoint(&mut self, address: Address) -> bool {
+        self.breakpoints.remove(&address)
+    }
+
+    pub fn add_watchpoint(&mut self, address: Address) {
+        self.watchpoints.insert(address);
+    }
+
+    /// Returns false if this address was not watched
+    pub fn remove_watchpoint(&mut self, address: Address) -> bool {
+        self.watchpoints.remove(&address)
+    }
+
+    pub fn step(&mut self) -> Result<StopReason<W>, ExecutionError<W>> {
+        let watched: Vec<(Address, W)> = self
+            .watchpoints
+            .iter()
+            .map(|&address| (address, self.interpreter.read_memory(address)))
+            .collect();
+        let io_action = self.interpreter.step()?;
+        for (address, old) in watched {
+            let new = self.interpreter.read_memory(address);
+            if new != old {
+                return Ok(StopReason::Watchpoint { address, old, new });
+            }
+        }
+        Ok(match io_action {
+            None => StopReason::Step,
+            Some(IOAction::ProduceOutput(output)) => StopReason::Output(output),
+            Some(IOAction::ReadInput) => StopReason::Input,
+            Some(IOAction::Halt) => StopReason::Halt,
+        })
+    }
+
+    /// Runs until a breakpoint, a watchpoint, the program needing input or halting. Outputs are
+    /// passed to `on_output`, unless `until_io` is set: the debugger then stops on outputs too.
+    ///
+    /// A breakpoint on the current instruction does not stop the program, so that resuming from a
+    /// breakpoint moves on.
+    pub fn resume(
+        &mut self,
+        until_io: bool,
+        mut on_output: impl FnMut(&W),
+    ) -> Result<StopReason<W>, ExecutionError<W>> {
+        loop {
+            match self.step()? {
+                StopReason::Step => {}
+                StopReason::Output(output) if !until_io => on_output(&output),
+                reason => return Ok(reason),
+            }
+            let instruction_pointer = self.interpreter.instruction_pointer();
+            if self.breakpoints.contains(&instruction_pointer) {
+                return Ok(StopReason::Breakpoint(instruction_pointer));
+            }
+        }
+    }
+
+    // Runs `count` instructions, like `continue` stopping early on breakpoints, watchpoints,
+    // input or halt. Outputs do not stop the program.
+    fn step_count(&mut self, count: usize) -> Result<Vec<StopReason<W>>, ExecutionError<W>> {
+        let mut reasons = Vec::new();
+        for remaining in (0..count).rev() {
+            let reason = self.step()?;
+            let stop = !matches!(reason, StopReason::Step | StopReason::Output(_));
+            reasons.push(reason);
+            if stop {
+                break;
+            }
+            let instruction_pointer = self.interpreter.instruction_pointer();
+            if remaining > 0 && self.breakpoints.contains(&instruction_pointer) {
+                reasons.push(StopReason::Breakpoint(instruction_pointer));
+                break;
+            }
+        }
+        Ok(reasons)
+    }
+
+    /// Reads commands from `input` until it is exhausted or the user quits. Type `help` for the
+    /// list of commands.
+    pub fn run_repl(&mut self, input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+        writeln!(output, "{}", self.interpreter.current_instruction())?;
+        for line in input.lines() {
+            let line = line?;
+            let arguments: Vec<&str> = line.split_whitespace().collect();
+            match arguments.as_slice() {
+                [] => continue,
+                ["quit"] | ["q"] => return Ok(()),
+                command => {
+                    if let Err(message) = self.run_command(command, &mut output)? {
+                        writeln!(output, "error: {}", message)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // The outer error is for failures to write, the inner one for invalid commands
+    fn run_command(
+        &mut self,
+        command: &[&str],
+        output: &mut impl Write,
+    ) -> io::Result<Result<(), String>> {
+        let result = match command {
+            ["help"] | ["h"] => {
+                write!(output, "{}", HELP)?;
+                return Ok(Ok(()));
+            }
+            ["step"] | ["s"] => self.step().map(|reason| vec![reason]),
+            ["step", count] | ["s", count] => match count.parse::<usize>() {
+                Ok(count) => self.step_count(count),
+                Err(_) => return Ok(Err(format!("invalid step count {}", count))),
+            },
+            ["back"] | ["back", _] => {
+                let count = match command.get(1).map(|count| count.parse::<u64>()) {
+                    None => 1,
+                    Some(Ok(count)) => count,
+                    Some(Err(_)) => return Ok(Err(format!("invalid step count {}", command[1]))),
+                };
+                let interpreter = &mut self.interpreter;
+                let target = interpreter.instruction_count().saturating_sub(count);
+                if !interpreter.rewind_to(target) {
+                    let earliest = interpreter.earliest_instruction_count();
+                    return Ok(Err(format!(
+                        "can only go back to instruction {} (history is disabled or too short)",
+                        earliest
+                    )));
+                }
+                Ok(Vec::new())
+            }
+            ["continue"] | ["c"] | ["io"] => {
+                let mut outputs = Vec::new();
+                let until_io = command[0] == "io";
+                let result = self.resume(until_io, |output| outputs.push(output.clone()));
+                for value in outputs {
+                    writeln!(output, "output: {}", value)?;
+                }
+                result.map(|reason| vec![reason])
+            }
+            ["input", value] | ["i", value] => {
+                let value = match W::parse_word(value) {
+                    Ok(value) => value,
+                    Err(err) => return Ok(Err(err.to_string())),
+                };
+                self.interpreter.queue_input(value);
+                return Ok(Ok(()));
+            }
+            ["break", address] | ["b", address] => {
+                return Ok(parse_address(address).map(|address| self.add_breakpoint(address)))
+            }
+            ["delete", address] => {
+                return Ok(parse_address(address).and_then(|address| {
+                    if self.remove_breakpoint(address) {
+                        Ok(())
+                    } else {
+                        Err(format!("no breakpoint at {}", address))
+                    }
+                }))
+            }
+            ["watch", address] | ["w", address] => {
+                return Ok(parse_address(address).map(|address| self.add_watchpoint(address)))
+            }
+            ["unwatch", address] => {
+                return Ok(parse_address(address).and_then(|address| {
+                    if self.remove_watchpoint(address) {
+                        Ok(())
+                    } else {
+                        Err(format!("{} is not watched", address))
+                    }
+                }))
+            }
+            ["info"] => {
+                writeln!(output, "breakpoints: {:?}", self.breakpoints)?;
+                writeln!(output, "watchpoints: {:?}", self.watchpoints)?;
+                return Ok(Ok(()));
+            }
+            ["mem", address] | ["mem", address, _] => {
+                let address = match parse_address(address) {
+                    Ok(address) => address,
+                    Err(message) => return Ok(Err(message)),
+                };
+                let count = match command.get(2).map(|count| count.parse::<usize>()) {
+                    None => 1,
+                    Some(Ok(count)) => count,
+                    Some(Err(_)) => return Ok(Err(format!("invalid count {}", command[2]))),
+                };
+                let end = match address.checked_add(count) {
+                    Some(end) => end,
+                    None => {
+                        return Ok(Err(format!(
+                            "{} cells from {} is past the memory",
+                            count, address
+                        )))
+                    }
+                };
+                let values: Vec<String> = (address..end)
+                    .map(|address| self.interpreter.read_memory(address).to_string())
+                    .collect();
+                writeln!(output, "{}: {}", address, values.join(", "))?;
+                return Ok(Ok(()));
+            }
+            ["set", address, value] => {
+                let address = match parse_address(address) {
+                    Ok(address) => address,
+                    Err(message) => return Ok(Err(message)),
+                };
+                return Ok(match W::parse_word(value) {
+                    Ok(value) => {
+                        self.interpreter.write_memory(address, value);
+                        Ok(())
+                    }
+                    Err(err) => Err(err.to_string()),
+                });
+            }
+            ["rb"] => {
+                writeln!(
+                    output,
+                    "relative base: {}",
+                    self.interpreter.relative_base()
+                )?;
+                return Ok(Ok(()));
+            }
+            ["rb", value] => {
+                return Ok(match W::parse_word(value) {
+                    Ok(value) => {
+                        self.interpreter.set_relative_base(value);
+                        Ok(())
+                    }
+                    Err(err) => Err(err.to_string()),
+                });
+            }
+            ["ip", address] => {
+                return Ok(parse_address(address).map(|address| {
+                    self.interpreter.set_instruction_pointer(address);
+                }))
+            }
+            ["show"] | ["ip"] => Ok(Vec::new()),
+            _ => {
+                return Ok(Err(format!(
+                    "unknown command {}, try help",
+                    command.join(" ")
+                )))
+            }
+        };
+
+        match result {
+            Ok(reasons) => {
+                for reason in reasons {
+                    write_stop_reason(output, &reason)?;
+                }
+                writeln!(output, "{}", self.interpreter.current_instruction())?;
+                Ok(Ok(()))
+            }
+            Err(err) => Ok(Err(err.to_string())),
+        }
+    }
+}
+
+const HELP: &str = "\
+step|s [count]        run one (or count) instructions, stopping like continue
+back [count]          undo one (or count) instructions, if the history is enabled
+continue|c            run until a breakpoint, a watchpoint, input or halt
+io                    same as continue, but also stop on outputs
+input|i <value>       queue an input value
+break|b <address>     add a breakpoint
+delete <address>      remove a breakpoint
+watch|w <address>     stop when the value at address changes
+unwatch <address>     remove a watchpoint
+info                  list breakpoints and watchpoints
+mem <address> [count] print memory
+set <address> <value> write memory
+rb [value]            print or set the relative base
+ip [address]          show the current instruction, or jump to address
+show                  show the current instruction
+quit|q                exit the debugger
+";
+
+fn parse_address(address: &str) -> Result<Address, String> {
+    address
+        .parse::<Address>()
+        .map_err(|_| format!("invalid address {}", address))
+}
+
+fn write_stop_reason<W: Word>(output: &mut impl Write, reason: &StopReason<W>) -> io::Result<()> {
+    match reason {
+        StopReason::Step => Ok(()),
+        StopReason::Breakpoint(address) => writeln!(output, "breakpoint at {}", address),
+        StopReason::Watchpoint { address, old, new } => {
+            writeln!(
+                output,
+                "watchpoint: {} changed from {} to {}",
+                address, old, new
+            )
+        }
+        StopReason::Output(value) => writeln!(output, "output: {}", value),
+        StopReason::Input => writeln!(output, "waiting for input"),
+        StopReason::Halt => writeln!(output, "halted"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::assemble;
+
+    // counts down from 3, printing every value
+    const COUNTDOWN: &str = "
+                in [pos counter]
+        loop:   out [pos counter]
+                add [pos counter], #-1, [pos counter]
+                jt [pos counter], #loop
+                hlt
+        counter: .data 0
+    ";
+
+    fn countdown_debugger() -> Debugger {
+        let mut interpreter: Interpreter =
+            Interpreter::from_code(&assemble(COUNTDOWN).unwrap()).unwrap();
+        interpreter.enable_history(1, 1);
+        Debugger::new(interpreter)
+    }
+
+    #[test]
+    fn test_debugger_breakpoints_and_watchpoints() {
+        let mut debugger = countdown_debugger();
+        let mut outputs = Vec::new();
+        assert_eq!(debugger.resume(false, |_| {}).unwrap(), StopReason::Input);
+        debugger.interpreter_mut().queue_input(3);
+
+        debugger.add_breakpoint(2);
+        assert_eq!(
+            debugger
+                .resume(false, |&value| outputs.push(value))
+                .unwrap(),
+            StopReason::Breakpoint(2)
+        );
+        assert!(outputs.is_empty());
+        // resuming from a breakpoint moves on
+        assert_eq!(
+            debugger
+                .resume(false, |&value| outputs.push(value))
+                .unwrap(),
+            StopReason::Breakpoint(2)
+        );
+        assert_eq!(outputs, vec![3]);
+        assert!(debugger.remove_breakpoint(2));
+
+        debugger.add_watchpoint(12);
+        assert_eq!(
+            debugger
+                .resume(false, |&value| outputs.push(value))
+                .unwrap(),
+            StopReason::Watchpoint {
+                address: 12,
+                old: 2,
+                new: 1
+            }
+        );
+        assert_eq!(outputs, vec![3, 2]);
+        assert!(debugger.remove_watchpoint(12));
+        assert_eq!(
+            debugger.resume(true, |_| {}).unwrap(),
+            StopReason::Output(1)
+        );
+        assert_eq!(debugger.resume(true, |_| {}).unwrap(), StopReason::Halt);
+    }
+
+    #[test]
+    fn test_debugger_step() {
+        let mut debugger = countdown_debugger();
+        debugger.interpreter_mut().queue_input(1);
+        assert_eq!(debugger.step().unwrap(), StopReason::Step);
+        assert_eq!(debugger.step().unwrap(), StopReason::Output(1));
+        assert_eq!(debugger.interpreter().instruction_pointer(), 4);
+    }
+
+    #[test]
+    fn test_debugger_repl() {
+        let mut debugger = countdown_debugger();
+        let commands = "
+            input 2
+            s
+            rb 5
+            rb
+            mem 12
+            set 12 1
+            io
+            back
+            back 5
+            s
+            break 11
+            c
+            mem 18446744073709551615 2
+            ip 2
+            set 12 1
+            s 5
+            foo
+            q
+            s
+        ";
+        let mut transcript = Vec::new();
+        debugger
+            .run_repl(commands.as_bytes(), &mut transcript)
+            .unwrap();
+        let expected_transcript = [
+            "0: IN [pos 12]",
+            "2: OUT [pos 12]",
+            "relative base: 5",
+            "12: 2",
+            "output: 1",
+            "4: ADD [pos 12], #-1, [pos 12]",
+            "2: OUT [pos 12]",
+            "error: can only go back to instruction 1 (history is disabled or too short)",
+            "output: 1",
+            "4: ADD [pos 12], #-1, [pos 12]",
+            "breakpoint at 11",
+            "11: HLT",
+            "error: 2 cells from 18446744073709551615 is past the memory",
+            "output: 1",
+            "breakpoint at 11",
+            "11: HLT",
+            "error: unknown command foo, try help",
+            "",
+        ];
+        assert_eq!(
+            String::from_utf8(transcript).unwrap(),
+            expected_transcript.join("\n")
+        );
+    }
+}
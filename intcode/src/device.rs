@@ -0,0 +1,291 @@
+use crate::error::ExecutionError;
+use crate::interpreter::{IOAction, Interpreter};
+use crate::memory::MemoryBackend;
+use crate::word::Word;
+use std::collections::VecDeque;
+use std::sync::mpsc::{Receiver, Sender, SyncSender};
+
+/// Gives inputs to a program, see `Interpreter::run_with`.
+pub trait InputDevice<W> {
+    /// The next input, or `None` if there is none available
+    fn read(&mut self) -> Option<W>;
+}
+
+/// Receives the outputs of a program, see `Interpreter::run_with`.
+pub trait OutputDevice<W> {
+    fn write(&mut self, value: W);
+}
+
+/// Receives the outputs of a program by groups of a fixed number of values, see `Framed`.
+pub trait FrameDevice<W> {
+    fn write_frame(&mut self, frame: &[W]);
+}
+
+/// Why `Interpreter::run_with` returned
+#[non_exhaustive]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Exit {
+    Halt,
+    /// The program wants an input and the device has none. Running again retries the read.
+    InputExhausted,
+}
+
+impl<W: Word, M: MemoryBackend<W>> Interpreter<W, M> {
+    /// Runs the program until it halts, reading the inputs that are not already queued from `io`
+    /// and writing every output to it. Pass the device by `&mut` to keep it afterwards.
+    pub fn run_with(
+        &mut self,
+        mut io: impl InputDevice<W> + OutputDevice<W>,
+    ) -> Result<Exit, ExecutionError<W>> {
+        loop {
+            match self.run_until_block()? {
+                IOAction::Halt => return Ok(Exit::Halt),
+                IOAction::ReadInput => match io.read() {
+                    Some(input) => self.queue_input(input),
+                    None => return Ok(Exit::InputExhausted),
+                },
+                IOAction::ProduceOutput(output) => io.write(output),
+            }
+        }
+    }
+}
+
+impl<W, T: InputDevice<W> + ?Sized> InputDevice<W> for &mut T {
+    fn read(&mut self) -> Option<W> {
+        (**self).read()
+    }
+}
+
+impl<W, T: OutputDevice<W> + ?Sized> OutputDevice<W> for &mut T {
+    fn write(&mut self, value: W) {
+        (**self).write(value)
+    }
+}
+
+impl<W, T: FrameDevice<W> + ?Sized> FrameDevice<W> for &mut T {
+    fn write_frame(&mut self, frame: &[W]) {
+        (**self).write_frame(frame)
+    }
+}
+
+// `()` is the device of programs without IO: it has no input and drops the outputs
+impl<W> InputDevice<W> for () {
+    fn read(&mut self) -> Option<W> {
+        None
+    }
+}
+
+impl<W> OutputDevice<W> for () {
+    fn write(&mut self, _value: W) {}
+}
+
+impl<W> InputDevice<W> for VecDeque<W> {
+    fn read(&mut self) -> Option<W> {
+        self.pop_front()
+    }
+}
+
+impl<W> OutputDevice<W> for Vec<W> {
+    fn write(&mut self, value: W) {
+        self.push(value);
+    }
+}
+
+/// Blocks until an input is sent, and has no more input once every sender is gone.
+impl<W> InputDevice<W> for Receiver<W> {
+    fn read(&mut self) -> Option<W> {
+        self.recv().ok()
+    }
+}
+
+/// Outputs written once the receiver is gone are dropped.
+impl<W> OutputDevice<W> for Sender<W> {
+    fn write(&mut self, value: W) {
+        let _ = self.send(value);
+    }
+}
+
+/// Blocks while the channel is full. Outputs written once the receiver is gone are dropped.
+impl<W> OutputDevice<W> for SyncSender<W> {
+    fn write(&mut self, value: W) {
+        let _ = self.send(value);
+    }
+}
+
+/// An input device and an output device used together.
+#[derive(Debug, Clone)]
+pub struct Io<I, O> {
+    pub input: I,
+    pub output: O,
+}
+
+impl<I, O> Io<I, O> {
+    pub fn new(input: I, output: O) -> Self {
+        Io { input, output }
+    }
+}
+
+impl<W, I: InputDevice<W>, O> InputDevice<W> for Io<I, O> {
+    fn read(&mut self) -> Option<W> {
+        self.input.read()
+    }
+}
+
+impl<W, I, O: OutputDevice<W>> OutputDevice<W> for Io<I, O> {
+    fn write(&mut self, value: W) {
+        self.output.write(value)
+    }
+}
+
+/// Reads the inputs from an iterator.
+#[derive(Debug, Clone)]
+pub struct IteratorInput<T>(pub T);
+
+impl<T: Iterator> InputDevice<T::Item> for IteratorInput<T> {
+    fn read(&mut self) -> Option<T::Item> {
+        self.0.next()
+    }
+}
+
+/// Reads the inputs from a closure.
+#[derive(Debug, Clone)]
+pub struct FnInput<F>(pub F);
+
+impl<W, F: FnMut() -> Option<W>> InputDevice<W> for FnInput<F> {
+    fn read(&mut self) -> Option<W> {
+        (self.0)()
+    }
+}
+
+/// Gives the outputs to a closure, one by one as an `OutputDevice`, or frame by frame as a
+/// `FrameDevice`.
+#[derive(Debug, Clone)]
+pub struct FnOutput<F>(pub F);
+
+impl<W, F: FnMut(W)> OutputDevice<W> for FnOutput<F> {
+    fn write(&mut self, value: W) {
+        (self.0)(value)
+    }
+}
+
+impl<W, F: FnMut(&[W])> FrameDevice<W> for FnOutput<F> {
+    fn write_frame(&mut self, frame: &[W]) {
+        (self.0)(frame)
+    }
+}
+
+/// Groups the outputs by `arity` values before giving them to a `FrameDevice`, for programs
+/// whose outputs are tuples such as `(x, y, tile)`. If the device is also an `InputDevice`, the
+/// inputs are read from it.
+#[derive(Debug, Clone)]
+pub struct Framed<D, W> {
+    device: D,
+    arity: usize,
+    pending: Vec<W>,
+}
+
+impl<D, W> Framed<D, W> {
+    pub fn new(device: D, arity: usize) -> Self {
+        assert!(arity > 0, "frames must have at least one value");
+        Framed {
+            device,
+            arity,
+            pending: Vec::with_capacity(arity),
+        }
+    }
+
+    pub fn device(&self) -> &D {
+        &self.device
+    }
+
+    pub fn device_mut(&mut self) -> &mut D {
+        &mut self.device
+    }
+
+    /// The outputs of the frame that is not complete yet
+    pub fn pending(&self) -> &[W] {
+        &self.pending
+    }
+
+    pub fn into_inner(self) -> D {
+        self.device
+    }
+}
+
+impl<D: InputDevice<W>, W> InputDevice<W> for Framed<D, W> {
+    fn read(&mut self) -> Option<W> {
+        self.device.read()
+    }
+}
+
+impl<D: FrameDevice<W>, W> OutputDevice<W> for Framed<D, W> {
+    fn write(&mut self, value: W) {
+        self.pending.push(value);
+        if self.pending.len() == self.arity {
+            self.device.write_frame(&self.pending);
+            self.pending.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Value;
+    use std::sync::mpsc;
+    use std::thread;
+
+    // outputs the sum of each pair of inputs, until it reads a 0
+    const PAIR_SUMS: &str = "3,17,1006,17,16,3,18,1,17,18,19,4,19,1105,1,0,99,0,0,0";
+
+    #[test]
+    fn test_run_with_iterator_and_vec() {
+        let mut interpreter: Interpreter = Interpreter::from_code(PAIR_SUMS).unwrap();
+        let mut outputs = Vec::new();
+        let io = Io::new(IteratorInput(vec![1, 2, 30, 40].into_iter()), &mut outputs);
+        assert_eq!(interpreter.run_with(io).unwrap(), Exit::InputExhausted);
+        assert_eq!(outputs, vec![3, 70]);
+
+        // queued inputs come first, and the run resumes at the input instruction
+        interpreter.queue_input(5);
+        let io = Io::new(VecDeque::from(vec![6, 0]), &mut outputs);
+        assert_eq!(interpreter.run_with(io).unwrap(), Exit::Halt);
+        assert_eq!(outputs, vec![3, 70, 11]);
+    }
+
+    #[test]
+    fn test_run_with_channels() {
+        let (input_sender, input_receiver) = mpsc::channel();
+        let (output_sender, output_receiver) = mpsc::channel();
+        let machine = thread::spawn(move || {
+            let mut interpreter: Interpreter = Interpreter::from_code(PAIR_SUMS).unwrap();
+            interpreter.run_with(Io::new(input_receiver, output_sender))
+        });
+        input_sender.send(20).unwrap();
+        input_sender.send(22).unwrap();
+        assert_eq!(output_receiver.recv().unwrap(), 42);
+        input_sender.send(0).unwrap();
+        assert_eq!(machine.join().unwrap().unwrap(), Exit::Halt);
+        assert!(output_receiver.recv().is_err());
+    }
+
+    #[test]
+    fn test_run_with_closures_and_frames() {
+        let mut counter = 0;
+        let input = FnInput(|| {
+            counter += 1;
+            Some(if counter <= 5 { counter } else { 0 })
+        });
+        let mut frames: Vec<Vec<Value>> = Vec::new();
+        let mut output = Framed::new(FnOutput(|frame: &[Value]| frames.push(frame.to_vec())), 2);
+        let mut interpreter: Interpreter = Interpreter::from_code(PAIR_SUMS).unwrap();
+        assert_eq!(
+            interpreter.run_with(Io::new(input, &mut output)).unwrap(),
+            Exit::Halt
+        );
+        // 1 + 2, 3 + 4 and 5 + 0, then the next 0 stops the program
+        assert_eq!(output.pending(), &[5]);
+        drop(output);
+        assert_eq!(frames, vec![vec![3, 7]]);
+    }
+}
@@ -0,0 +1,250 @@
+use crate::interpreter::{
+    mnemonic, parameter_count, parse_instruction_definition, written_parameter, Address,
+    InstructionDefinition, InstructionMode, Value,
+};
+use crate::word::Word;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// A parameter of a disassembled instruction
+#[derive(Debug, PartialEq, Clone)]
+pub enum Operand<W = Value> {
+    Position(W),
+    Immediate(W),
+    Relative(W),
+}
+
+impl<W: Word> fmt::Display for Operand<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Operand::Position(value) => write!(f, "[pos {}]", value),
+            Operand::Immediate(value) => write!(f, "#{}", value),
+            Operand::Relative(value) => write!(f, "[rel {}]", value),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Item<W = Value> {
+    Instruction {
+        /// Lowercase mnemonic, as accepted by the assembler (`add`, `jt`, `hlt`...)
+        mnemonic: &'static str,
+        operands: Vec<Operand<W>>,
+    },
+    /// A word that does not decode to a valid instruction
+    Data(W),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Line<W = Value> {
+    pub address: Address,
+    /// Number of words covered by the line
+    pub length: usize,
+    pub item: Item<W>,
+    /// Addresses of the instructions writing somewhere in this line
+    pub modified_by: Vec<Address>,
+}
+
+/// A program decoded from its first word to its last one, one instruction (or data word) after
+/// the other. Data words that happen to decode to a valid instruction are listed as
+/// instructions, and so are the writes they seem to do.
+///
+/// Self-modifications are only detected for writes in position mode: the target of a write in
+/// relative mode depends on the relative base, which is only known at run time.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Disassembly<W = Value> {
+    lines: Vec<Line<W>>,
+}
+
+impl<W> Disassembly<W> {
+    pub fn lines(&self) -> &[Line<W>] {
+        &self.lines
+    }
+}
+
+pub fn disassemble<W: Word>(program: &[W]) -> Disassembly<W> {
+    let mut lines = Vec::new();
+    // written address => addresses of the instructions writing there
+    let mut writes: BTreeMap<Address, Vec<Address>> = BTreeMap::new();
+    let mut address = 0;
+    while address < program.len() {
+        let line = decode_line(program, address);
+        if let Some(definition) = decode_instruction(program, address) {
+            if let Some(target) = position_write_target(program, address, &definition) {
+                writes.entry(target).or_default().push(address);
+            }
+        }
+        address += line.length;
+        lines.push(line);
+    }
+
+    for line in lines.iter_mut() {
+        for (_, writers) in writes.range(line.address..line.address + line.length) {
+            line.modified_by.extend(writers);
+        }
+        line.modified_by.sort_unstable();
+        line.modified_by.dedup();
+    }
+    Disassembly { lines }
+}
+
+// The instruction (or data word) at `address`, without self-modification marks
+pub(crate) fn decode_line<W: Word>(program: &[W], address: Address) -> Line<W> {
+    match decode_instruction(program, address) {
+        Some(definition) => instruction_line(program, address, &definition),
+        None => Line {
+            address,
+            length: 1,
+            item: Item::Data(program[address].clone()),
+            modified_by: Vec::new(),
+        },
+    }
+}
+
+// None if the word at `address` is not an instruction the interpreter could run, or if it has
+// modes for more parameters than the instruction takes (the listing could not be assembled back
+// to the same word)
+fn decode_instruction<W: Word>(program: &[W], address: Address) -> Option<InstructionDefinition> {
+    let instruction_code = program[address].to_i64()?;
+    let definition = parse_instruction_definition::<W>(instruction_code).ok()?;
+    let parameter_count = parameter_count(&definition.opcode);
+    if instruction_code / 100 >= 10i64.pow(parameter_count as u32) {
+        return None;
+    }
+    if address + parameter_count >= program.len() {
+        return None;
+    }
+    if let Some(written) = written_parameter(&definition.opcode) {
+        if definition.instruction_modes[written] == InstructionMode::Immediate {
+            return None;
+        }
+    }
+    Some(definition)
+}
+
+fn instruction_line<W: Word>(
+    program: &[W],
+    address: Address,
+    definition: &InstructionDefinition,
+) -> Line<W> {
+    let parameter_count = parameter_count(&definition.opcode);
+    let operands = (0..parameter_count)
+        .map(|index| {
+            let value = program[address + 1 + index].clone();
+            match definition.instruction_modes[index] {
+                InstructionMode::Position => Operand::Position(value),
+                InstructionMode::Immediate => Operand::Immediate(value),
+                InstructionMode::Relative => Operand::Relative(value),
+            }
+        })
+        .collect();
+    Line {
+        address,
+        length: parameter_count + 1,
+        item: Item::Instruction {
+            mnemonic: mnemonic(&definition.opcode),
+            operands,
+        },
+        modified_by: Vec::new(),
+    }
+}
+
+fn position_write_target<W: Word>(
+    program: &[W],
+    address: Address,
+    definition: &InstructionDefinition,
+) -> Option<Address> {
+    let written = written_parameter(&definition.opcode)?;
+    if definition.instruction_modes[written] != InstructionMode::Position {
+        return None;
+    }
+    program[address + 1 + written].to_address()
+}
+
+impl<W: Word> fmt::Display for Line<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let width = f.width().unwrap_or(0);
+        write!(f, "{:>width$}: ", self.address, width = width)?;
+        match &self.item {
+            Item::Instruction { mnemonic, operands } => {
+                write!(f, "{}", mnemonic.to_uppercase())?;
+                for (index, operand) in operands.iter().enumerate() {
+                    let separator = if index == 0 { " " } else { ", " };
+                    write!(f, "{}{}", separator, operand)?;
+                }
+            }
+            Item::Data(value) => write!(f, "DATA {}", value)?,
+        }
+        if !self.modified_by.is_empty() {
+            let writers: Vec<String> = self.modified_by.iter().map(|a| a.to_string()).collect();
+            write!(f, "  ; modified by {}", writers.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Word> fmt::Display for Disassembly<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // right-align addresses on the largest one
+        let width = match self.lines.last() {
+            Some(line) => line.address.to_string().len(),
+            None => 0,
+        };
+        for line in &self.lines {
+            writeln!(f, "{:>width$}", line, width = width)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::parse_code;
+
+    #[test]
+    fn test_disassemble_listing() {
+        let program: Vec<Value> = parse_code("21001,9,10,-3,109,1,1,4,11,12,99,7,0").unwrap();
+        let expected_listing = [
+            " 0: ADD [pos 9], #10, [rel -3]",
+            " 4: ARB #1",
+            " 6: ADD [pos 4], [pos 11], [pos 12]",
+            "10: HLT",
+            "11: DATA 7",
+            "12: DATA 0  ; modified by 6",
+            "",
+        ];
+        assert_eq!(
+            disassemble(&program).to_string(),
+            expected_listing.join("\n")
+        );
+    }
+
+    #[test]
+    fn test_disassemble_invalid_instructions_as_data() {
+        // writes in immediate mode, unknown opcode, extra parameter mode, truncated instruction
+        let program: Vec<Value> = parse_code("11101,42,199,1,0").unwrap();
+        let disassembly = disassemble(&program);
+        let items: Vec<&Item> = disassembly.lines().iter().map(|line| &line.item).collect();
+        assert_eq!(
+            items,
+            vec![
+                &Item::Data(11101),
+                &Item::Data(42),
+                &Item::Data(199),
+                &Item::Data(1),
+                &Item::Data(0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_disassemble_self_modification() {
+        // the first instruction turns the second one into a multiplication
+        let program: Vec<Value> = parse_code("1101,1,1,4,1,5,6,8,99").unwrap();
+        let disassembly = disassemble(&program);
+        assert_eq!(disassembly.lines()[1].address, 4);
+        assert_eq!(disassembly.lines()[1].modified_by, vec![0]);
+        assert!(disassembly.lines()[0].modified_by.is_empty());
+    }
+}
@@ -0,0 +1,107 @@
+use crate::interpreter::{Address, Value};
+use crate::word::Word;
+use std::error;
+use std::fmt;
+
+/// What went wrong while executing an instruction.
+#[non_exhaustive]
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ExecutionErrorKind<W = Value> {
+    UnknownOpcode,
+    InvalidParameterMode,
+    WriteInImmediateMode,
+    // the offending value, once the relative base has been applied
+    NegativeAddress(W),
+    // same, for addresses that do not fit in an `Address`
+    AddressOutOfRange(W),
+    ArithmeticOverflow,
+    // the address whose write would have made the memory grow past its limit
+    MemoryLimitExceeded(Address),
+    // the handler of the opcode returned an effect its parameters do not allow, e.g. a write
+    // without a write parameter
+    InvalidEffect,
+    // the address is in a range whose device was not cloned with the interpreter, see
+    // `Interpreter::map_device`
+    DetachedDevice(Address),
+}
+
+impl<W: Word> fmt::Display for ExecutionErrorKind<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExecutionErrorKind::UnknownOpcode => write!(f, "unknown opcode"),
+            ExecutionErrorKind::InvalidParameterMode => write!(f, "invalid parameter mode"),
+            ExecutionErrorKind::WriteInImmediateMode => {
+                write!(f, "attempted to write a value using immediate mode")
+            }
+            ExecutionErrorKind::NegativeAddress(address) => {
+                write!(f, "negative address {}", address)
+            }
+            ExecutionErrorKind::AddressOutOfRange(address) => {
+                write!(f, "address {} is out of range", address)
+            }
+            ExecutionErrorKind::ArithmeticOverflow => write!(f, "arithmetic overflow"),
+            ExecutionErrorKind::MemoryLimitExceeded(address) => {
+                write!(f, "writing at address {} exceeds the memory limit", address)
+            }
+            ExecutionErrorKind::InvalidEffect => {
+                write!(
+                    f,
+                    "the instruction has no parameter for the effect of its handler"
+                )
+            }
+            ExecutionErrorKind::DetachedDevice(address) => write!(
+                f,
+                "address {} is mapped to a device that was not cloned",
+                address
+            ),
+        }
+    }
+}
+
+/// An instruction that could not be executed. The interpreter is left pointing at the faulty
+/// instruction.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ExecutionError<W = Value> {
+    kind: ExecutionErrorKind<W>,
+    instruction_pointer: Address,
+    instruction: W,
+}
+
+impl<W: Word> ExecutionError<W> {
+    pub(crate) fn new(
+        kind: ExecutionErrorKind<W>,
+        instruction_pointer: Address,
+        instruction: W,
+    ) -> Self {
+        ExecutionError {
+            kind,
+            instruction_pointer,
+            instruction,
+        }
+    }
+
+    pub fn kind(&self) -> &ExecutionErrorKind<W> {
+        &self.kind
+    }
+
+    pub fn instruction_pointer(&self) -> Address {
+        self.instruction_pointer
+    }
+
+    /// The raw instruction word (opcode and parameter modes) at the instruction pointer.
+    pub fn instruction(&self) -> &W {
+        &self.instruction
+    }
+}
+
+impl<W: Word> fmt::Display for ExecutionError<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} (instruction {} at position {})",
+            self.kind, self.instruction, self.instruction_pointer
+        )
+    }
+}
+
+impl<W: Word> error::Error for ExecutionError<W> {}
@@ -0,0 +1,538 @@
+use crate::compiler::CompiledInterpreter;
+use crate::error::{ExecutionError, ExecutionErrorKind};
+use crate::instruction_set::BUILTIN_OPCODES;
+use crate::interpreter::{
+    instruction_length, opcode_number, written_parameter, BudgetedRun, IOAction, Interpreter,
+    Memory, Opcode, Value, MAX_INSTRUCTION_LENGTH,
+};
+use crate::memory::{DenseMemory, MemoryBackend, SparseMemory};
+use crate::reference;
+use crate::symbolic::{Solver, SymbolicError};
+use std::fmt;
+
+const DEFAULT_MAX_STEPS: u64 = 1000;
+const MAX_INSTRUCTIONS: usize = 12;
+const MAX_INPUTS: usize = 4;
+// programs computing their addresses may write anywhere
+const MEMORY_LIMIT: usize = 1 << 16;
+
+/// Why a run stopped
+#[non_exhaustive]
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Stop {
+    Halted,
+    /// The program wanted more inputs than it was given
+    MissingInput,
+    /// The program was still running after the maximum number of steps
+    StepLimit,
+    Error(ExecutionErrorKind),
+}
+
+/// What a program did on a backend
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Outcome {
+    pub stop: Stop,
+    pub outputs: Vec<Value>,
+    /// The memory once the program stopped, without its trailing zeros
+    pub memory: Vec<Value>,
+}
+
+impl Outcome {
+    // backends may count steps and memory cells differently
+    fn is_inconclusive(&self) -> bool {
+        matches!(
+            self.stop,
+            Stop::StepLimit | Stop::Error(ExecutionErrorKind::MemoryLimitExceeded(_))
+        )
+    }
+
+    pub fn new(stop: Stop, outputs: Vec<Value>, mut memory: Vec<Value>) -> Self {
+        while memory.last() == Some(&0) {
+            memory.pop();
+        }
+        Outcome {
+            stop,
+            outputs,
+            memory,
+        }
+    }
+
+    /// Runs an interpreter on `inputs`, for at most `max_steps` instructions. A sparse memory
+    /// extending past the memory limit of the fuzzer is reported as an error, instead of being
+    /// copied in the outcome.
+    pub fn from_interpreter<M: MemoryBackend<Value>>(
+        interpreter: &mut Interpreter<Value, M>,
+        inputs: &[Value],
+        max_steps: u64,
+    ) -> Self {
+        for input in inputs {
+            interpreter.queue_input(*input);
+        }
+        let (stop, outputs) = run_to_stop(|| {
+            let budget = max_steps.saturating_sub(interpreter.instruction_count());
+            interpreter.run_for(budget)
+        });
+        Outcome::from_stop(stop, outputs, interpreter)
+    }
+
+    /// Same as `from_interpreter`, with a compiled interpreter
+    pub fn from_compiled<M: MemoryBackend<Value> + 'static>(
+        compiled: &mut CompiledInterpreter<Value, M>,
+        inputs: &[Value],
+        max_steps: u64,
+    ) -> Self {
+        for input in inputs {
+            compiled.queue_input(*input);
+        }
+        let (stop, outputs) = run_to_stop(|| {
+            let budget = max_steps.saturating_sub(compiled.instruction_count());
+            compiled.run_for(budget)
+        });
+        Outcome::from_stop(stop, outputs, compiled.interpreter())
+    }
+
+    fn from_stop<M: MemoryBackend<Value>>(
+        stop: Stop,
+        outputs: Vec<Value>,
+        interpreter: &Interpreter<Value, M>,
+    ) -> Self {
+        let extent = match interpreter.memory.values.chunks().last() {
+            Some((address, values)) => address + values.len(),
+            None => 0,
+        };
+        if extent > MEMORY_LIMIT {
+            let stop = Stop::Error(ExecutionErrorKind::MemoryLimitExceeded(extent - 1));
+            return Outcome::new(stop, outputs, Vec::new());
+        }
+        Outcome::new(stop, outputs, interpreter.memory_words())
+    }
+}
+
+// Collects the outputs until a run does something else than outputting
+fn run_to_stop(
+    mut run_for: impl FnMut() -> Result<BudgetedRun<Value>, ExecutionError>,
+) -> (Stop, Vec<Value>) {
+    let mut outputs = Vec::new();
+    let stop = loop {
+        match run_for() {
+            Ok(BudgetedRun::Action(IOAction::ProduceOutput(output))) => outputs.push(output),
+            Ok(BudgetedRun::Action(IOAction::Halt)) => break Stop::Halted,
+            Ok(BudgetedRun::Action(IOAction::ReadInput)) => break Stop::MissingInput,
+            Ok(_) => break Stop::StepLimit,
+            Err(error) => break Stop::Error(error.kind().clone()),
+        }
+    };
+    (stop, outputs)
+}
+
+/// An implementation of Intcode the fuzzer compares to the others: runs a program on some
+/// inputs, for at most a number of steps. Closures with the signature of `run` are backends.
+pub trait Backend {
+    fn run(&self, program: &[Value], inputs: &[Value], max_steps: u64) -> Outcome;
+}
+
+impl<F: Fn(&[Value], &[Value], u64) -> Outcome> Backend for F {
+    fn run(&self, program: &[Value], inputs: &[Value], max_steps: u64) -> Outcome {
+        self(program, inputs, max_steps)
+    }
+}
+
+/// A program whose outcome depends on the backend running it
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Divergence {
+    pub program: Vec<Value>,
+    pub inputs: Vec<Value>,
+    /// The outcome on each backend, by name
+    pub outcomes: Vec<(String, Outcome)>,
+}
+
+impl Divergence {
+    /// A test failing as long as the built-in backends disagree on the program, to paste in a
+    /// module with `Fuzzer` in scope. `None` if the divergence involves backends added with
+    /// `add_backend`: the test would not run them, and would pass.
+    pub fn to_regression_test(&self, name: &str) -> Option<String> {
+        let builtin = Fuzzer::new(0);
+        if !self
+            .outcomes
+            .iter()
+            .all(|(backend, _)| builtin.backends.iter().any(|(other, _)| other == backend))
+        {
+            return None;
+        }
+        let join = |values: &[Value]| {
+            let values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+            values.join(", ")
+        };
+        Some(format!(
+            "#[test]\nfn {}() {{\n    let divergence = Fuzzer::new(0).check(&[{}], &[{}]);\n    \
+             assert_eq!(divergence, None);\n}}\n",
+            name,
+            join(&self.program),
+            join(&self.inputs)
+        ))
+    }
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let program: Vec<String> = self.program.iter().map(|v| v.to_string()).collect();
+        let inputs: Vec<String> = self.inputs.iter().map(|v| v.to_string()).collect();
+        writeln!(f, "program: {}", program.join(","))?;
+        writeln!(f, "inputs: {}", inputs.join(","))?;
+        for (name, outcome) in &self.outcomes {
+            writeln!(f, "{}: {:?}", name, outcome)?;
+        }
+        Ok(())
+    }
+}
+
+// xorshift64*, good enough to generate programs and reproducible from its seed
+#[derive(Debug, Clone)]
+pub(crate) struct Random(u64);
+
+impl Random {
+    pub(crate) fn new(seed: u64) -> Self {
+        // the state must not be zero
+        Random(seed ^ 0x9e37_79b9_7f4a_7c15)
+    }
+
+    pub(crate) fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+
+    // in low..=high
+    fn between(&mut self, low: Value, high: Value) -> Value {
+        low + (self.next() % (high - low + 1) as u64) as Value
+    }
+}
+
+/// Runs random programs on several implementations of Intcode, and reports the first program
+/// they do not agree on.
+///
+/// The days all run on the `Interpreter` of this crate, so the built-in backends are its
+/// configurations (`dense`, `dense-uncached`, `sparse`, `compiled`, and `rewind`, which runs the
+/// program a second time after rewinding its history), the executor of the symbolic `Solver`,
+/// and `reference`, a plain implementation of the puzzles sharing no code with the interpreter.
+/// Other backends can be added.
+///
+/// The programs are sequences of valid instructions followed by a halt, whose jumps mostly go to
+/// instructions and whose addresses mostly stay close to the program. Runs that reach the step
+/// limit, or write past 65536, are not compared: backends may count steps and memory cells
+/// differently.
+pub struct Fuzzer {
+    backends: Vec<(String, Box<dyn Backend>)>,
+    random: Random,
+    max_steps: u64,
+}
+
+impl Fuzzer {
+    /// A fuzzer with the built-in backends, generating programs from `seed`
+    pub fn new(seed: u64) -> Self {
+        let mut fuzzer = Fuzzer {
+            backends: Vec::new(),
+            random: Random::new(seed),
+            max_steps: DEFAULT_MAX_STEPS,
+        };
+        fuzzer.add_backend("dense", |program: &[Value], inputs: &[Value], max_steps| {
+            let mut interpreter = interpreter::<DenseMemory<Value>>(program);
+            Outcome::from_interpreter(&mut interpreter, inputs, max_steps)
+        });
+        fuzzer.add_backend(
+            "dense-uncached",
+            |program: &[Value], inputs: &[Value], max_steps| {
+                let mut interpreter = interpreter::<DenseMemory<Value>>(program);
+                interpreter.set_decode_cache(false);
+                Outcome::from_interpreter(&mut interpreter, inputs, max_steps)
+            },
+        );
+        fuzzer.add_backend(
+            "sparse",
+            |program: &[Value], inputs: &[Value], max_steps| {
+                let mut interpreter = interpreter::<SparseMemory<Value>>(program);
+                Outcome::from_interpreter(&mut interpreter, inputs, max_steps)
+            },
+        );
+        fuzzer.add_backend(
+            "compiled",
+            |program: &[Value], inputs: &[Value], max_steps| {
+                let mut compiled = interpreter::<DenseMemory<Value>>(program).compile();
+                Outcome::from_compiled(&mut compiled, inputs, max_steps)
+            },
+        );
+        fuzzer.add_backend(
+            "rewind",
+            |program: &[Value], inputs: &[Value], max_steps| {
+                let mut interpreter = interpreter::<DenseMemory<Value>>(program);
+                interpreter.enable_history(max_steps as usize, 7);
+                Outcome::from_interpreter(&mut interpreter, inputs, max_steps);
+                // the inputs consumed by the first run are queued again
+                assert!(interpreter.rewind_to(0));
+                Outcome::from_interpreter(&mut interpreter, &[], max_steps)
+            },
+        );
+        fuzzer.add_backend("symbolic", symbolic_backend);
+        fuzzer.add_backend(
+            "reference",
+            |program: &[Value], inputs: &[Value], max_steps| {
+                reference::run(program, inputs, max_steps, MEMORY_LIMIT)
+            },
+        );
+        fuzzer
+    }
+
+    pub fn add_backend(&mut self, name: &str, backend: impl Backend + 'static) {
+        self.backends.push((name.to_string(), Box::new(backend)));
+    }
+
+    /// The number of instructions a program may run. Longer runs are not compared.
+    pub fn set_max_steps(&mut self, steps: u64) {
+        self.max_steps = steps;
+    }
+
+    /// Tries `iterations` random programs. Returns the first divergence found, minimized.
+    pub fn run(&mut self, iterations: usize) -> Option<Divergence> {
+        for _ in 0..iterations {
+            let (program, inputs) = self.generate();
+            if let Some(divergence) = self.check(&program, &inputs) {
+                return Some(self.minimize(divergence));
+            }
+        }
+        None
+    }
+
+    /// Runs a program on every backend. Returns their outcomes if they do not agree.
+    pub fn check(&self, program: &[Value], inputs: &[Value]) -> Option<Divergence> {
+        let outcomes: Vec<(String, Outcome)> = self
+            .backends
+            .iter()
+            .map(|(name, backend)| (name.clone(), backend.run(program, inputs, self.max_steps)))
+            .collect();
+        if outcomes
+            .iter()
+            .any(|(_, outcome)| outcome.is_inconclusive())
+        {
+            return None;
+        }
+        if outcomes.windows(2).all(|pair| pair[0].1 == pair[1].1) {
+            return None;
+        }
+        Some(Divergence {
+            program: program.to_vec(),
+            inputs: inputs.to_vec(),
+            outcomes,
+        })
+    }
+
+    /// Shrinks a divergent program and its inputs while the backends still disagree on them:
+    /// inputs and runs of words are removed, then words are brought closer to zero, until
+    /// nothing changes.
+    pub fn minimize(&self, mut divergence: Divergence) -> Divergence {
+        loop {
+            let mut shrunk = false;
+            for index in (0..divergence.inputs.len()).rev() {
+                let mut inputs = divergence.inputs.clone();
+                inputs.remove(index);
+                if let Some(smaller) = self.check(&divergence.program, &inputs) {
+                    divergence = smaller;
+                    shrunk = true;
+                }
+            }
+            // whole instructions first
+            for length in (1..=MAX_INSTRUCTION_LENGTH).rev() {
+                let mut start = divergence.program.len().saturating_sub(length);
+                while start + length <= divergence.program.len() {
+                    let mut program = divergence.program.clone();
+                    program.drain(start..start + length);
+                    if let Some(smaller) = self.check(&program, &divergence.inputs) {
+                        divergence = smaller;
+                        shrunk = true;
+                    }
+                    if start == 0 {
+                        break;
+                    }
+                    start -= 1;
+                }
+            }
+            for index in 0..divergence.program.len() {
+                let word = divergence.program[index];
+                for &candidate in &[0, word / 2, word - word.signum()] {
+                    if candidate == word {
+                        continue;
+                    }
+                    let mut program = divergence.program.clone();
+                    program[index] = candidate;
+                    if let Some(smaller) = self.check(&program, &divergence.inputs) {
+                        divergence = smaller;
+                        shrunk = true;
+                        break;
+                    }
+                }
+            }
+            if !shrunk {
+                return divergence;
+            }
+        }
+    }
+
+    /// A random program ending with a halt, and its inputs
+    pub fn generate(&mut self) -> (Vec<Value>, Vec<Value>) {
+        // the layout comes first, for jumps to know where the instructions are
+        let count = 1 + self.random.below(MAX_INSTRUCTIONS);
+        let mut opcodes: Vec<Opcode> = (0..count)
+            // any opcode but the halt, which comes last
+            .map(|_| BUILTIN_OPCODES[self.random.below(BUILTIN_OPCODES.len() - 1)].opcode)
+            .collect();
+        opcodes.push(Opcode::Halt);
+        let mut starts = Vec::with_capacity(opcodes.len());
+        let mut length = 0;
+        for opcode in &opcodes {
+            starts.push(length as Value);
+            length += instruction_length(*opcode).max(1);
+        }
+        // room for data after the program
+        let length = length as Value + 4;
+
+        let mut program = Vec::new();
+        for opcode in opcodes {
+            let parameter_count = instruction_length(opcode).max(1) - 1;
+            let mut instruction = opcode_number(&opcode);
+            let mut parameters = Vec::with_capacity(parameter_count);
+            for index in 0..parameter_count {
+                let writes = written_parameter(&opcode) == Some(index);
+                let jump_target =
+                    index == 1 && (opcode == Opcode::JumpIfTrue || opcode == Opcode::JumpIfFalse);
+                // 0: position, 1: immediate, 2: relative. Writes are rarely in immediate mode.
+                let mode = match self.random.below(10) {
+                    0..=3 => 0,
+                    4..=6 if !writes => 1,
+                    4..=8 => 2,
+                    _ => self.random.below(3),
+                };
+                instruction += mode as Value * 10i64.pow(index as u32 + 2);
+                parameters.push(match mode {
+                    1 if jump_target => starts[self.random.below(starts.len())],
+                    1 if opcode == Opcode::AdjustRelativeBase => self.random.between(-4, 8),
+                    1 => self.random.between(-20, 20),
+                    2 => self.random.between(-4, length),
+                    _ => self.random.between(0, length),
+                });
+            }
+            program.push(instruction);
+            program.extend(parameters);
+        }
+        program.extend((0..self.random.below(4)).map(|_| self.random.between(-20, 20)));
+
+        let inputs = (0..self.random.below(MAX_INPUTS + 1))
+            .map(|_| self.random.between(-20, 20))
+            .collect();
+        (program, inputs)
+    }
+}
+
+fn interpreter<M: MemoryBackend<Value>>(program: &[Value]) -> Interpreter<Value, M> {
+    let mut interpreter = Interpreter::from_memory(Memory::new(M::from_values(program.to_vec())));
+    interpreter.set_memory_limit(Some(MEMORY_LIMIT));
+    interpreter
+}
+
+fn symbolic_backend(program: &[Value], inputs: &[Value], max_steps: u64) -> Outcome {
+    let mut solver = Solver::new(program.to_vec());
+    for input in inputs {
+        solver.queue_input(*input);
+    }
+    solver.set_max_steps(max_steps);
+    solver.set_memory_limit(Some(MEMORY_LIMIT));
+    let (run, result) = solver.execute_partially(&[]);
+    let stop = match result {
+        Ok(()) => Stop::Halted,
+        Err(SymbolicError::Execution(error)) => Stop::Error(error.kind().clone()),
+        Err(SymbolicError::MissingInput(_)) => Stop::MissingInput,
+        Err(_) => Stop::StepLimit,
+    };
+    let (memory, outputs) = run.concrete_values();
+    Outcome::new(stop, outputs, memory)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::parse_instruction_definition;
+
+    #[test]
+    fn test_backends_agree() {
+        let mut fuzzer = Fuzzer::new(2019);
+        if let Some(divergence) = fuzzer.run(2000) {
+            panic!("{}", divergence);
+        }
+    }
+
+    #[test]
+    fn test_reference_backend() {
+        // the quine of day 9, and the comparison to 8 of day 5
+        let quine = vec![
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+        let outcome = reference::run(&quine, &[], DEFAULT_MAX_STEPS, MEMORY_LIMIT);
+        assert_eq!(outcome.stop, Stop::Halted);
+        assert_eq!(outcome.outputs, quine);
+        let outcome = reference::run(&[3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8], &[8], 100, 16);
+        assert_eq!(outcome.outputs, vec![1]);
+        let outcome = reference::run(&[1101, 1, 1, 20, 99], &[], 100, 16);
+        let error = ExecutionErrorKind::MemoryLimitExceeded(20);
+        assert_eq!(outcome.stop, Stop::Error(error));
+    }
+
+    #[test]
+    fn test_generated_programs_are_well_formed() {
+        let mut fuzzer = Fuzzer::new(7);
+        for _ in 0..100 {
+            let (program, _) = fuzzer.generate();
+            // valid instructions up to a halt
+            let mut address = 0;
+            loop {
+                let definition = parse_instruction_definition::<Value>(program[address]).unwrap();
+                if definition.opcode == Opcode::Halt {
+                    break;
+                }
+                address += instruction_length(definition.opcode);
+            }
+        }
+    }
+
+    #[test]
+    fn test_minimize_divergence() {
+        // a broken backend that prints negative outputs without their sign
+        let mut fuzzer = Fuzzer::new(1);
+        fuzzer.add_backend("abs", |program: &[Value], inputs: &[Value], max_steps| {
+            let mut interpreter = interpreter::<DenseMemory<Value>>(program);
+            let mut outcome = Outcome::from_interpreter(&mut interpreter, inputs, max_steps);
+            for output in outcome.outputs.iter_mut() {
+                *output = output.abs();
+            }
+            outcome
+        });
+        let divergence = fuzzer.run(1000).unwrap();
+        assert_eq!(divergence.program, vec![104, -1]);
+        assert!(divergence.inputs.is_empty());
+        assert_eq!(divergence.outcomes[0].1.outputs, vec![-1]);
+        assert_eq!(divergence.outcomes[7].1.outputs, vec![1]);
+        // the test would not run the broken backend
+        assert_eq!(divergence.to_regression_test("test_abs"), None);
+        let builtin = Divergence {
+            outcomes: divergence.outcomes[..2].to_vec(),
+            ..divergence
+        };
+        assert_eq!(
+            builtin.to_regression_test("test_abs").unwrap(),
+            "#[test]\nfn test_abs() {\n    let divergence = Fuzzer::new(0).check(&[104, -1], &[]);\n    \
+             assert_eq!(divergence, None);\n}\n"
+        );
+    }
+}
@@ -0,0 +1,272 @@
+use crate::interpreter::{Address, Interpreter};
+use crate::memory::MemoryBackend;
+use crate::word::Word;
+use std::collections::VecDeque;
+
+// What is needed to undo one instruction
+#[derive(Debug, Clone)]
+pub(crate) struct UndoEntry<W> {
+    pub(crate) instruction_pointer: Address,
+    pub(crate) relative_base: W,
+    // the cell the instruction wrote, with its previous value
+    pub(crate) overwritten: Option<(Address, W)>,
+    pub(crate) consumed_input: Option<W>,
+}
+
+// The machine state after `instruction_count` instructions, input queue excepted
+#[derive(Debug, Clone)]
+struct Checkpoint<W, M> {
+    instruction_count: u64,
+    values: M,
+    relative_base: W,
+    instruction_pointer: Address,
+}
+
+// The undo log of the last `capacity` instructions, and a checkpoint every `checkpoint_interval`
+// instructions so that rewinding far back does not have to undo every instruction
+#[derive(Debug, Clone)]
+pub(crate) struct History<W, M> {
+    capacity: usize,
+    checkpoint_interval: u64,
+    // oldest instruction first
+    undo_log: VecDeque<UndoEntry<W>>,
+    checkpoints: VecDeque<Checkpoint<W, M>>,
+}
+
+impl<W: Word, M: MemoryBackend<W>> Interpreter<W, M> {
+    /// Starts recording the last `capacity` instructions, so that they can be undone with
+    /// `step_back` and `rewind_to`. The memory is also copied every `checkpoint_interval`
+    /// instructions, which makes rewinding faster at the cost of memory.
+    pub fn enable_history(&mut self, capacity: usize, checkpoint_interval: u64) {
+        self.history = Some(History {
+            capacity,
+            checkpoint_interval: checkpoint_interval.max(1),
+            undo_log: VecDeque::new(),
+            checkpoints: VecDeque::new(),
+        });
+    }
+
+    pub fn disable_history(&mut self) {
+        self.history = None;
+    }
+
+    /// The oldest instruction count `rewind_to` can go back to
+    pub fn earliest_instruction_count(&self) -> u64 {
+        match &self.history {
+            Some(history) => self.instruction_count - history.undo_log.len() as u64,
+            None => self.instruction_count,
+        }
+    }
+
+    /// Undoes the last instruction. Returns false if it is not in the history.
+    pub fn step_back(&mut self) -> bool {
+        self.instruction_count > 0 && self.rewind_to(self.instruction_count - 1)
+    }
+
+    /// Undoes instructions until `instruction_count` of them have been executed. Inputs consumed
+    /// from the queue by the undone instructions are put back in front of it, while outputs and
+    /// inputs given by the input policy are not taken back. Returns false, and does nothing, if
+    /// `instruction_count` is not in the history.
+    pub fn rewind_to(&mut self, instruction_count: u64) -> bool {
+        if instruction_count < self.earliest_instruction_count()
+            || instruction_count > self.instruction_count
+        {
+            return false;
+        }
+        let history = match &mut self.history {
+            Some(history) => history,
+            None => return instruction_count == self.instruction_count,
+        };
+
+        // jump to the closest checkpoint at or after the target, then undo the remaining
+        // instructions one by one
+        let checkpoint = history
+            .checkpoints
+            .iter()
+            .position(|checkpoint| checkpoint.instruction_count >= instruction_count);
+        if let Some(index) = checkpoint {
+            let checkpoint = history.checkpoints[index].clone();
+            history.checkpoints.truncate(index + 1);
+            let undone = (self.instruction_count - checkpoint.instruction_count) as usize;
+            let kept = history.undo_log.len() - undone;
+            for entry in history.undo_log.drain(kept..).rev() {
+                if let Some(input) = entry.consumed_input {
+                    self.input_queue.push_front(input);
+                }
+            }
+            self.memory.set_values(checkpoint.values);
+            self.memory.relative_base = checkpoint.relative_base;
+            self.instruction_pointer = checkpoint.instruction_pointer;
+            self.instruction_count = checkpoint.instruction_count;
+        }
+
+        while self.instruction_count > instruction_count {
+            let entry = history.undo_log.pop_back().unwrap();
+            if let Some((address, value)) = entry.overwritten {
+                self.memory.set(address, value);
+            }
+            if let Some(input) = entry.consumed_input {
+                self.input_queue.push_front(input);
+            }
+            self.memory.relative_base = entry.relative_base;
+            self.instruction_pointer = entry.instruction_pointer;
+            self.instruction_count -= 1;
+        }
+        let count = self.instruction_count;
+        history
+            .checkpoints
+            .retain(|checkpoint| checkpoint.instruction_count <= count);
+        true
+    }
+
+    // Checkpoints copy the whole memory, so rewinding to one would revert writes that do not
+    // come from the program
+    pub(crate) fn drop_checkpoints(&mut self) {
+        if let Some(history) = &mut self.history {
+            history.checkpoints.clear();
+        }
+    }
+
+    // called after each executed instruction
+    pub(crate) fn record_history(&mut self, entry: UndoEntry<W>) {
+        let history = match &mut self.history {
+            Some(history) => history,
+            None => return,
+        };
+        history.undo_log.push_back(entry);
+        if history.undo_log.len() > history.capacity {
+            history.undo_log.pop_front();
+            let earliest = self.instruction_count - history.undo_log.len() as u64;
+            while let Some(checkpoint) = history.checkpoints.front() {
+                if checkpoint.instruction_count >= earliest {
+                    break;
+                }
+                history.checkpoints.pop_front();
+            }
+        }
+        if self
+            .instruction_count
+            .is_multiple_of(history.checkpoint_interval)
+        {
+            history.checkpoints.push_back(Checkpoint {
+                instruction_count: self.instruction_count,
+                values: self.memory.values.clone(),
+                relative_base: self.memory.relative_base.clone(),
+                instruction_pointer: self.instruction_pointer,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::{IOAction, InputPolicy, Value};
+
+    // (instruction pointer, relative base, memory) after every instruction. Undoing a write does
+    // not shrink the memory back, so only compare the cells the programs below use.
+    type State = (Address, Value, Vec<Value>);
+
+    fn state(interpreter: &Interpreter) -> State {
+        (
+            interpreter.instruction_pointer,
+            interpreter.memory.relative_base,
+            (0..128)
+                .map(|address| interpreter.read_memory(address))
+                .collect(),
+        )
+    }
+
+    fn run_recording_states(interpreter: &mut Interpreter) -> Vec<State> {
+        let mut states = vec![state(interpreter)];
+        while interpreter.step().unwrap() != Some(IOAction::Halt) {
+            states.push(state(interpreter));
+        }
+        states.push(state(interpreter));
+        states
+    }
+
+    #[test]
+    fn test_rewind() {
+        let quine = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+        let mut interpreter: Interpreter = Interpreter::from_code(quine).unwrap();
+        interpreter.enable_history(1000, 7);
+        let states = run_recording_states(&mut interpreter);
+        assert_eq!(interpreter.instruction_count(), 81);
+
+        assert!(interpreter.step_back());
+        assert_eq!(state(&interpreter), states[80]);
+        for &target in &[50, 49, 42, 3, 0] {
+            assert!(interpreter.rewind_to(target));
+            assert_eq!(interpreter.instruction_count(), target);
+            assert_eq!(state(&interpreter), states[target as usize]);
+        }
+        assert!(!interpreter.step_back());
+        assert!(!interpreter.rewind_to(1));
+
+        // the program runs the same way again
+        assert_eq!(run_recording_states(&mut interpreter), states);
+    }
+
+    #[test]
+    fn test_rewind_puts_inputs_back() {
+        // adds two inputs, twice
+        let code = "3,13,3,14,1,13,14,15,4,15,1105,1,0,0,0,0";
+        let mut interpreter: Interpreter = Interpreter::from_code(code).unwrap();
+        interpreter.enable_history(100, 3);
+        for input in &[1, 2, 30, 40] {
+            interpreter.queue_input(*input);
+        }
+        assert_eq!(
+            interpreter.run_until_block().unwrap(),
+            IOAction::ProduceOutput(3)
+        );
+        assert_eq!(
+            interpreter.run_until_block().unwrap(),
+            IOAction::ProduceOutput(70)
+        );
+        assert_eq!(interpreter.instruction_count(), 9);
+
+        assert!(interpreter.rewind_to(1));
+        assert_eq!(interpreter.input_queue, vec![2, 30, 40]);
+        assert_eq!(
+            interpreter.run_until_block().unwrap(),
+            IOAction::ProduceOutput(3)
+        );
+    }
+
+    #[test]
+    fn test_rewind_ignores_default_inputs() {
+        let echo = "3,9,4,9,1105,1,0,0,0,0";
+        let mut interpreter: Interpreter = Interpreter::from_code(echo).unwrap();
+        interpreter.enable_history(100, 10);
+        interpreter.set_input_policy(InputPolicy::Default(-1));
+        interpreter.queue_input(4);
+        for expected in &[4, -1] {
+            assert_eq!(
+                interpreter.run_until_block().unwrap(),
+                IOAction::ProduceOutput(*expected)
+            );
+        }
+        assert!(interpreter.rewind_to(0));
+        assert_eq!(interpreter.input_queue, vec![4]);
+    }
+
+    #[test]
+    fn test_history_capacity() {
+        let quine = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+        let mut interpreter: Interpreter = Interpreter::from_code(quine).unwrap();
+        interpreter.enable_history(10, 4);
+        let states = run_recording_states(&mut interpreter);
+        assert_eq!(interpreter.earliest_instruction_count(), 71);
+        let history = interpreter.history.as_ref().unwrap();
+        assert!(history
+            .checkpoints
+            .iter()
+            .all(|c| c.instruction_count >= 71));
+
+        assert!(!interpreter.rewind_to(70));
+        assert!(interpreter.rewind_to(71));
+        assert_eq!(state(&interpreter), states[71]);
+    }
+}
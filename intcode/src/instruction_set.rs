@@ -0,0 +1,571 @@
+use crate::arithmetic::ArithmeticMode;
+use crate::error::ExecutionErrorKind;
+use crate::interpreter::{
+    instruction_length, parse_instruction_modes, InstructionDefinition, Opcode, Value,
+    MAX_PARAMETERS,
+};
+use crate::word::Word;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// What an instruction does with one of its parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    /// Reads a value
+    Read,
+    /// Writes the result of the instruction, so it can not be in immediate mode
+    Write,
+    /// The address the instruction jumps to when its handler returns `Effect::Jump`. Only read
+    /// then, so it is not given to the handler.
+    Jump,
+}
+
+/// What an instruction does once the handler of its opcode ran, see `InstructionSet::register`.
+/// Effects needing a parameter the opcode does not have stop the program with an
+/// `InvalidEffect` error.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Effect<W = Value> {
+    /// Goes on with the next instruction
+    Next,
+    /// Writes the value to the write parameter, then goes on with the next instruction
+    Write(W),
+    /// Writes the next input to the write parameter, like opcode 3
+    Input,
+    /// Jumps to the address in the jump parameter, like opcodes 5 and 6
+    Jump,
+    /// Produces an output, like opcode 4
+    Output(W),
+    /// Adds the value to the relative base, like opcode 9
+    AdjustRelativeBase(W),
+    /// Halts the program, like opcode 99
+    Halt,
+}
+
+// number, mnemonic and parameters of a built-in opcode
+pub(crate) struct BuiltinOpcode {
+    pub(crate) opcode: Opcode,
+    pub(crate) number: u8,
+    pub(crate) mnemonic: &'static str,
+    pub(crate) roles: &'static [Role],
+}
+
+const BINARY: &[Role] = &[Role::Read, Role::Read, Role::Write];
+const JUMP: &[Role] = &[Role::Read, Role::Jump];
+
+pub(crate) const BUILTIN_OPCODES: [BuiltinOpcode; 10] = [
+    builtin(Opcode::Addition, 1, "add", BINARY),
+    builtin(Opcode::Multiplication, 2, "mul", BINARY),
+    builtin(Opcode::Input, 3, "in", &[Role::Write]),
+    builtin(Opcode::Output, 4, "out", &[Role::Read]),
+    builtin(Opcode::JumpIfTrue, 5, "jt", JUMP),
+    builtin(Opcode::JumpIfFalse, 6, "jf", JUMP),
+    builtin(Opcode::LessThan, 7, "lt", BINARY),
+    builtin(Opcode::Equals, 8, "eq", BINARY),
+    builtin(Opcode::AdjustRelativeBase, 9, "arb", &[Role::Read]),
+    builtin(Opcode::Halt, 99, "hlt", &[]),
+];
+
+const fn builtin(
+    opcode: Opcode,
+    number: u8,
+    mnemonic: &'static str,
+    roles: &'static [Role],
+) -> BuiltinOpcode {
+    BuiltinOpcode {
+        opcode,
+        number,
+        mnemonic,
+        roles,
+    }
+}
+
+// the built-in opcode of each number below 100
+pub(crate) const BUILTIN_NUMBERS: [Option<Opcode>; 100] = {
+    let mut numbers = [None; 100];
+    let mut index = 0;
+    while index < BUILTIN_OPCODES.len() {
+        numbers[BUILTIN_OPCODES[index].number as usize] = Some(BUILTIN_OPCODES[index].opcode);
+        index += 1;
+    }
+    numbers
+};
+
+pub(crate) fn builtin_opcode(opcode: &Opcode) -> &'static BuiltinOpcode {
+    BUILTIN_OPCODES
+        .iter()
+        .find(|builtin| builtin.opcode == *opcode)
+        .expect("registered opcodes are described by their instruction set")
+}
+
+type BuiltinHandler<W> = fn(&[W], ArithmeticMode) -> Result<Effect<W>, ExecutionErrorKind<W>>;
+type RegisteredHandler<W> = dyn Fn(&[W]) -> Result<Effect<W>, ExecutionErrorKind<W>> + Send + Sync;
+
+// What runs an instruction, given the values of its read parameters. Built-in handlers also get
+// the arithmetic mode of the interpreter.
+#[derive(Clone)]
+pub(crate) enum Handler<W> {
+    Builtin(BuiltinHandler<W>),
+    Registered(Arc<RegisteredHandler<W>>),
+}
+
+impl<W> Handler<W> {
+    pub(crate) fn run(
+        &self,
+        operands: &[W],
+        arithmetic_mode: ArithmeticMode,
+    ) -> Result<Effect<W>, ExecutionErrorKind<W>> {
+        match self {
+            Handler::Builtin(handler) => handler(operands, arithmetic_mode),
+            Handler::Registered(handler) => handler(operands),
+        }
+    }
+}
+
+// The handlers of the built-in opcodes, which the profiles declare like registered ones
+fn builtin_handler<W: Word>(opcode: Opcode) -> Handler<W> {
+    Handler::Builtin(match opcode {
+        Opcode::Addition => |operands, arithmetic_mode| {
+            Ok(Effect::Write(
+                arithmetic_mode.add(&operands[0], &operands[1])?,
+            ))
+        },
+        Opcode::Multiplication => |operands, arithmetic_mode| {
+            Ok(Effect::Write(
+                arithmetic_mode.mul(&operands[0], &operands[1])?,
+            ))
+        },
+        Opcode::Input => |_, _| Ok(Effect::Input),
+        Opcode::Output => |operands, _| Ok(Effect::Output(operands[0].clone())),
+        Opcode::JumpIfTrue => |operands, _| {
+            Ok(if operands[0].is_zero() {
+                Effect::Next
+            } else {
+                Effect::Jump
+            })
+        },
+        Opcode::JumpIfFalse => |operands, _| {
+            Ok(if operands[0].is_zero() {
+                Effect::Jump
+            } else {
+                Effect::Next
+            })
+        },
+        Opcode::LessThan => |operands, _| {
+            Ok(Effect::Write(W::from_i64(
+                (operands[0] < operands[1]) as i64,
+            )))
+        },
+        Opcode::Equals => |operands, _| {
+            Ok(Effect::Write(W::from_i64(
+                (operands[0] == operands[1]) as i64,
+            )))
+        },
+        Opcode::AdjustRelativeBase => {
+            |operands, _| Ok(Effect::AdjustRelativeBase(operands[0].clone()))
+        }
+        Opcode::Halt => |_, _| Ok(Effect::Halt),
+        Opcode::Registered(_) => unreachable!("registered opcodes are not built-in"),
+    })
+}
+
+// An opcode of an instruction set, built-in or registered
+#[derive(Clone)]
+pub(crate) struct OpcodeEntry<W> {
+    pub(crate) opcode: Opcode,
+    pub(crate) mnemonic: &'static str,
+    pub(crate) roles: Vec<Role>,
+    pub(crate) handler: Handler<W>,
+}
+
+/// The opcodes an `Interpreter` runs, with the handler running each of them, and the parameter
+/// modes it accepts.
+///
+/// The profiles follow the puzzles that introduced each part of Intcode: `day_2` only adds,
+/// multiplies and halts, in position mode; `day_5` adds IO, jumps, comparisons and immediate
+/// mode; `day_9` adds the relative base and is the default. More opcodes can be registered on any
+/// of them, e.g. to try out an extension of the language.
+///
+/// The tools of this crate (the assembler, disassembler, symbolic solver, fuzzer and compiler)
+/// only know the built-in opcodes: the compiler leaves registered ones to the interpreter, and
+/// the others see them as data.
+#[derive(Clone)]
+pub struct InstructionSet<W = Value> {
+    // the opcode of each number below 100, registered opcodes being `Opcode::Registered`
+    opcodes: Vec<Option<OpcodeEntry<W>>>,
+    // parameter modes are numbered in the order they were introduced, so a profile supports
+    // the first `modes` of them
+    modes: i64,
+}
+
+impl<W: Word> InstructionSet<W> {
+    /// Opcodes 1, 2 and 99, in position mode
+    pub fn day_2() -> Self {
+        Self::builtin(&[1, 2, 99], 1)
+    }
+
+    /// Opcodes 1 to 8 and 99, in position and immediate mode
+    pub fn day_5() -> Self {
+        Self::builtin(&[1, 2, 3, 4, 5, 6, 7, 8, 99], 2)
+    }
+
+    /// All of Intcode: opcodes 1 to 9 and 99, in position, immediate and relative mode
+    pub fn day_9() -> Self {
+        Self::builtin(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 99], 3)
+    }
+
+    /// The profile called `name`: "day-2", "day-5" or "day-9"
+    pub fn profile(name: &str) -> Option<Self> {
+        match name {
+            "day-2" => Some(Self::day_2()),
+            "day-5" => Some(Self::day_5()),
+            "day-9" => Some(Self::day_9()),
+            _ => None,
+        }
+    }
+
+    fn builtin(numbers: &[usize], modes: i64) -> Self {
+        let mut opcodes = vec![None; 100];
+        for &number in numbers {
+            let builtin = BUILTIN_NUMBERS[number].map(|opcode| builtin_opcode(&opcode));
+            opcodes[number] = builtin.map(|builtin| OpcodeEntry {
+                opcode: builtin.opcode,
+                mnemonic: builtin.mnemonic,
+                roles: builtin.roles.to_vec(),
+                handler: builtin_handler(builtin.opcode),
+            });
+        }
+        InstructionSet { opcodes, modes }
+    }
+}
+
+impl<W> InstructionSet<W> {
+    /// Adds opcode `number`, whose parameters play `roles`. When an instruction with this opcode
+    /// runs, `handler` is given the values of its read parameters, in order, and tells the
+    /// interpreter what to do next, as the built-in opcodes do.
+    ///
+    /// Instructions with a registered opcode accept the parameter modes of the set, and are
+    /// recorded by traces and histories like the others.
+    ///
+    /// Panics if `number` is not in 1..=99 or is already in the set, or if there are more than 3
+    /// parameters or more than one write parameter.
+    pub fn register(
+        &mut self,
+        number: u8,
+        mnemonic: &'static str,
+        roles: &[Role],
+        handler: impl Fn(&[W]) -> Result<Effect<W>, ExecutionErrorKind<W>> + Send + Sync + 'static,
+    ) {
+        assert!((1..100).contains(&number), "invalid opcode {}", number);
+        assert!(
+            self.opcodes[number as usize].is_none(),
+            "opcode {} is already in the instruction set",
+            number
+        );
+        assert!(
+            roles.len() <= MAX_PARAMETERS,
+            "instructions take at most {} parameters",
+            MAX_PARAMETERS
+        );
+        assert!(
+            roles.iter().filter(|&&role| role == Role::Write).count() <= 1,
+            "instructions write at most one parameter"
+        );
+        assert!(
+            roles.iter().filter(|&&role| role == Role::Jump).count() <= 1,
+            "instructions jump to at most one parameter"
+        );
+        self.opcodes[number as usize] = Some(OpcodeEntry {
+            opcode: Opcode::Registered(number),
+            mnemonic,
+            roles: roles.to_vec(),
+            handler: Handler::Registered(Arc::new(handler)),
+        });
+    }
+
+    /// Takes opcode `number` out of the set, whether it is built-in or registered. Returns
+    /// whether it was in the set.
+    pub fn remove(&mut self, number: u8) -> bool {
+        match self.opcodes.get_mut(number as usize) {
+            Some(opcode) => opcode.take().is_some(),
+            None => false,
+        }
+    }
+
+    pub fn contains(&self, number: u8) -> bool {
+        self.entry(number).is_some()
+    }
+
+    pub fn mnemonic(&self, number: u8) -> Option<&'static str> {
+        self.entry(number).map(|entry| entry.mnemonic)
+    }
+
+    pub fn roles(&self, number: u8) -> Option<&[Role]> {
+        self.entry(number).map(|entry| &entry.roles[..])
+    }
+
+    /// Whether relative mode, introduced on day 9, is accepted
+    pub fn supports_relative_mode(&self) -> bool {
+        self.modes > 2
+    }
+
+    /// Whether immediate mode, introduced on day 5, is accepted
+    pub fn supports_immediate_mode(&self) -> bool {
+        self.modes > 1
+    }
+
+    pub(crate) fn entry(&self, number: u8) -> Option<&OpcodeEntry<W>> {
+        self.opcodes.get(number as usize)?.as_ref()
+    }
+
+    pub(crate) fn decode(
+        &self,
+        instruction_code: i64,
+    ) -> Result<InstructionDefinition, ExecutionErrorKind<W>> {
+        if instruction_code < 0 {
+            return Err(ExecutionErrorKind::UnknownOpcode);
+        }
+        let number = (instruction_code % 100) as u8;
+        let entry = self
+            .entry(number)
+            .ok_or(ExecutionErrorKind::UnknownOpcode)?;
+        let mut modes = instruction_code / 100;
+        if self.modes < 3 {
+            while modes > 0 {
+                if modes % 10 >= self.modes {
+                    return Err(ExecutionErrorKind::InvalidParameterMode);
+                }
+                modes /= 10;
+            }
+        }
+        let instruction_modes = parse_instruction_modes(instruction_code / 100)
+            .ok_or(ExecutionErrorKind::InvalidParameterMode)?;
+        let length = match entry.opcode {
+            Opcode::Registered(_) => entry.roles.len() + 1,
+            opcode => instruction_length(opcode),
+        };
+        Ok(InstructionDefinition {
+            opcode: entry.opcode,
+            number,
+            instruction_modes,
+            length,
+        })
+    }
+}
+
+impl<W: Word> InstructionSet<W> {
+    // Instructions are decoded from an i64, whatever the word type: wider words can not be valid
+    // instructions anyway
+    pub(crate) fn decode_word(
+        &self,
+        instruction: &W,
+    ) -> Result<InstructionDefinition, ExecutionErrorKind<W>> {
+        self.decode(
+            instruction
+                .to_i64()
+                .ok_or(ExecutionErrorKind::UnknownOpcode)?,
+        )
+    }
+}
+
+impl<W: Word> Default for InstructionSet<W> {
+    fn default() -> Self {
+        Self::day_9()
+    }
+}
+
+// Handlers can not be printed, so opcodes are listed by number and mnemonic
+impl<W> fmt::Debug for InstructionSet<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let opcodes: BTreeMap<usize, &str> = self
+            .opcodes
+            .iter()
+            .enumerate()
+            .filter_map(|(number, entry)| Some((number, entry.as_ref()?.mnemonic)))
+            .collect();
+        f.debug_struct("InstructionSet")
+            .field("opcodes", &opcodes)
+            .field("modes", &self.modes)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::Io;
+    use crate::error::ExecutionError;
+    use crate::interpreter::{IOAction, Interpreter};
+    use crate::trace::Profiler;
+    use std::sync::Mutex;
+
+    fn run(interpreter: &mut Interpreter) -> Result<Vec<Value>, ExecutionError> {
+        let mut outputs = Vec::new();
+        interpreter.run_with(Io::new((), &mut outputs))?;
+        Ok(outputs)
+    }
+
+    // opcode 10: writes the first parameter modulo the second one
+    fn with_modulo(mut instruction_set: InstructionSet) -> InstructionSet {
+        instruction_set.register(
+            10,
+            "mod",
+            &[Role::Read, Role::Read, Role::Write],
+            |operands| match operands[0].checked_rem(operands[1]) {
+                Some(remainder) => Ok(Effect::Write(remainder)),
+                None => Err(ExecutionErrorKind::ArithmeticOverflow),
+            },
+        );
+        instruction_set
+    }
+
+    #[test]
+    fn test_profiles() {
+        let day_2 = InstructionSet::<Value>::profile("day-2").unwrap();
+        assert_eq!(day_2.mnemonic(2), Some("mul"));
+        assert!(!day_2.contains(3));
+        assert!(!day_2.supports_immediate_mode());
+        let day_5 = InstructionSet::<Value>::profile("day-5").unwrap();
+        assert_eq!(day_5.roles(5), Some(&[Role::Read, Role::Jump][..]));
+        assert!(!day_5.contains(9));
+        assert!(day_5.supports_immediate_mode() && !day_5.supports_relative_mode());
+        let day_9 = InstructionSet::<Value>::profile("day-9").unwrap();
+        assert!(day_9.contains(9) && day_9.supports_relative_mode());
+        assert!(InstructionSet::<Value>::profile("day-10").is_none());
+
+        let run_on = |code: &str, instruction_set: InstructionSet| {
+            let mut interpreter: Interpreter = Interpreter::from_code(code).unwrap();
+            interpreter.set_instruction_set(instruction_set);
+            run(&mut interpreter).map_err(|error| error.kind().clone())
+        };
+        assert_eq!(run_on("1,0,0,0,99", InstructionSet::day_2()), Ok(vec![]));
+        assert_eq!(
+            run_on("104,7,99", InstructionSet::day_2()),
+            Err(ExecutionErrorKind::UnknownOpcode)
+        );
+        assert_eq!(
+            run_on("1101,1,1,0,99", InstructionSet::day_2()),
+            Err(ExecutionErrorKind::InvalidParameterMode)
+        );
+        assert_eq!(run_on("104,7,99", InstructionSet::day_5()), Ok(vec![7]));
+        assert_eq!(
+            run_on("204,0,99", InstructionSet::day_5()),
+            Err(ExecutionErrorKind::InvalidParameterMode)
+        );
+        assert_eq!(
+            run_on("109,1,99", InstructionSet::day_5()),
+            Err(ExecutionErrorKind::UnknownOpcode)
+        );
+        assert_eq!(run_on("204,0,99", InstructionSet::day_9()), Ok(vec![204]));
+    }
+
+    #[test]
+    fn test_registered_opcodes() {
+        let mut instruction_set = with_modulo(InstructionSet::day_9());
+        // opcode 11: jumps to its second parameter if the first one is even
+        instruction_set.register(11, "jeven", &[Role::Read, Role::Jump], |operands| {
+            Ok(match operands[0] % 2 {
+                0 => Effect::Jump,
+                _ => Effect::Next,
+            })
+        });
+        // opcode 12: outputs twice its parameter, then halts if it is 0
+        instruction_set.register(12, "dbl", &[Role::Read], |operands| {
+            Ok(match operands[0] {
+                0 => Effect::Halt,
+                value => Effect::Output(2 * value),
+            })
+        });
+        // 17 % 5 = 2, doubled; 5 + 9 = 14 is even, so the output of 2 is jumped over; then halts
+        // on the registered opcode, without moving to the halt instruction behind it
+        let code = "1110,17,5,19,12,19,1101,5,9,20,1011,20,15,4,19,112,0,99,0,0,0";
+        let mut interpreter: Interpreter = Interpreter::from_code(code).unwrap();
+        interpreter.set_instruction_set(instruction_set);
+        interpreter.enable_history(100, 10);
+        let profiler = Arc::new(Mutex::new(Profiler::new()));
+        interpreter.set_trace_sink(profiler.clone());
+        assert_eq!(run(&mut interpreter).unwrap(), vec![4]);
+        assert_eq!(interpreter.instruction_pointer(), 15);
+        let profiler = profiler.lock().unwrap();
+        assert_eq!(profiler.count_of("mod"), 1);
+        assert_eq!(profiler.count_of("jeven"), 1);
+        assert_eq!(profiler.count_of("dbl"), 2);
+        assert_eq!(profiler.count_of("out"), 0);
+        // the write of the registered opcode is undone like any other
+        assert_eq!(interpreter.read_memory(19), 2);
+        while interpreter.step_back() {}
+        assert_eq!(interpreter.read_memory(19), 0);
+
+        // built-in opcodes have handlers too, which can be replaced
+        let mut instruction_set = InstructionSet::day_9();
+        instruction_set.remove(1);
+        instruction_set.register(
+            1,
+            "sub",
+            &[Role::Read, Role::Read, Role::Write],
+            |operands| Ok(Effect::Write(operands[0] - operands[1])),
+        );
+        let mut interpreter: Interpreter = Interpreter::from_code("1101,5,7,0,4,0,99").unwrap();
+        interpreter.set_instruction_set(instruction_set);
+        assert_eq!(run(&mut interpreter).unwrap(), vec![-2]);
+    }
+
+    #[test]
+    fn test_registered_errors() {
+        let mut interpreter: Interpreter = Interpreter::from_code("1110,1,0,5,99").unwrap();
+        interpreter.set_instruction_set(with_modulo(InstructionSet::day_9()));
+        let error = run(&mut interpreter).unwrap_err();
+        assert_eq!(error.kind(), &ExecutionErrorKind::ArithmeticOverflow);
+        assert_eq!(error.instruction_pointer(), 0);
+        // immediate mode on the write parameter
+        let mut interpreter: Interpreter = Interpreter::from_code("11110,7,5,5,99").unwrap();
+        interpreter.set_instruction_set(with_modulo(InstructionSet::day_9()));
+        let error = run(&mut interpreter).unwrap_err();
+        assert_eq!(error.kind(), &ExecutionErrorKind::WriteInImmediateMode);
+        // effects the parameters of the opcode do not allow
+        let mut instruction_set = InstructionSet::day_9();
+        instruction_set.register(10, "bad", &[Role::Read], |operands| {
+            Ok(match operands[0] {
+                0 => Effect::Write(1),
+                _ => Effect::Jump,
+            })
+        });
+        for code in &["110,0,99", "110,1,99"] {
+            let mut interpreter: Interpreter = Interpreter::from_code(code).unwrap();
+            interpreter.set_instruction_set(instruction_set.clone());
+            let error = run(&mut interpreter).unwrap_err();
+            assert_eq!(error.kind(), &ExecutionErrorKind::InvalidEffect);
+            assert_eq!(interpreter.instruction_pointer(), 0);
+        }
+        // unknown to the default set
+        let mut interpreter: Interpreter = Interpreter::from_code("1110,7,5,5,99").unwrap();
+        let error = run(&mut interpreter).unwrap_err();
+        assert_eq!(error.kind(), &ExecutionErrorKind::UnknownOpcode);
+        // the removed opcodes are unknown too
+        let mut instruction_set = InstructionSet::day_9();
+        assert!(instruction_set.remove(4));
+        assert!(!instruction_set.remove(4));
+        let mut interpreter: Interpreter = Interpreter::from_code("104,7,99").unwrap();
+        interpreter.set_instruction_set(instruction_set);
+        let error = run(&mut interpreter).unwrap_err();
+        assert_eq!(error.kind(), &ExecutionErrorKind::UnknownOpcode);
+    }
+
+    #[test]
+    #[should_panic(expected = "already in the instruction set")]
+    fn test_register_builtin() {
+        with_modulo(InstructionSet::day_9()).register(1, "add", &[], |_| Ok(Effect::Next));
+    }
+
+    #[test]
+    fn test_compiled() {
+        // the compiler leaves registered opcodes to the interpreter
+        let mut interpreter: Interpreter =
+            Interpreter::from_code("1110,17,5,11,1001,11,1,11,4,11,99").unwrap();
+        interpreter.set_instruction_set(with_modulo(InstructionSet::day_9()));
+        let mut compiled = interpreter.compile();
+        assert_eq!(compiled.run_until_block(), Ok(IOAction::ProduceOutput(3)));
+        assert_eq!(compiled.run_until_block(), Ok(IOAction::Halt));
+    }
+}
@@ -0,0 +1,1277 @@
+use crate::arithmetic::ArithmeticMode;
+use crate::disassembler::{decode_line, Line, Operand};
+use crate::error::{ExecutionError, ExecutionErrorKind};
+use crate::history::{History, UndoEntry};
+use crate::instruction_set::{
+    builtin_opcode, Effect, InstructionSet, OpcodeEntry, Role, BUILTIN_NUMBERS,
+};
+use crate::memory::{DenseMemory, MemoryBackend};
+use crate::mmio::DeviceMap;
+use crate::trace::{TraceEvent, TraceSink};
+use crate::word::Word;
+use std::collections::VecDeque;
+use std::error;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::sync::Arc;
+
+pub type Address = usize;
+/// The default word type
+pub type Value = i64;
+
+// Instructions at higher addresses are decoded every time they run
+const MAX_CACHED_ADDRESS: Address = 1 << 20;
+// The number of instructions a machine decodes before it starts caching them: filling the cache
+// costs more than it saves on short runs, e.g. the clones of day 7 running a few dozen
+// instructions each
+const DECODE_CACHE_WARM_UP: u32 = 1_000;
+
+#[derive(Debug, Clone)]
+pub(crate) struct Memory<W, M> {
+    pub(crate) values: M,
+    pub(crate) relative_base: W,
+    // maximum number of cells the backend may allocate, None for no limit
+    pub(crate) limit: Option<usize>,
+    // address written by the last instruction and the value it overwrote, for tracing and
+    // reverse stepping
+    pub(crate) last_write: Option<(Address, W)>,
+    // the decoded instruction at each address, None if not decoded yet or overwritten since.
+    // None when the cache is disabled.
+    decoded: Option<Vec<Option<InstructionDefinition>>>,
+    // instructions to decode before the cache is used, see DECODE_CACHE_WARM_UP
+    warm_up_decodes: u32,
+    // host devices the program reads and writes through, instead of the cells of their ranges
+    pub(crate) devices: DeviceMap<W>,
+}
+
+// The decode cache does not change the state of the machine, and devices can not be compared
+impl<W: PartialEq, M: PartialEq> PartialEq for Memory<W, M> {
+    fn eq(&self, other: &Self) -> bool {
+        self.values == other.values
+            && self.relative_base == other.relative_base
+            && self.limit == other.limit
+            && self.last_write == other.last_write
+    }
+}
+
+impl<W: Word, M: MemoryBackend<W>> Memory<W, M> {
+    pub(crate) fn new(values: M) -> Memory<W, M> {
+        Memory {
+            values,
+            relative_base: W::from_i64(0),
+            limit: M::DEFAULT_LIMIT,
+            last_write: None,
+            decoded: Some(Vec::new()),
+            warm_up_decodes: DECODE_CACHE_WARM_UP,
+            devices: DeviceMap::new(),
+        }
+    }
+
+    fn from_string(input: &str) -> Result<Memory<W, M>, W::ParseError> {
+        Ok(Memory::new(M::from_values(parse_code(input)?)))
+    }
+
+    pub(crate) fn get(&self, address: Address) -> W {
+        match self.values.get(address) {
+            Some(value) => value.clone(),
+            None => W::from_i64(0),
+        }
+    }
+
+    pub(crate) fn set(&mut self, address: Address, value: W) {
+        self.values.set(address, value);
+        if let Some(Some(decoded)) = self.decoded.as_mut().map(|cache| cache.get_mut(address)) {
+            *decoded = None;
+        }
+    }
+
+    // replaces every cell, e.g. when restoring a checkpoint
+    pub(crate) fn set_values(&mut self, values: M) {
+        self.values = values;
+        self.clear_decode_cache();
+    }
+
+    fn clear_decode_cache(&mut self) {
+        if let Some(cache) = &mut self.decoded {
+            cache.clear();
+        }
+    }
+
+    fn set_decode_cache(&mut self, enabled: bool) {
+        self.decoded = if enabled { Some(Vec::new()) } else { None };
+    }
+
+    pub(crate) fn decode(
+        &mut self,
+        address: Address,
+        instruction_set: &InstructionSet<W>,
+    ) -> Result<InstructionDefinition, ExecutionErrorKind<W>> {
+        let cache = match &mut self.decoded {
+            Some(cache) if self.warm_up_decodes == 0 => cache,
+            Some(_) => {
+                self.warm_up_decodes -= 1;
+                return instruction_set.decode_word(&self.get(address));
+            }
+            None => return instruction_set.decode_word(&self.get(address)),
+        };
+        if let Some(Some(definition)) = cache.get(address) {
+            return Ok(*definition);
+        }
+        let word = match self.values.get(address) {
+            Some(word) => word.clone(),
+            None => W::from_i64(0),
+        };
+        let definition = instruction_set.decode_word(&word)?;
+        if address < MAX_CACHED_ADDRESS {
+            if address >= cache.len() {
+                cache.resize(address + 1, None);
+            }
+            cache[address] = Some(definition);
+        }
+        Ok(definition)
+    }
+
+    pub(crate) fn checked_set(
+        &mut self,
+        address: Address,
+        value: W,
+    ) -> Result<(), ExecutionErrorKind<W>> {
+        // the IO of devices is not undone, like outputs
+        if self.devices.is_mapped(address) {
+            return self.store(address, value);
+        }
+        let overwritten = self.get(address);
+        self.store(address, value)?;
+        self.last_write = Some((address, overwritten));
+        Ok(())
+    }
+
+    // a write of the program, without keeping the overwritten value
+    pub(crate) fn store(
+        &mut self,
+        address: Address,
+        value: W,
+    ) -> Result<(), ExecutionErrorKind<W>> {
+        if let Some((device, offset)) = self.devices.device_at(address)? {
+            device.write(offset, value);
+            return Ok(());
+        }
+        if let Some(limit) = self.limit {
+            if self.values.allocated_cells_after_write(address) > limit {
+                return Err(ExecutionErrorKind::MemoryLimitExceeded(address));
+            }
+        }
+        self.set(address, value);
+        Ok(())
+    }
+
+    // a read of the program, from the device mapped at address if there is one
+    pub(crate) fn load(&mut self, address: Address) -> Result<W, ExecutionErrorKind<W>> {
+        match self.devices.device_at(address)? {
+            Some((device, offset)) => Ok(device.read(offset)),
+            None => Ok(self.get(address)),
+        }
+    }
+
+    // the address an operand refers to, None for immediate operands
+    fn operand_address(&self, parameter: &W, mode: &InstructionMode) -> Option<Address> {
+        match mode {
+            InstructionMode::Position => to_address(parameter).ok(),
+            InstructionMode::Immediate => None,
+            InstructionMode::Relative => self.relative_address(parameter).ok(),
+        }
+    }
+
+    pub(crate) fn read_value(
+        &mut self,
+        parameter: &W,
+        mode: &InstructionMode,
+    ) -> Result<W, ExecutionErrorKind<W>> {
+        match mode {
+            InstructionMode::Position => self.load(to_address(parameter)?),
+            InstructionMode::Immediate => Ok(parameter.clone()),
+            InstructionMode::Relative => {
+                let address = self.relative_address(parameter)?;
+                self.load(address)
+            }
+        }
+    }
+
+    // Yes, address is a word (since it's read from memory)
+    // What's important is that its an Address after taking into account the relative base
+    fn write_value(
+        &mut self,
+        address: &W,
+        value: W,
+        mode: &InstructionMode,
+    ) -> Result<(), ExecutionErrorKind<W>> {
+        let address = match mode {
+            InstructionMode::Position => to_address(address)?,
+            InstructionMode::Immediate => return Err(ExecutionErrorKind::WriteInImmediateMode),
+            InstructionMode::Relative => self.relative_address(address)?,
+        };
+        self.checked_set(address, value)
+    }
+
+    pub(crate) fn relative_address(&self, offset: &W) -> Result<Address, ExecutionErrorKind<W>> {
+        let address = offset
+            .checked_add(&self.relative_base)
+            .ok_or(ExecutionErrorKind::ArithmeticOverflow)?;
+        to_address(&address)
+    }
+
+    pub(crate) fn adjust_relative_base(
+        &mut self,
+        relative_base: &W,
+    ) -> Result<(), ExecutionErrorKind<W>> {
+        self.relative_base = self
+            .relative_base
+            .checked_add(relative_base)
+            .ok_or(ExecutionErrorKind::ArithmeticOverflow)?;
+        Ok(())
+    }
+}
+
+/// Parses a comma-separated Intcode program
+pub fn parse_code<W: Word>(code: &str) -> Result<Vec<W>, W::ParseError> {
+    // assume we'll need, on average, one memory cell per 3 characters in the input
+    let mut out: Vec<W> = Vec::with_capacity(code.len() / 3);
+    for s in code.trim().split(',') {
+        let parsed = W::parse_word(s)?;
+        out.push(parsed);
+    }
+    Ok(out)
+}
+
+pub(crate) fn to_address<W: Word>(value: &W) -> Result<Address, ExecutionErrorKind<W>> {
+    value.to_address().ok_or_else(|| {
+        if value.is_negative() {
+            ExecutionErrorKind::NegativeAddress(value.clone())
+        } else {
+            ExecutionErrorKind::AddressOutOfRange(value.clone())
+        }
+    })
+}
+
+/// An Intcode machine using words of type `W`, stored in memory `M` (see `SparseMemory` for
+/// programs writing at huge addresses).
+pub struct Interpreter<W = Value, M = DenseMemory<W>> {
+    pub(crate) memory: Memory<W, M>,
+    pub(crate) instruction_pointer: Address,
+    pub(crate) input_queue: VecDeque<W>,
+    pub(crate) arithmetic_mode: ArithmeticMode,
+    // number of instructions executed so far
+    pub(crate) instruction_count: u64,
+    pub(crate) history: Option<History<W, M>>,
+    pub(crate) instruction_set: Arc<InstructionSet<W>>,
+    trace_sink: Option<Box<dyn TraceSink<W> + Send>>,
+    input_policy: InputPolicy<W>,
+    idle_read_limit: Option<u64>,
+    idle_reads: u64,
+    consecutive_idle_reads: u64,
+}
+
+// Clones do not inherit the trace sink nor an input callback, which can not be cloned
+impl<W: Clone, M: Clone> Clone for Interpreter<W, M> {
+    fn clone(&self) -> Self {
+        Interpreter {
+            memory: self.memory.clone(),
+            instruction_pointer: self.instruction_pointer,
+            input_queue: self.input_queue.clone(),
+            arithmetic_mode: self.arithmetic_mode,
+            instruction_count: self.instruction_count,
+            history: self.history.clone(),
+            instruction_set: Arc::clone(&self.instruction_set),
+            trace_sink: None,
+            input_policy: match &self.input_policy {
+                InputPolicy::Default(value) => InputPolicy::Default(value.clone()),
+                _ => InputPolicy::Block,
+            },
+            idle_read_limit: self.idle_read_limit,
+            idle_reads: self.idle_reads,
+            consecutive_idle_reads: self.consecutive_idle_reads,
+        }
+    }
+}
+
+/// What an input instruction does when the input queue is empty.
+pub enum InputPolicy<W = Value> {
+    /// Stops with `IOAction::ReadInput`, so that the caller can queue an input. The default.
+    Block,
+    /// Reads this value instead, e.g. -1 for "no packet"
+    Default(W),
+    /// Asks the callback for the input, and blocks if it has none
+    Callback(Box<dyn FnMut() -> Option<W> + Send>),
+}
+
+impl<W: fmt::Debug> fmt::Debug for InputPolicy<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InputPolicy::Block => write!(f, "Block"),
+            InputPolicy::Default(value) => write!(f, "Default({:?})", value),
+            InputPolicy::Callback(_) => write!(f, "Callback"),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum StepResult<W> {
+    NextInstruction,
+    SetInstructionPointerTo(Address),
+    ReadInput,
+    ProduceOutput(W),
+    Halt,
+}
+
+impl<W> StepResult<W> {
+    fn is_read_input(&self) -> bool {
+        matches!(self, StepResult::ReadInput)
+    }
+}
+
+#[non_exhaustive]
+#[derive(Debug, PartialEq)]
+pub enum IOAction<W = Value> {
+    ReadInput,
+    ProduceOutput(W),
+    Halt,
+}
+
+/// What `Interpreter::run_for` stopped on
+#[non_exhaustive]
+#[derive(Debug, PartialEq)]
+pub enum BudgetedRun<W = Value> {
+    /// The program did some IO or halted, as with `run_until_block`
+    Action(IOAction<W>),
+    /// The program executed every instruction it was given without doing any IO
+    BudgetExhausted,
+}
+
+impl<W: Word, M: MemoryBackend<W>> Interpreter<W, M> {
+    pub fn queue_input(&mut self, input: W) {
+        self.input_queue.push_back(input);
+    }
+
+    pub fn from_code(code: &str) -> Result<Self, W::ParseError> {
+        let memory = Memory::from_string(code)?;
+        Ok(Self::from_memory(memory))
+    }
+
+    pub fn from_file(filename: &str) -> Result<Self, Box<dyn error::Error>> {
+        let mut file = File::open(filename)?;
+        let mut buffer = String::new();
+        file.read_to_string(&mut buffer)?;
+        match Self::from_code(&buffer) {
+            Ok(x) => Ok(x),
+            Err(err) => Err(Box::new(err)),
+        }
+    }
+
+    pub fn read_memory(&self, address: Address) -> W {
+        self.memory.get(address)
+    }
+
+    // every cell up to the last allocated one
+    pub(crate) fn memory_words(&self) -> Vec<W> {
+        let length = match self.memory.values.chunks().last() {
+            Some((address, values)) => address + values.len(),
+            None => 0,
+        };
+        (0..length)
+            .map(|address| self.read_memory(address))
+            .collect()
+    }
+
+    /// Writes from outside the program are not subject to the memory limit, and are kept when
+    /// rewinding the history
+    pub fn write_memory(&mut self, address: Address, value: W) {
+        self.memory.set(address, value);
+        self.drop_checkpoints();
+    }
+
+    /// Stops the program with a `MemoryLimitExceeded` error when one of its writes would make
+    /// the memory allocate more than `limit` cells. By default, `DENSE_MEMORY_LIMIT` cells for
+    /// `DenseMemory` and no limit for `SparseMemory`.
+    pub fn set_memory_limit(&mut self, limit: Option<usize>) {
+        self.memory.limit = limit;
+    }
+
+    pub fn instruction_pointer(&self) -> Address {
+        self.instruction_pointer
+    }
+
+    pub fn set_instruction_pointer(&mut self, instruction_pointer: Address) {
+        self.instruction_pointer = instruction_pointer;
+    }
+
+    pub fn relative_base(&self) -> &W {
+        &self.memory.relative_base
+    }
+
+    pub fn set_relative_base(&mut self, relative_base: W) {
+        self.memory.relative_base = relative_base;
+    }
+
+    /// Instructions are decoded once and kept until the program overwrites them, which is on by
+    /// default. The cache is only filled once the machine decoded a thousand instructions, and
+    /// clones keep it, so that short runs do not pay for it. Only worth disabling to measure
+    /// what the cache brings.
+    pub fn set_decode_cache(&mut self, enabled: bool) {
+        self.memory.set_decode_cache(enabled);
+    }
+
+    pub fn set_arithmetic_mode(&mut self, arithmetic_mode: ArithmeticMode) {
+        self.arithmetic_mode = arithmetic_mode;
+    }
+
+    /// Runs the opcodes of `instruction_set` from now on, e.g. one of the profiles of earlier
+    /// puzzles or one with opcodes registered for an experiment. The default is the full
+    /// instruction set of day 9.
+    pub fn set_instruction_set(&mut self, instruction_set: InstructionSet<W>) {
+        self.instruction_set = Arc::new(instruction_set);
+        self.memory.clear_decode_cache();
+    }
+
+    pub fn instruction_set(&self) -> &InstructionSet<W> {
+        &self.instruction_set
+    }
+
+    /// Records every instruction executed from now on into `sink`. Wrap the sink in an
+    /// `Arc<Mutex<_>>` to keep a handle on it (e.g. to read a `Profiler` afterwards).
+    pub fn set_trace_sink(&mut self, sink: impl TraceSink<W> + Send + 'static) {
+        self.trace_sink = Some(Box::new(sink));
+    }
+
+    /// Stops tracing, and returns the sink if there was one
+    pub fn take_trace_sink(&mut self) -> Option<Box<dyn TraceSink<W> + Send>> {
+        self.trace_sink.take()
+    }
+
+    pub fn set_input_policy(&mut self, input_policy: InputPolicy<W>) {
+        self.input_policy = input_policy;
+    }
+
+    /// Blocks, whatever the input policy, once the program made `limit` idle reads in a row, so
+    /// that a program spinning on empty input eventually returns control to the caller. The
+    /// next input queued or given by a callback starts a new series.
+    pub fn set_idle_read_limit(&mut self, limit: Option<u64>) {
+        self.idle_read_limit = limit;
+    }
+
+    /// Number of input instructions that found the input queue empty, and either blocked or
+    /// read the default value
+    pub fn idle_reads(&self) -> u64 {
+        self.idle_reads
+    }
+
+    /// Same, since the last input that was actually given to the program
+    pub fn consecutive_idle_reads(&self) -> u64 {
+        self.consecutive_idle_reads
+    }
+
+    // for input instructions that do not run through `step`, once they wrote the next input
+    pub(crate) fn consume_queued_input(&mut self) {
+        self.input_queue.pop_front();
+        self.consecutive_idle_reads = 0;
+    }
+
+    // whether something records every instruction, which then has to run through `step`
+    pub(crate) fn is_observed(&self) -> bool {
+        self.trace_sink.is_some() || self.history.is_some()
+    }
+
+    pub(crate) fn from_memory(memory: Memory<W, M>) -> Interpreter<W, M> {
+        Interpreter {
+            memory,
+            instruction_pointer: 0,
+            input_queue: VecDeque::new(),
+            arithmetic_mode: ArithmeticMode::default(),
+            instruction_count: 0,
+            history: None,
+            instruction_set: Arc::new(InstructionSet::default()),
+            trace_sink: None,
+            input_policy: InputPolicy::Block,
+            idle_read_limit: None,
+            idle_reads: 0,
+            consecutive_idle_reads: 0,
+        }
+    }
+
+    pub fn run_until_block(&mut self) -> Result<IOAction<W>, ExecutionError<W>> {
+        loop {
+            if let Some(io_action) = self.step()? {
+                return Ok(io_action);
+            }
+        }
+    }
+
+    /// Same as `run_until_block`, but gives up after executing `max_instructions`, so that a
+    /// program stuck in a loop without IO can not hang the caller. Running again resumes it.
+    pub fn run_for(&mut self, max_instructions: u64) -> Result<BudgetedRun<W>, ExecutionError<W>> {
+        let budget_end = self.instruction_count.saturating_add(max_instructions);
+        while self.instruction_count < budget_end {
+            if let Some(io_action) = self.step()? {
+                return Ok(BudgetedRun::Action(io_action));
+            }
+        }
+        Ok(BudgetedRun::BudgetExhausted)
+    }
+
+    /// Runs a single instruction. Returns `None` if that instruction did not do any IO.
+    pub fn step(&mut self) -> Result<Option<IOAction<W>>, ExecutionError<W>> {
+        let instruction_pointer = self.instruction_pointer;
+        // failing instructions do not write anything, so the word is still the instruction
+        let to_error = |kind, memory: &Memory<W, M>| {
+            ExecutionError::new(kind, instruction_pointer, memory.get(instruction_pointer))
+        };
+        let instruction_def = self
+            .memory
+            .decode(instruction_pointer, &self.instruction_set)
+            .map_err(|kind| to_error(kind, &self.memory))?;
+        // operands are read before running the instruction, which may overwrite them
+        let mut trace_event = self
+            .trace_sink
+            .as_ref()
+            .map(|_| self.trace_event(&instruction_def));
+        let relative_base = self
+            .history
+            .as_ref()
+            .map(|_| self.memory.relative_base.clone());
+        self.memory.last_write = None;
+        let queued_inputs = self.input_queue.len();
+        let step_result = self
+            .run_instruction(&instruction_def)
+            .map_err(|kind| to_error(kind, &self.memory))?;
+
+        // Advance instruction pointer
+        match step_result {
+            StepResult::SetInstructionPointerTo(jump_address) => {
+                self.instruction_pointer = jump_address
+            }
+            // The input instruction will return either ReadInput (= "WouldBlock") when not enough inputs are
+            // available in the buffer, or NextInstruction if input is available.
+            // In the first case, the instruction pointer should still point at the Input
+            // instruction, so that on resume the instruction can try to read input again.
+            StepResult::ReadInput | StepResult::Halt => {}
+            _ => self.instruction_pointer += instruction_def.length,
+        }
+
+        // blocking on input does not execute anything
+        if !step_result.is_read_input() {
+            self.instruction_count += 1;
+            if let Some(event) = &mut trace_event {
+                event.write = self
+                    .memory
+                    .last_write
+                    .as_ref()
+                    .map(|(address, _)| (*address, self.memory.get(*address)));
+                if let Some(sink) = &mut self.trace_sink {
+                    sink.record(event);
+                }
+            }
+            if let Some(relative_base) = relative_base {
+                // inputs given by the input policy are not queued again when rewinding
+                let consumed_input = if self.input_queue.len() < queued_inputs {
+                    self.consumed_input()
+                } else {
+                    None
+                };
+                let overwritten = self.memory.last_write.take();
+                self.record_history(UndoEntry {
+                    instruction_pointer,
+                    relative_base,
+                    overwritten,
+                    consumed_input,
+                });
+            }
+        }
+
+        // Handle IO actions
+        match step_result {
+            StepResult::NextInstruction => Ok(None),
+            StepResult::Halt => Ok(Some(IOAction::Halt)),
+            StepResult::SetInstructionPointerTo(_) => Ok(None),
+            StepResult::ReadInput => Ok(Some(IOAction::ReadInput)),
+            StepResult::ProduceOutput(output) => Ok(Some(IOAction::ProduceOutput(output))),
+        }
+    }
+
+    /// Number of instructions executed so far, which is the cycle count of the machine since
+    /// every instruction takes one cycle (blocking on input does not count)
+    pub fn instruction_count(&self) -> u64 {
+        self.instruction_count
+    }
+
+    // the input an input instruction just wrote
+    fn consumed_input(&self) -> Option<W> {
+        let (address, _) = self.memory.last_write.as_ref()?;
+        Some(self.memory.get(*address))
+    }
+
+    /// The instruction `step` would run next, decoded from memory
+    pub fn current_instruction(&self) -> Line<W> {
+        let words: Vec<W> = (0..MAX_INSTRUCTION_LENGTH)
+            .map(|offset| self.memory.get(self.instruction_pointer + offset))
+            .collect();
+        let mut line = decode_line(&words, 0);
+        line.address = self.instruction_pointer;
+        line
+    }
+
+    pub fn run_interactively(&mut self) -> Result<(), ExecutionError<W>> {
+        loop {
+            let io_action = self.run_until_block()?;
+            match io_action {
+                IOAction::Halt => return Ok(()),
+                IOAction::ProduceOutput(x) => println!("output: {}", x),
+                IOAction::ReadInput => {
+                    println!("enter input: ");
+                    let mut input = String::new();
+                    io::stdin()
+                        .read_line(&mut input)
+                        .expect("error: unable to read user input");
+                    let input_int = W::parse_word(input.trim())
+                        .expect("error: unable to parse user input as integer");
+                    self.queue_input(input_int);
+                }
+            }
+        }
+    }
+
+    fn parameter(&self, offset: Address) -> W {
+        self.memory.get(self.instruction_pointer + offset)
+    }
+
+    fn trace_event(&self, instruction_def: &InstructionDefinition) -> TraceEvent<W> {
+        let entry = self.entry(instruction_def);
+        let parameter_count = entry.roles.len();
+        let mut operands = Vec::with_capacity(parameter_count);
+        let mut addresses = Vec::with_capacity(parameter_count);
+        for (index, mode) in instruction_def.instruction_modes[..parameter_count]
+            .iter()
+            .enumerate()
+        {
+            let parameter = self.parameter(index + 1);
+            addresses.push(self.memory.operand_address(&parameter, mode));
+            operands.push(match mode {
+                InstructionMode::Position => Operand::Position(parameter),
+                InstructionMode::Immediate => Operand::Immediate(parameter),
+                InstructionMode::Relative => Operand::Relative(parameter),
+            });
+        }
+        TraceEvent {
+            instruction_pointer: self.instruction_pointer,
+            mnemonic: entry.mnemonic,
+            operands,
+            addresses,
+            write: None,
+        }
+    }
+
+    // decoding only gives opcodes of the instruction set
+    fn entry(&self, instruction_def: &InstructionDefinition) -> &OpcodeEntry<W> {
+        self.instruction_set
+            .entry(instruction_def.number)
+            .expect("decoded opcodes are in the instruction set")
+    }
+
+    fn run_instruction(
+        &mut self,
+        instruction_def: &InstructionDefinition,
+    ) -> Result<StepResult<W>, ExecutionErrorKind<W>> {
+        let instruction_modes = &instruction_def.instruction_modes;
+        let entry = self
+            .instruction_set
+            .entry(instruction_def.number)
+            .expect("decoded opcodes are in the instruction set");
+        // the values of the read parameters, and the write and jump parameters as they are
+        let mut operands = [W::from_i64(0), W::from_i64(0), W::from_i64(0)];
+        let mut operand_count = 0;
+        let mut written = None;
+        let mut jump = None;
+        for (index, role) in entry.roles.iter().enumerate() {
+            let parameter = self.memory.get(self.instruction_pointer + index + 1);
+            match role {
+                Role::Read => {
+                    operands[operand_count] = self
+                        .memory
+                        .read_value(&parameter, &instruction_modes[index])?;
+                    operand_count += 1;
+                }
+                Role::Write => written = Some((parameter, instruction_modes[index])),
+                Role::Jump => jump = Some((parameter, instruction_modes[index])),
+            }
+        }
+        let effect = entry
+            .handler
+            .run(&operands[..operand_count], self.arithmetic_mode)?;
+
+        match effect {
+            Effect::Next => Ok(StepResult::NextInstruction),
+            Effect::Write(value) => {
+                let (parameter, mode) = written.ok_or(ExecutionErrorKind::InvalidEffect)?;
+                self.memory.write_value(&parameter, value, &mode)?;
+                Ok(StepResult::NextInstruction)
+            }
+            Effect::Input => {
+                let (parameter, mode) = written.ok_or(ExecutionErrorKind::InvalidEffect)?;
+                self.read_input(&parameter, &mode)
+            }
+            Effect::Jump => {
+                let (parameter, mode) = jump.ok_or(ExecutionErrorKind::InvalidEffect)?;
+                let jump_address = to_address(&self.memory.read_value(&parameter, &mode)?)?;
+                Ok(StepResult::SetInstructionPointerTo(jump_address))
+            }
+            Effect::Output(value) => Ok(StepResult::ProduceOutput(value)),
+            Effect::AdjustRelativeBase(value) => {
+                self.memory.adjust_relative_base(&value)?;
+                Ok(StepResult::NextInstruction)
+            }
+            Effect::Halt => Ok(StepResult::Halt),
+        }
+    }
+
+    // writes the next input to the parameter, or blocks if there is none
+    fn read_input(
+        &mut self,
+        parameter: &W,
+        mode: &InstructionMode,
+    ) -> Result<StepResult<W>, ExecutionErrorKind<W>> {
+        // only consume the input once it has been written, so that it is not lost on error
+        let next_input = self.input_queue.front().cloned();
+        if let Some(input) = next_input {
+            self.memory.write_value(parameter, input, mode)?;
+            self.input_queue.pop_front();
+            self.consecutive_idle_reads = 0;
+            return Ok(StepResult::NextInstruction);
+        }
+
+        let limit_reached = self
+            .idle_read_limit
+            .is_some_and(|limit| self.consecutive_idle_reads >= limit);
+        let (input, idle) = match &mut self.input_policy {
+            _ if limit_reached => (None, true),
+            InputPolicy::Block => (None, true),
+            InputPolicy::Default(value) => (Some(value.clone()), true),
+            InputPolicy::Callback(callback) => {
+                let input = callback();
+                let idle = input.is_none();
+                (input, idle)
+            }
+        };
+        if idle {
+            self.idle_reads += 1;
+            self.consecutive_idle_reads += 1;
+        } else {
+            self.consecutive_idle_reads = 0;
+        }
+        match input {
+            Some(input) => {
+                self.memory.write_value(parameter, input, mode)?;
+                Ok(StepResult::NextInstruction)
+            }
+            None => Ok(StepResult::ReadInput),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub(crate) enum Opcode {
+    Addition,
+    Multiplication,
+    Input,
+    Output,
+    JumpIfTrue,
+    JumpIfFalse,
+    LessThan,
+    Equals,
+    AdjustRelativeBase,
+    Halt,
+    // an opcode registered on the instruction set of the interpreter, with its number
+    Registered(u8),
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub(crate) enum InstructionMode {
+    Position,
+    Immediate,
+    Relative,
+}
+
+// Instructions take at most 3 parameters
+pub(crate) const MAX_PARAMETERS: usize = 3;
+
+// Small enough to be copied around instead of allocated
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub(crate) struct InstructionDefinition {
+    pub(crate) opcode: Opcode,
+    // the number of the opcode in the instruction set, which has its handler
+    pub(crate) number: u8,
+    pub(crate) instruction_modes: [InstructionMode; MAX_PARAMETERS],
+    // the number of words the instruction pointer moves past it, 0 for halting
+    pub(crate) length: usize,
+}
+
+// Decodes an instruction of the full instruction set (the one of day 9), for the tools that do
+// not run on an interpreter
+pub(crate) fn parse_instruction_definition<W>(
+    instruction_code: i64,
+) -> Result<InstructionDefinition, ExecutionErrorKind<W>> {
+    if instruction_code < 0 {
+        return Err(ExecutionErrorKind::UnknownOpcode);
+    }
+    let opcode = BUILTIN_NUMBERS[(instruction_code % 100) as usize]
+        .ok_or(ExecutionErrorKind::UnknownOpcode)?;
+    let instruction_modes = parse_instruction_modes(instruction_code / 100)
+        .ok_or(ExecutionErrorKind::InvalidParameterMode)?;
+    Ok(InstructionDefinition {
+        opcode,
+        number: (instruction_code % 100) as u8,
+        instruction_modes,
+        length: instruction_length(opcode),
+    })
+}
+
+pub(crate) const MAX_INSTRUCTION_LENGTH: usize = 4;
+
+// The functions below describe the built-in opcodes, see `InstructionSet` for registered ones
+
+pub(crate) fn instruction_length(opcode: Opcode) -> usize {
+    match opcode {
+        // halting never moves the instruction pointer
+        Opcode::Halt => 0,
+        _ => parameter_count(&opcode) + 1,
+    }
+}
+
+// index of the parameter the instruction writes to, if any
+pub(crate) fn written_parameter(opcode: &Opcode) -> Option<usize> {
+    builtin_opcode(opcode)
+        .roles
+        .iter()
+        .position(|&role| role == Role::Write)
+}
+
+// number of parameters following the instruction word
+pub(crate) fn parameter_count(opcode: &Opcode) -> usize {
+    builtin_opcode(opcode).roles.len()
+}
+
+pub(crate) fn opcode_number(opcode: &Opcode) -> i64 {
+    i64::from(builtin_opcode(opcode).number)
+}
+
+pub(crate) fn mnemonic(opcode: &Opcode) -> &'static str {
+    builtin_opcode(opcode).mnemonic
+}
+
+// Missing modes are position modes. Digits past the third one are checked, but ignored.
+pub(crate) fn parse_instruction_modes(
+    instruction_modes_int: i64,
+) -> Option<[InstructionMode; MAX_PARAMETERS]> {
+    let mut instruction_modes = [InstructionMode::Position; MAX_PARAMETERS];
+    let mut instruction_modes_int = instruction_modes_int;
+    let mut index = 0;
+    while instruction_modes_int > 0 {
+        let mode = match instruction_modes_int % 10 {
+            0 => InstructionMode::Position,
+            1 => InstructionMode::Immediate,
+            2 => InstructionMode::Relative,
+            _ => return None,
+        };
+        if index < MAX_PARAMETERS {
+            instruction_modes[index] = mode;
+        }
+        index += 1;
+        instruction_modes_int /= 10;
+    }
+    Some(instruction_modes)
+}
+
+// TODO more tests, make a distinction between unit and integration tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::SparseMemory;
+    use num_bigint::BigInt;
+
+    #[test]
+    fn test_interpreter_run_complex_example() {
+        assert_interpreter_run(
+            "1,9,10,3,2,3,11,0,99,30,40,50",
+            "3500,9,10,70,2,3,11,0,99,30,40,50",
+        );
+    }
+
+    #[test]
+    fn test_interpreter_run_simple_examples() {
+        assert_interpreter_run("1,0,0,0,99", "2,0,0,0,99");
+        assert_interpreter_run("2,3,0,3,99", "2,3,0,6,99");
+        assert_interpreter_run("2,4,4,5,99,0", "2,4,4,5,99,9801");
+        assert_interpreter_run("1,1,1,4,99,5,6,0,99", "30,1,1,4,2,5,6,0,99");
+    }
+
+    #[test]
+    fn test_interpreter_run_quine() {
+        let quine = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+        let mut interpreter: Interpreter = Interpreter::from_code(quine).unwrap();
+        let mut outputs: Vec<String> = Vec::new();
+        loop {
+            let output = interpreter.run_until_block().unwrap();
+            match output {
+                IOAction::Halt => {
+                    assert_eq!(outputs, quine.split(',').collect::<Vec<&str>>());
+                    return;
+                }
+                IOAction::ProduceOutput(x) => {
+                    outputs.push(x.to_string());
+                }
+                _ => panic!("unexpected output type"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_interpreter_output() {
+        assert_interpreter_single_output("1102,34915192,34915192,7,4,7,99,0", 1219070632396864);
+        assert_interpreter_single_output("104,1125899906842624,99", 1125899906842624);
+        assert_interpreter_single_output("1102,6,7,1985,109,2000,109,19,204,-34,99", 42);
+    }
+
+    #[test]
+    fn test_interpreter_read_past_end() {
+        assert_interpreter_single_output("4,3,99", 0);
+        assert_interpreter_single_output("204,3,99", 0);
+    }
+
+    #[test]
+    fn test_interpreter_memory_backends() {
+        let far_away_write = "1101,20,22,1000000000000,4,1000000000000,99";
+        let mut interpreter =
+            Interpreter::<Value, SparseMemory<Value>>::from_code(far_away_write).unwrap();
+        interpreter.set_memory_limit(Some(4096));
+        assert_eq!(
+            interpreter.run_until_block().unwrap(),
+            IOAction::ProduceOutput(42)
+        );
+
+        let mut interpreter: Interpreter = Interpreter::from_code(far_away_write).unwrap();
+        interpreter.set_memory_limit(Some(4096));
+        let error = interpreter.run_until_block().unwrap_err();
+        assert_eq!(
+            error.kind(),
+            &ExecutionErrorKind::MemoryLimitExceeded(1000000000000)
+        );
+        assert_eq!(error.instruction_pointer(), 0);
+    }
+
+    #[test]
+    fn test_interpreter_default_memory_limit() {
+        let mut interpreter: Interpreter =
+            Interpreter::from_code("1101,20,22,1000000000000,99").unwrap();
+        assert_eq!(
+            interpreter.run_until_block().unwrap_err().kind(),
+            &ExecutionErrorKind::MemoryLimitExceeded(1000000000000)
+        );
+    }
+
+    #[test]
+    fn test_interpreter_run_immediate_mode() {
+        assert_interpreter_run("1002,4,3,4,33", "1002,4,3,4,99");
+    }
+
+    #[test]
+    fn test_interpreter_overflow() {
+        assert_interpreter_single_output("109,10,21102,6,7,-5,4,5,99", 42);
+    }
+
+    #[test]
+    fn test_interpreter_wide_words() {
+        // (2^63 - 1)^2 does not fit in an i64
+        let code = format!("1102,{},{},7,4,7,99,0", i64::MAX, i64::MAX);
+        let expected_output = "85070591730234615847396907784232501249";
+        assert_single_output::<i128>(&code, expected_output);
+        assert_single_output::<BigInt>(&code, expected_output);
+
+        let mut interpreter: Interpreter = Interpreter::from_code(&code).unwrap();
+        assert_eq!(
+            interpreter.run_until_block().unwrap_err().kind(),
+            &ExecutionErrorKind::ArithmeticOverflow
+        );
+    }
+
+    // runs the program with every word type
+    fn assert_interpreter_run(starting_memory: &str, expected_memory: &str) {
+        assert_run::<Value>(starting_memory, expected_memory);
+        assert_run::<i128>(starting_memory, expected_memory);
+        assert_run::<BigInt>(starting_memory, expected_memory);
+    }
+
+    fn assert_run<W: Word>(starting_memory: &str, expected_memory: &str) {
+        let mut interpreter = Interpreter::<W>::from_code(starting_memory).unwrap();
+        let output = interpreter.run_until_block();
+        assert!(output.is_ok());
+        let expected: Memory<W, DenseMemory<W>> = Memory::from_string(expected_memory).unwrap();
+        assert_eq!(interpreter.memory, expected);
+
+        // same program, with a sparse memory
+        let mut interpreter =
+            Interpreter::<W, SparseMemory<W>>::from_code(starting_memory).unwrap();
+        assert!(interpreter.run_until_block().is_ok());
+        let expected: Memory<W, SparseMemory<W>> = Memory::from_string(expected_memory).unwrap();
+        assert_eq!(interpreter.memory, expected);
+    }
+
+    #[test]
+    fn test_interpreter_read_memory() {
+        assert_interpreter_first_cell("1,9,10,3,2,3,11,0,99,30,40,50", 3500);
+        assert_interpreter_first_cell("1,0,0,0,99", 2);
+        assert_interpreter_first_cell("1,1,1,4,99,5,6,0,99", 30);
+    }
+
+    #[test]
+    fn test_interpreter_write_memory() {
+        let mut interpreter: Interpreter = Interpreter::from_code("1,0,0,0,99").unwrap();
+        interpreter.write_memory(1, 4);
+        interpreter.write_memory(2, 4);
+        assert_eq!(interpreter.run_until_block().unwrap(), IOAction::Halt);
+        assert_eq!(interpreter.read_memory(0), 198);
+    }
+
+    fn assert_interpreter_first_cell(starting_memory: &str, expected_value: Value) {
+        let mut interpreter: Interpreter = Interpreter::from_code(starting_memory).unwrap();
+        assert_eq!(interpreter.run_until_block().unwrap(), IOAction::Halt);
+        assert_eq!(interpreter.read_memory(0), expected_value);
+    }
+
+    // runs the program with every word type
+    fn assert_interpreter_single_output(starting_memory: &str, expected_output: Value) {
+        let expected_output = expected_output.to_string();
+        assert_single_output::<Value>(starting_memory, &expected_output);
+        assert_single_output::<i128>(starting_memory, &expected_output);
+        assert_single_output::<BigInt>(starting_memory, &expected_output);
+    }
+
+    fn assert_single_output<W: Word>(starting_memory: &str, expected_output: &str) {
+        let mut interpreter = Interpreter::<W>::from_code(starting_memory).unwrap();
+        let output = interpreter.run_until_block();
+        assert!(output.is_ok());
+        match output.unwrap() {
+            IOAction::ProduceOutput(value) => assert_eq!(value.to_string(), expected_output),
+            _ => panic!("unexpected output type"),
+        }
+        assert_eq!(interpreter.run_until_block().unwrap(), IOAction::Halt);
+    }
+
+    #[test]
+    fn test_parse_instruction_definition() {
+        assert_eq!(
+            Ok(InstructionDefinition {
+                opcode: Opcode::Multiplication,
+                number: 2,
+                instruction_modes: [
+                    InstructionMode::Position,
+                    InstructionMode::Immediate,
+                    InstructionMode::Position
+                ],
+                length: 4,
+            }),
+            parse_instruction_definition::<Value>(1002)
+        );
+
+        assert_eq!(
+            Ok(InstructionDefinition {
+                opcode: Opcode::Input,
+                number: 3,
+                instruction_modes: [InstructionMode::Position; MAX_PARAMETERS],
+                length: 2,
+            }),
+            parse_instruction_definition::<Value>(3)
+        );
+
+        assert_eq!(
+            Err(ExecutionErrorKind::UnknownOpcode),
+            parse_instruction_definition::<Value>(42)
+        );
+        assert_eq!(
+            Err(ExecutionErrorKind::UnknownOpcode),
+            parse_instruction_definition::<Value>(-1)
+        );
+        assert_eq!(
+            Err(ExecutionErrorKind::InvalidParameterMode),
+            parse_instruction_definition::<Value>(301)
+        );
+    }
+
+    #[test]
+    fn test_interpreter_errors() {
+        assert_interpreter_error("1,0,0,0,42", ExecutionErrorKind::UnknownOpcode, 4, 42);
+        assert_interpreter_error(
+            "1,0,0,0,301,0,0,0,99",
+            ExecutionErrorKind::InvalidParameterMode,
+            4,
+            301,
+        );
+        assert_interpreter_error(
+            "11101,1,1,0,99",
+            ExecutionErrorKind::WriteInImmediateMode,
+            0,
+            11101,
+        );
+        assert_interpreter_error("1,-1,0,0,99", ExecutionErrorKind::NegativeAddress(-1), 0, 1);
+        assert_interpreter_error(
+            "1105,1,-5",
+            ExecutionErrorKind::NegativeAddress(-5),
+            0,
+            1105,
+        );
+        assert_interpreter_error(
+            "109,-3,22101,0,0,0,99",
+            ExecutionErrorKind::NegativeAddress(-3),
+            2,
+            22101,
+        );
+        let overflowing_multiplication = format!("1102,{},2,0,99", Value::MAX);
+        assert_interpreter_error(
+            &overflowing_multiplication,
+            ExecutionErrorKind::ArithmeticOverflow,
+            0,
+            1102,
+        );
+    }
+
+    #[test]
+    fn test_interpreter_arithmetic_modes() {
+        let overflowing_addition = format!("1101,{},1,7,4,7,99,0", Value::MAX);
+        let mut interpreter: Interpreter = Interpreter::from_code(&overflowing_addition).unwrap();
+        interpreter.set_arithmetic_mode(ArithmeticMode::Wrapping);
+        assert_eq!(
+            interpreter.run_until_block().unwrap(),
+            IOAction::ProduceOutput(Value::MIN)
+        );
+
+        let mut interpreter: Interpreter = Interpreter::from_code(&overflowing_addition).unwrap();
+        interpreter.set_arithmetic_mode(ArithmeticMode::Saturating);
+        assert_eq!(
+            interpreter.run_until_block().unwrap(),
+            IOAction::ProduceOutput(Value::MAX)
+        );
+
+        let mut interpreter: Interpreter = Interpreter::from_code(&overflowing_addition).unwrap();
+        assert_eq!(
+            interpreter.run_until_block().unwrap_err().kind(),
+            &ExecutionErrorKind::ArithmeticOverflow
+        );
+    }
+
+    #[test]
+    fn test_interpreter_decode_cache() {
+        // outputs #7, then turns that instruction into `out [pos 7]` and runs it again
+        let self_modifying = "104,7,1101,4,0,0,1105,1,0";
+        for &cached in &[true, false] {
+            let mut interpreter: Interpreter = Interpreter::from_code(self_modifying).unwrap();
+            interpreter.set_decode_cache(cached);
+            interpreter.memory.warm_up_decodes = 0;
+            interpreter.enable_history(100, 2);
+            for expected in &[7, 1] {
+                assert_eq!(
+                    interpreter.run_until_block().unwrap(),
+                    IOAction::ProduceOutput(*expected)
+                );
+            }
+            // undoing the write brings the first instruction back
+            assert!(interpreter.rewind_to(0));
+            assert_eq!(
+                interpreter.run_until_block().unwrap(),
+                IOAction::ProduceOutput(7)
+            );
+        }
+
+        // short runs do not fill the cache
+        let mut interpreter: Interpreter = Interpreter::from_code("1105,1,0").unwrap();
+        let warm_up = u64::from(DECODE_CACHE_WARM_UP);
+        assert_eq!(
+            interpreter.run_for(warm_up),
+            Ok(BudgetedRun::BudgetExhausted)
+        );
+        assert_eq!(interpreter.memory.decoded.as_ref().map(Vec::len), Some(0));
+        assert_eq!(interpreter.run_for(1), Ok(BudgetedRun::BudgetExhausted));
+        assert_eq!(interpreter.memory.decoded.as_ref().map(Vec::len), Some(1));
+    }
+
+    #[test]
+    fn test_interpreter_run_for() {
+        let mut interpreter: Interpreter = Interpreter::from_code("1105,1,0").unwrap();
+        assert_eq!(
+            interpreter.run_for(10).unwrap(),
+            BudgetedRun::BudgetExhausted
+        );
+        assert_eq!(interpreter.instruction_count(), 10);
+        assert_eq!(
+            interpreter.run_for(0).unwrap(),
+            BudgetedRun::BudgetExhausted
+        );
+
+        // outputs 1, runs an addition, outputs 2 and halts
+        let mut interpreter: Interpreter =
+            Interpreter::from_code("104,1,1101,0,0,0,104,2,99").unwrap();
+        let expected_runs = [
+            BudgetedRun::Action(IOAction::ProduceOutput(1)),
+            BudgetedRun::BudgetExhausted,
+            BudgetedRun::Action(IOAction::ProduceOutput(2)),
+            BudgetedRun::Action(IOAction::Halt),
+        ];
+        for expected in &expected_runs {
+            assert_eq!(&interpreter.run_for(1).unwrap(), expected);
+        }
+        assert_eq!(interpreter.instruction_count(), 4);
+    }
+
+    #[test]
+    fn test_interpreter_input_policies() {
+        let echo = "3,9,4,9,1105,1,0,0,0,0";
+        let mut interpreter: Interpreter = Interpreter::from_code(echo).unwrap();
+        interpreter.set_input_policy(InputPolicy::Default(-1));
+        interpreter.set_idle_read_limit(Some(2));
+        interpreter.queue_input(5);
+        let mut outputs = Vec::new();
+        while let IOAction::ProduceOutput(output) = interpreter.run_until_block().unwrap() {
+            outputs.push(output);
+        }
+        assert_eq!(outputs, vec![5, -1, -1]);
+        // two reads of the default value, then one that blocked
+        assert_eq!(interpreter.idle_reads(), 3);
+        assert_eq!(interpreter.consecutive_idle_reads(), 3);
+        interpreter.queue_input(7);
+        assert_eq!(
+            interpreter.run_until_block().unwrap(),
+            IOAction::ProduceOutput(7)
+        );
+        assert_eq!(interpreter.consecutive_idle_reads(), 0);
+
+        let mut inputs = vec![2, 1];
+        let mut interpreter: Interpreter = Interpreter::from_code(echo).unwrap();
+        interpreter.set_input_policy(InputPolicy::Callback(Box::new(move || inputs.pop())));
+        let mut outputs = Vec::new();
+        while let IOAction::ProduceOutput(output) = interpreter.run_until_block().unwrap() {
+            outputs.push(output);
+        }
+        assert_eq!(outputs, vec![1, 2]);
+        assert_eq!(interpreter.idle_reads(), 1);
+    }
+
+    fn assert_interpreter_error(
+        starting_memory: &str,
+        expected_kind: ExecutionErrorKind,
+        expected_instruction_pointer: Address,
+        expected_instruction: Value,
+    ) {
+        let mut interpreter: Interpreter = Interpreter::from_code(starting_memory).unwrap();
+        let error = interpreter.run_until_block().unwrap_err();
+        assert_eq!(error.kind(), &expected_kind);
+        assert_eq!(error.instruction_pointer(), expected_instruction_pointer);
+        assert_eq!(error.instruction(), &expected_instruction);
+    }
+}
@@ -0,0 +1,48 @@
+//! Intcode interpreter shared by the Advent of Code 2019 solutions
+//! (days 2, 5, 7, 9, 11 and 13).
+
+mod arithmetic;
+mod assembler;
+mod compiler;
+mod console;
+mod debugger;
+mod device;
+mod disassembler;
+mod error;
+mod fuzz;
+mod history;
+mod instruction_set;
+mod interpreter;
+mod memory;
+mod mmio;
+mod network;
+mod reference;
+mod scheduler;
+mod snapshot;
+mod symbolic;
+mod trace;
+mod word;
+
+pub use arithmetic::ArithmeticMode;
+pub use assembler::{assemble, AssemblyError, AssemblyErrorKind};
+pub use compiler::CompiledInterpreter;
+pub use console::AsciiConsole;
+pub use debugger::{Debugger, StopReason};
+pub use device::{
+    Exit, FnInput, FnOutput, FrameDevice, Framed, InputDevice, Io, IteratorInput, OutputDevice,
+};
+pub use disassembler::{disassemble, Disassembly, Item, Line, Operand};
+pub use error::{ExecutionError, ExecutionErrorKind};
+pub use fuzz::{Backend, Divergence, Fuzzer, Outcome, Stop};
+pub use instruction_set::{Effect, InstructionSet, Role};
+pub use interpreter::{
+    parse_code, Address, BudgetedRun, IOAction, InputPolicy, Interpreter, Value,
+};
+pub use memory::{DenseMemory, MemoryBackend, SparseMemory, DENSE_MEMORY_LIMIT};
+pub use mmio::{Clock, Framebuffer, Keyboard, MappedDevice, RandomSource};
+pub use network::{Network, NetworkError, NetworkRun, Quiescence, Topology};
+pub use num_bigint::BigInt;
+pub use scheduler::Scheduler;
+pub use symbolic::{Constraint, Expr, Observation, Solver, SymbolicError, SymbolicRun};
+pub use trace::{JsonLinesTrace, Profiler, TraceEvent, TraceSink};
+pub use word::Word;
@@ -0,0 +1,153 @@
+use crate::interpreter::Address;
+use crate::word::Word;
+use std::collections::HashMap;
+
+/// Where an Intcode machine stores its words. Cells that were never written read as zero.
+pub trait MemoryBackend<W: Word>: Clone {
+    fn from_values(values: Vec<W>) -> Self;
+    /// `None` if the cell was never allocated
+    fn get(&self, address: Address) -> Option<&W>;
+    fn set(&mut self, address: Address, value: W);
+    /// Number of cells currently allocated
+    fn allocated_cells(&self) -> usize;
+    /// Number of cells that would be allocated once `address` has been written
+    fn allocated_cells_after_write(&self, address: Address) -> usize;
+    /// The allocated cells, as runs of consecutive cells sorted by their first address
+    fn chunks(&self) -> Vec<(Address, &[W])>;
+    /// Number of cells a new machine may allocate before its writes fail, `None` for no limit
+    const DEFAULT_LIMIT: Option<usize> = None;
+}
+
+/// Stores every cell from address 0 up to the highest address written. Fastest for the usual
+/// programs, but a single write far away allocates everything before it, so writes are limited
+/// to the first `DENSE_MEMORY_LIMIT` cells by default.
+pub const DENSE_MEMORY_LIMIT: usize = 1 << 26;
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct DenseMemory<W> {
+    values: Vec<W>,
+}
+
+impl<W: Word> MemoryBackend<W> for DenseMemory<W> {
+    const DEFAULT_LIMIT: Option<usize> = Some(DENSE_MEMORY_LIMIT);
+
+    fn from_values(values: Vec<W>) -> Self {
+        DenseMemory { values }
+    }
+
+    fn get(&self, address: Address) -> Option<&W> {
+        self.values.get(address)
+    }
+
+    fn set(&mut self, address: Address, value: W) {
+        if address >= self.values.len() {
+            self.values.resize(address + 1, W::from_i64(0));
+        }
+        self.values[address] = value;
+    }
+
+    fn allocated_cells(&self) -> usize {
+        self.values.len()
+    }
+
+    fn allocated_cells_after_write(&self, address: Address) -> usize {
+        self.values.len().max(address.saturating_add(1))
+    }
+
+    fn chunks(&self) -> Vec<(Address, &[W])> {
+        vec![(0, &self.values[..])]
+    }
+}
+
+const PAGE_SIZE: usize = 1024;
+
+/// Allocates memory by pages of `PAGE_SIZE` cells, only for the pages that were written to, so
+/// that programs can write at huge addresses.
+#[derive(PartialEq, Debug, Clone)]
+pub struct SparseMemory<W> {
+    pages: HashMap<usize, Vec<W>>,
+}
+
+impl<W: Word> MemoryBackend<W> for SparseMemory<W> {
+    fn from_values(values: Vec<W>) -> Self {
+        let mut memory = SparseMemory {
+            pages: HashMap::new(),
+        };
+        for (address, value) in values.into_iter().enumerate() {
+            memory.set(address, value);
+        }
+        memory
+    }
+
+    fn get(&self, address: Address) -> Option<&W> {
+        self.pages
+            .get(&(address / PAGE_SIZE))
+            .map(|page| &page[address % PAGE_SIZE])
+    }
+
+    fn set(&mut self, address: Address, value: W) {
+        let page = self
+            .pages
+            .entry(address / PAGE_SIZE)
+            .or_insert_with(|| vec![W::from_i64(0); PAGE_SIZE]);
+        page[address % PAGE_SIZE] = value;
+    }
+
+    fn allocated_cells(&self) -> usize {
+        self.pages.len() * PAGE_SIZE
+    }
+
+    fn allocated_cells_after_write(&self, address: Address) -> usize {
+        if self.pages.contains_key(&(address / PAGE_SIZE)) {
+            self.allocated_cells()
+        } else {
+            self.allocated_cells() + PAGE_SIZE
+        }
+    }
+
+    fn chunks(&self) -> Vec<(Address, &[W])> {
+        let mut chunks: Vec<(Address, &[W])> = self
+            .pages
+            .iter()
+            .map(|(page, values)| (page * PAGE_SIZE, &values[..]))
+            .collect();
+        chunks.sort_by_key(|&(address, _)| address);
+        chunks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Value;
+
+    #[test]
+    fn test_dense_memory() {
+        let mut memory = DenseMemory::<Value>::from_values(vec![1, 2, 3]);
+        assert_eq!(memory.get(2), Some(&3));
+        assert_eq!(memory.get(3), None);
+        assert_eq!(memory.allocated_cells_after_write(9), 10);
+        memory.set(9, 4);
+        assert_eq!(memory.get(9), Some(&4));
+        assert_eq!(memory.get(8), Some(&0));
+        assert_eq!(memory.allocated_cells(), 10);
+    }
+
+    #[test]
+    fn test_sparse_memory() {
+        let mut memory = SparseMemory::<Value>::from_values(vec![1, 2, 3]);
+        assert_eq!(memory.get(2), Some(&3));
+        assert_eq!(memory.get(3), Some(&0));
+        assert_eq!(memory.allocated_cells(), PAGE_SIZE);
+        assert_eq!(memory.allocated_cells_after_write(PAGE_SIZE - 1), PAGE_SIZE);
+
+        let far_away = 1_000_000_000_000;
+        assert_eq!(memory.get(far_away), None);
+        assert_eq!(memory.allocated_cells_after_write(far_away), 2 * PAGE_SIZE);
+        memory.set(far_away, 4);
+        assert_eq!(memory.get(far_away), Some(&4));
+        assert_eq!(memory.allocated_cells(), 2 * PAGE_SIZE);
+        let chunk_addresses: Vec<Address> = memory.chunks().iter().map(|c| c.0).collect();
+        assert_eq!(chunk_addresses, vec![0, far_away / PAGE_SIZE * PAGE_SIZE]);
+    }
+}
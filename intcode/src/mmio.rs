@@ -0,0 +1,540 @@
+use crate::error::ExecutionErrorKind;
+use crate::fuzz::Random;
+use crate::interpreter::{Address, Interpreter, Value};
+use crate::memory::MemoryBackend;
+use crate::word::Word;
+use std::collections::VecDeque;
+use std::fmt;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// A host device mapped onto a range of addresses, see `Interpreter::map_device`.
+///
+/// The offsets are relative to the start of the range.
+pub trait MappedDevice<W = Value> {
+    /// Called when an instruction reads a parameter from the range
+    fn read(&mut self, offset: Address) -> W;
+    /// Called when an instruction writes into the range
+    fn write(&mut self, offset: Address, value: W);
+}
+
+// Lets the caller keep a handle on the device given to the interpreter
+impl<W, T: MappedDevice<W>> MappedDevice<W> for Arc<Mutex<T>> {
+    fn read(&mut self, offset: Address) -> W {
+        self.lock().unwrap().read(offset)
+    }
+
+    fn write(&mut self, offset: Address, value: W) {
+        self.lock().unwrap().write(offset, value)
+    }
+}
+
+struct Mapping<W> {
+    range: Range<Address>,
+    // None in clones: devices can not be cloned
+    device: Option<Box<dyn MappedDevice<W> + Send>>,
+}
+
+// a device, and the offset of an address in its range
+type Target<'a, W> = (&'a mut dyn MappedDevice<W>, Address);
+
+// The devices mapped in the memory of an interpreter. Devices can not be cloned, so clones of the
+// memory keep the mapped ranges without their devices, and fail to read or write in them.
+pub(crate) struct DeviceMap<W> {
+    mappings: Vec<Mapping<W>>,
+}
+
+impl<W> DeviceMap<W> {
+    pub(crate) fn new() -> Self {
+        DeviceMap {
+            mappings: Vec::new(),
+        }
+    }
+
+    // the device mapped at address, and the offset of address in its range. An error if the
+    // device was left behind by a clone.
+    pub(crate) fn device_at(
+        &mut self,
+        address: Address,
+    ) -> Result<Option<Target<'_, W>>, ExecutionErrorKind<W>> {
+        if self.mappings.is_empty() {
+            return Ok(None);
+        }
+        let mapping = match self
+            .mappings
+            .iter_mut()
+            .find(|mapping| mapping.range.contains(&address))
+        {
+            Some(mapping) => mapping,
+            None => return Ok(None),
+        };
+        match &mut mapping.device {
+            Some(device) => Ok(Some((device.as_mut(), address - mapping.range.start))),
+            None => Err(ExecutionErrorKind::DetachedDevice(address)),
+        }
+    }
+
+    pub(crate) fn is_mapped(&self, address: Address) -> bool {
+        self.mappings
+            .iter()
+            .any(|mapping| mapping.range.contains(&address))
+    }
+}
+
+impl<W> Clone for DeviceMap<W> {
+    fn clone(&self) -> Self {
+        let mappings = self
+            .mappings
+            .iter()
+            .map(|mapping| Mapping {
+                range: mapping.range.clone(),
+                device: None,
+            })
+            .collect();
+        DeviceMap { mappings }
+    }
+}
+
+impl<W> fmt::Debug for DeviceMap<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.mappings.iter().map(|mapping| &mapping.range))
+            .finish()
+    }
+}
+
+impl<W: Word, M: MemoryBackend<W>> Interpreter<W, M> {
+    /// Maps `device` onto `range`: from now on, the parameters instructions read from the range
+    /// come from `device.read`, and their writes into it go to `device.write`, with the offset of
+    /// the address in the range.
+    ///
+    /// The cells of the range are left alone: instructions are still fetched from them, and
+    /// `read_memory` and `write_memory` still see them. Device IO is not undone by `step_back`.
+    /// Devices are not cloned: the ranges of a clone stay mapped, but its reads and writes in
+    /// them stop the program with a `DetachedDevice` error until a device is mapped onto the same
+    /// range again. Give the interpreter an `Arc<Mutex<_>>` to share a device with its clones.
+    ///
+    /// Panics if the range is empty or overlaps a range already mapped, other than the same range
+    /// left without its device by a clone.
+    pub fn map_device(
+        &mut self,
+        range: Range<Address>,
+        device: impl MappedDevice<W> + Send + 'static,
+    ) {
+        assert!(range.start < range.end, "empty device range {:?}", range);
+        let mappings = &mut self.memory.devices.mappings;
+        if let Some(mapping) = mappings
+            .iter_mut()
+            .find(|mapping| mapping.range == range && mapping.device.is_none())
+        {
+            mapping.device = Some(Box::new(device));
+            return;
+        }
+        if let Some(mapping) = mappings
+            .iter()
+            .find(|mapping| mapping.range.start < range.end && range.start < mapping.range.end)
+        {
+            panic!(
+                "device range {:?} overlaps the mapped range {:?}",
+                range, mapping.range
+            );
+        }
+        mappings.push(Mapping {
+            range,
+            device: Some(Box::new(device)),
+        });
+    }
+
+    /// Removes the device mapped at `address`, if any, and returns it. A range left without its
+    /// device by a clone is unmapped too, returning `None`.
+    pub fn unmap_device(&mut self, address: Address) -> Option<Box<dyn MappedDevice<W> + Send>> {
+        let mappings = &mut self.memory.devices.mappings;
+        let index = mappings
+            .iter()
+            .position(|mapping| mapping.range.contains(&address))?;
+        mappings.remove(index).device
+    }
+
+    /// The ranges devices are mapped onto, in the order they were mapped, including those a clone
+    /// left without their device
+    pub fn mapped_ranges(&self) -> Vec<Range<Address>> {
+        self.memory
+            .devices
+            .mappings
+            .iter()
+            .map(|mapping| mapping.range.clone())
+            .collect()
+    }
+}
+
+/// A grid of `width` × `height` cells, row after row, that the program draws into by writing.
+/// Reads return what was written last, 0 at first. Offsets past the grid are ignored and read 0.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Framebuffer<W = Value> {
+    width: usize,
+    height: usize,
+    cells: Vec<W>,
+}
+
+impl<W: Word> Framebuffer<W> {
+    pub fn new(width: usize, height: usize) -> Self {
+        Framebuffer {
+            width,
+            height,
+            cells: vec![W::from_i64(0); width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The number of addresses to map, `width * height`
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> &W {
+        assert!(
+            x < self.width && y < self.height,
+            "({}, {}) is off screen",
+            x,
+            y
+        );
+        &self.cells[y * self.width + x]
+    }
+
+    /// The cells, row after row
+    pub fn pixels(&self) -> &[W] {
+        &self.cells
+    }
+}
+
+impl<W: Word> MappedDevice<W> for Framebuffer<W> {
+    fn read(&mut self, offset: Address) -> W {
+        match self.cells.get(offset) {
+            Some(value) => value.clone(),
+            None => W::from_i64(0),
+        }
+    }
+
+    fn write(&mut self, offset: Address, value: W) {
+        if let Some(cell) = self.cells.get_mut(offset) {
+            *cell = value;
+        }
+    }
+}
+
+// Same characters as the hull painted on day 11: '+' for lit cells, ' ' for the others
+impl<W: Word> fmt::Display for Framebuffer<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let zero = W::from_i64(0);
+        for row in self.cells.chunks(self.width.max(1)) {
+            for cell in row {
+                write!(f, "{}", if *cell == zero { ' ' } else { '+' })?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Keys pressed by the host, waiting for the program.
+///
+/// Reading offset 0 takes the next key, or 0 if none is waiting; reading offset 1 gives the
+/// number of keys waiting, without taking any. Writes are ignored.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Keyboard<W = Value> {
+    keys: VecDeque<W>,
+}
+
+impl<W: Word> Keyboard<W> {
+    pub fn new() -> Self {
+        Keyboard {
+            keys: VecDeque::new(),
+        }
+    }
+
+    pub fn press(&mut self, key: W) {
+        self.keys.push_back(key);
+    }
+
+    /// Presses the character codes of `text`
+    pub fn type_text(&mut self, text: &str) {
+        for byte in text.bytes() {
+            self.press(W::from_i64(i64::from(byte)));
+        }
+    }
+
+    pub fn pending(&self) -> usize {
+        self.keys.len()
+    }
+}
+
+impl<W: Word> MappedDevice<W> for Keyboard<W> {
+    fn read(&mut self, offset: Address) -> W {
+        match offset {
+            0 => self.keys.pop_front().unwrap_or_else(|| W::from_i64(0)),
+            1 => W::from_i64(self.keys.len() as i64),
+            _ => W::from_i64(0),
+        }
+    }
+
+    fn write(&mut self, _offset: Address, _value: W) {}
+}
+
+/// Reading any offset gives the milliseconds elapsed since the clock started. Writes are
+/// ignored.
+///
+/// A manual clock only moves when the host advances it, for reproducible runs.
+#[derive(Debug, Clone)]
+pub struct Clock {
+    source: ClockSource,
+}
+
+#[derive(Debug, Clone)]
+enum ClockSource {
+    System(Instant),
+    Manual(u64),
+}
+
+impl Clock {
+    /// A clock on the system time, starting now
+    pub fn new() -> Self {
+        Clock {
+            source: ClockSource::System(Instant::now()),
+        }
+    }
+
+    /// A clock stopped at 0 until `advance` is called
+    pub fn manual() -> Self {
+        Clock {
+            source: ClockSource::Manual(0),
+        }
+    }
+
+    /// Moves a manual clock forward. Panics on a system clock.
+    pub fn advance(&mut self, milliseconds: u64) {
+        match &mut self.source {
+            ClockSource::Manual(elapsed) => *elapsed += milliseconds,
+            ClockSource::System(_) => panic!("only a manual clock can be advanced"),
+        }
+    }
+
+    pub fn elapsed_milliseconds(&self) -> u64 {
+        match &self.source {
+            ClockSource::System(start) => start.elapsed().as_millis() as u64,
+            ClockSource::Manual(elapsed) => *elapsed,
+        }
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Clock::new()
+    }
+}
+
+impl<W: Word> MappedDevice<W> for Clock {
+    fn read(&mut self, _offset: Address) -> W {
+        W::from_i64(self.elapsed_milliseconds() as i64)
+    }
+
+    fn write(&mut self, _offset: Address, _value: W) {}
+}
+
+/// Reading any offset gives a new pseudo-random number in 0..2^31; writing any offset reseeds
+/// the generator with the value written. The same seed gives the same numbers.
+#[derive(Debug, Clone)]
+pub struct RandomSource {
+    random: Random,
+}
+
+impl RandomSource {
+    pub fn new(seed: u64) -> Self {
+        RandomSource {
+            random: Random::new(seed),
+        }
+    }
+}
+
+impl<W: Word> MappedDevice<W> for RandomSource {
+    fn read(&mut self, _offset: Address) -> W {
+        W::from_i64((self.random.next() >> 33) as i64)
+    }
+
+    fn write(&mut self, _offset: Address, value: W) {
+        // seeds that do not fit in 64 bits are truncated to their low bits
+        let seed = value.to_i64().map_or(0, |seed| seed as u64);
+        self.random = Random::new(seed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::assemble;
+    use crate::device::Io;
+
+    fn interpreter(source: &str) -> Interpreter {
+        Interpreter::from_code(&assemble(source).unwrap()).unwrap()
+    }
+
+    fn run(interpreter: &mut Interpreter) -> Vec<Value> {
+        let mut outputs = Vec::new();
+        interpreter.run_with(Io::new((), &mut outputs)).unwrap();
+        outputs
+    }
+
+    #[test]
+    fn test_framebuffer() {
+        // draws a diagonal on a 4x3 screen at 1000, then reads two pixels back
+        let mut interpreter = interpreter(
+            "
+                add #1, #0, [pos 1000]
+                add #1, #0, [pos 1005]
+                add #7, #0, [pos 1010]
+                out [pos 1010]
+                out [pos 1011]
+                hlt
+            ",
+        );
+        let screen = Arc::new(Mutex::new(Framebuffer::new(4, 3)));
+        interpreter.map_device(1000..1012, screen.clone());
+        assert_eq!(run(&mut interpreter), vec![7, 0]);
+        let screen = screen.lock().unwrap();
+        assert_eq!(*screen.pixel(2, 2), 7);
+        assert_eq!(screen.to_string(), "+   \n +  \n  + \n");
+        // the cells of the memory are untouched
+        assert_eq!(interpreter.read_memory(1005), 0);
+    }
+
+    #[test]
+    fn test_keyboard() {
+        // outputs the number of keys waiting, then echoes keys until it reads 0
+        let mut interpreter = interpreter(
+            "
+                        out [pos 501]
+                loop:   add [pos 500], #0, [pos key]
+                        jf [pos key], #end
+                        out [pos key]
+                        jt #1, #loop
+                end:    hlt
+                key:    .data 0
+            ",
+        );
+        let mut keyboard = Keyboard::new();
+        keyboard.type_text("hi");
+        interpreter.map_device(500..502, keyboard);
+        assert_eq!(run(&mut interpreter), vec![2, 104, 105]);
+    }
+
+    #[test]
+    fn test_clock_and_random() {
+        let mut interpreter = interpreter(
+            "
+                out [pos 200]
+                out [pos 300]
+                out [pos 300]
+                add #42, #0, [pos 300]
+                out [pos 300]
+                hlt
+            ",
+        );
+        let clock = Arc::new(Mutex::new(Clock::manual()));
+        interpreter.map_device(200..201, clock.clone());
+        interpreter.map_device(300..301, RandomSource::new(1));
+        clock.lock().unwrap().advance(1500);
+        let outputs = run(&mut interpreter);
+        assert_eq!(outputs[0], 1500);
+        assert_ne!(outputs[1], outputs[2]);
+        assert!(outputs[1..]
+            .iter()
+            .all(|&value| (0..1 << 31).contains(&value)));
+        let mut reseeded = RandomSource::new(0);
+        MappedDevice::<Value>::write(&mut reseeded, 0, 42);
+        assert_eq!(outputs[3], MappedDevice::<Value>::read(&mut reseeded, 0));
+    }
+
+    #[test]
+    fn test_mapping() {
+        let mut interpreter: Interpreter = Interpreter::from_code("99").unwrap();
+        interpreter.map_device(10..20, Keyboard::new());
+        interpreter.map_device(20..30, Clock::manual());
+        assert_eq!(interpreter.mapped_ranges(), vec![10..20, 20..30]);
+        assert_eq!(interpreter.clone().mapped_ranges(), vec![10..20, 20..30]);
+        assert!(interpreter.unmap_device(25).is_some());
+        assert!(interpreter.unmap_device(25).is_none());
+        assert_eq!(interpreter.mapped_ranges(), vec![10..20]);
+    }
+
+    #[test]
+    fn test_clone() {
+        let mut interpreter = interpreter(
+            "
+                out [pos 200]
+                hlt
+            ",
+        );
+        let clock = Arc::new(Mutex::new(Clock::manual()));
+        clock.lock().unwrap().advance(1500);
+        interpreter.map_device(200..201, clock.clone());
+        let mut clone = interpreter.clone();
+        let error = clone.run_until_block().unwrap_err();
+        assert_eq!(error.kind(), &ExecutionErrorKind::DetachedDevice(200));
+        assert_eq!(error.instruction_pointer(), 0);
+        let error = clone.clone().compile().run_until_block().unwrap_err();
+        assert_eq!(error.kind(), &ExecutionErrorKind::DetachedDevice(200));
+        // the range is mapped again in the clone
+        clone.map_device(200..201, clock);
+        assert_eq!(run(&mut clone), vec![1500]);
+        assert_eq!(run(&mut interpreter), vec![1500]);
+    }
+
+    #[test]
+    #[should_panic(expected = "overlaps")]
+    fn test_overlapping_detached_range() {
+        let mut interpreter: Interpreter = Interpreter::from_code("99").unwrap();
+        interpreter.map_device(10..20, Keyboard::new());
+        let mut clone = interpreter.clone();
+        clone.map_device(10..21, Keyboard::new());
+    }
+
+    #[test]
+    #[should_panic(expected = "overlaps")]
+    fn test_overlapping_ranges() {
+        let mut interpreter: Interpreter = Interpreter::from_code("99").unwrap();
+        interpreter.map_device(10..20, Keyboard::new());
+        interpreter.map_device(19..21, Keyboard::new());
+    }
+
+    #[test]
+    fn test_compiled() {
+        let mut interpreter = interpreter(
+            "
+                add [pos 500], [pos 500], [pos 600]
+                out [pos 601]
+                hlt
+            ",
+        );
+        let mut keyboard = Keyboard::new();
+        keyboard.type_text("ab");
+        interpreter.map_device(500..501, keyboard);
+        let screen = Arc::new(Mutex::new(Framebuffer::new(2, 1)));
+        interpreter.map_device(600..602, screen.clone());
+        let mut compiled = interpreter.compile();
+        let mut outputs = Vec::new();
+        compiled.run_with(Io::new((), &mut outputs)).unwrap();
+        assert_eq!(outputs, vec![0]);
+        assert_eq!(screen.lock().unwrap().pixels(), &[97 + 98, 0]);
+    }
+}
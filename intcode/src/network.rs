@@ -0,0 +1,471 @@
+use crate::error::ExecutionError;
+use crate::interpreter::{IOAction, Interpreter, Value};
+use crate::memory::{DenseMemory, MemoryBackend};
+use crate::word::Word;
+use std::error;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+
+// Reads of the idle input in a row after which a machine is considered idle: a machine reading
+// it once may only be polling before its next output
+const IDLE_READS: u64 = 2;
+
+/// How the outputs of the machines of a `Network` are routed.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Topology {
+    /// Machine `i` feeds machine `i + 1`. The outputs of the last machine leave the network.
+    Chain,
+    /// Like `Chain`, but the last machine also feeds the first one (day 7's feedback loop).
+    Ring,
+    /// Every output is sent to every other machine, and leaves the network.
+    Broadcast,
+    /// Outputs are packets: a destination machine, followed by `payload` values that are sent to
+    /// it. Packets to an address outside of the network leave it, address included.
+    Addressed { payload: usize },
+}
+
+/// Why a `Network` stopped.
+#[non_exhaustive]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Quiescence {
+    /// Every machine halted
+    Halted,
+    /// Some machines are blocked on an input that no other machine will ever send
+    Deadlock,
+    /// Every machine that did not halt read the idle input twice in a row, without outputting
+    /// anything in between, see `Network::set_idle_input`
+    Idle,
+}
+
+/// The state of a `Network` once it stopped.
+pub struct NetworkRun<W = Value, M = DenseMemory<W>> {
+    pub quiescence: Quiescence,
+    /// The values that left the network, in the order they were produced
+    pub outputs: Vec<W>,
+    /// The machines, in the same order as given to `Network::new`. Inputs that were sent to them
+    /// but not read yet are in their input queue, so running them again resumes the network.
+    pub machines: Vec<Interpreter<W, M>>,
+    /// The indices of the machines that did not halt
+    pub waiting: Vec<usize>,
+}
+
+/// A machine of a `Network` failed. The other machines are stopped.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct NetworkError<W = Value> {
+    pub machine: usize,
+    pub error: ExecutionError<W>,
+}
+
+impl<W: Word> fmt::Display for NetworkError<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "machine {}: {}", self.machine, self.error)
+    }
+}
+
+impl<W: Word> error::Error for NetworkError<W> {}
+
+/// Runs interpreters connected by a `Topology`, each one on its own thread.
+///
+/// Inputs already queued in the interpreters (e.g. day 7's phase settings) are read first. The
+/// network stops once every machine halted, or once the machines that did not are all waiting for
+/// an input and no value is in flight.
+pub struct Network<W = Value, M = DenseMemory<W>> {
+    machines: Vec<Interpreter<W, M>>,
+    topology: Topology,
+    idle_input: Option<W>,
+}
+
+// What the machine threads tell the router
+enum Event<W> {
+    Output(usize, W),
+    // the machine found its input channel empty, after reading that many values from it
+    Waiting(usize, u64),
+    Halted(usize),
+    Failed(usize),
+}
+
+// What a machine thread gives back. Its input channel outlives the thread, so that no value sent
+// to it is lost.
+struct Machine<W, M> {
+    interpreter: Interpreter<W, M>,
+    inputs: Receiver<W>,
+    result: Result<(), ExecutionError<W>>,
+}
+
+impl<W, M> Network<W, M>
+where
+    W: Word + Send + 'static,
+    M: MemoryBackend<W> + Send + 'static,
+{
+    pub fn new(machines: Vec<Interpreter<W, M>>, topology: Topology) -> Self {
+        Network {
+            machines,
+            topology,
+            idle_input: None,
+        }
+    }
+
+    /// Instead of blocking, machines read `idle_input` when no input is available (day 23 uses
+    /// -1). The network then stops with `Quiescence::Idle` rather than `Deadlock`.
+    pub fn set_idle_input(&mut self, idle_input: Option<W>) {
+        self.idle_input = idle_input;
+    }
+
+    pub fn run(self) -> Result<NetworkRun<W, M>, NetworkError<W>> {
+        let count = self.machines.len();
+        let stop = Arc::new(AtomicBool::new(false));
+        let (event_sender, events) = mpsc::channel();
+        let mut input_senders = Vec::with_capacity(count);
+        let mut threads = Vec::with_capacity(count);
+        for (index, interpreter) in self.machines.into_iter().enumerate() {
+            let (input_sender, inputs) = mpsc::channel();
+            input_senders.push(input_sender);
+            let events = event_sender.clone();
+            let stop = stop.clone();
+            let idle_input = self.idle_input.clone();
+            threads.push(thread::spawn(move || {
+                run_machine(index, interpreter, inputs, events, &stop, idle_input)
+            }));
+        }
+        drop(event_sender);
+
+        let mut router = Router::new(self.topology, count);
+        // number of values sent to each machine
+        let mut delivered = vec![0; count];
+        // `Some(n)` once a machine found its input empty after reading `n` values, which means
+        // it is waiting if nothing was sent to it since
+        let mut waiting: Vec<Option<u64>> = vec![None; count];
+        let mut halted = vec![false; count];
+        let mut failed = None;
+        let quiescent = |waiting: &[Option<u64>], halted: &[bool], delivered: &[u64]| {
+            (0..count).all(|i| halted[i] || waiting[i] == Some(delivered[i]))
+        };
+        for event in &events {
+            match event {
+                Event::Output(machine, value) => {
+                    waiting[machine] = None;
+                    router.route(machine, value, |to, value| {
+                        delivered[to] += 1;
+                        // the receivers live as long as the network
+                        input_senders[to].send(value).unwrap();
+                    });
+                }
+                Event::Waiting(machine, read) => waiting[machine] = Some(read),
+                Event::Halted(machine) => halted[machine] = true,
+                Event::Failed(machine) => {
+                    failed = Some(machine);
+                    break;
+                }
+            }
+            if quiescent(&waiting, &halted, &delivered) {
+                break;
+            }
+        }
+
+        // Blocked machines wake up once their input channel is closed
+        stop.store(true, Ordering::Relaxed);
+        input_senders.clear();
+        let machines: Vec<Machine<W, M>> = threads
+            .into_iter()
+            .map(|thread| thread.join().expect("machine thread panicked"))
+            .collect();
+        if let Some(index) = failed {
+            let error = machines[index].result.clone().unwrap_err();
+            return Err(NetworkError {
+                machine: index,
+                error,
+            });
+        }
+        // inputs sent but not read are kept, so that the machines can be resumed
+        let mut machines: Vec<Interpreter<W, M>> = machines
+            .into_iter()
+            .map(|mut machine| {
+                machine
+                    .interpreter
+                    .input_queue
+                    .extend(machine.inputs.try_iter());
+                machine.interpreter
+            })
+            .collect();
+        // outputs produced while the network was stopping
+        for event in events.try_iter() {
+            if let Event::Output(machine, value) = event {
+                router.route(machine, value, |to, value| machines[to].queue_input(value));
+            }
+        }
+
+        let waiting: Vec<usize> = (0..count).filter(|&i| !halted[i]).collect();
+        let quiescence = if waiting.is_empty() {
+            Quiescence::Halted
+        } else if self.idle_input.is_some() {
+            Quiescence::Idle
+        } else {
+            Quiescence::Deadlock
+        };
+        Ok(NetworkRun {
+            quiescence,
+            outputs: router.outputs,
+            machines,
+            waiting,
+        })
+    }
+}
+
+// Applies a topology to the outputs of the machines
+pub(crate) struct Router<W> {
+    topology: Topology,
+    count: usize,
+    // the packet each machine is writing, for the addressed topology
+    packets: Vec<Vec<W>>,
+    // the values that left the network
+    pub(crate) outputs: Vec<W>,
+}
+
+impl<W: Word> Router<W> {
+    pub(crate) fn new(topology: Topology, count: usize) -> Self {
+        Router {
+            topology,
+            count,
+            packets: vec![Vec::new(); count],
+            outputs: Vec::new(),
+        }
+    }
+
+    // `deliver(to, value)` sends a value to another machine
+    pub(crate) fn route(&mut self, from: usize, value: W, mut deliver: impl FnMut(usize, W)) {
+        match self.topology {
+            Topology::Chain | Topology::Ring => {
+                let to = from + 1;
+                if to < self.count {
+                    deliver(to, value);
+                } else {
+                    if self.topology == Topology::Ring {
+                        deliver(0, value.clone());
+                    }
+                    self.outputs.push(value);
+                }
+            }
+            Topology::Broadcast => {
+                for to in (0..self.count).filter(|&to| to != from) {
+                    deliver(to, value.clone());
+                }
+                self.outputs.push(value);
+            }
+            Topology::Addressed { payload } => {
+                self.packets[from].push(value);
+                if self.packets[from].len() <= payload {
+                    return;
+                }
+                let mut packet = std::mem::take(&mut self.packets[from]).into_iter();
+                let address = packet.next().unwrap();
+                match address.to_address().filter(|&to| to < self.count) {
+                    Some(to) => {
+                        for value in packet {
+                            deliver(to, value);
+                        }
+                    }
+                    None => {
+                        self.outputs.push(address);
+                        self.outputs.extend(packet);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Checking the stop flag at every instruction is cheap next to running it
+fn run_machine<W: Word, M: MemoryBackend<W>>(
+    index: usize,
+    mut interpreter: Interpreter<W, M>,
+    inputs: Receiver<W>,
+    events: Sender<Event<W>>,
+    stop: &AtomicBool,
+    idle_input: Option<W>,
+) -> Machine<W, M> {
+    let mut read = 0;
+    // reads in a row that found no input, since the machine last read a value or output one.
+    // Waiting is reported once, when they reach `waiting_after`.
+    let mut empty_reads = 0;
+    let waiting_after = if idle_input.is_some() { IDLE_READS } else { 1 };
+    let result = loop {
+        if stop.load(Ordering::Relaxed) {
+            break Ok(());
+        }
+        match interpreter.step() {
+            Err(error) => {
+                let _ = events.send(Event::Failed(index));
+                break Err(error);
+            }
+            Ok(None) => {}
+            Ok(Some(IOAction::Halt)) => {
+                let _ = events.send(Event::Halted(index));
+                break Ok(());
+            }
+            Ok(Some(IOAction::ProduceOutput(value))) => {
+                empty_reads = 0;
+                let _ = events.send(Event::Output(index, value));
+            }
+            Ok(Some(_)) => {
+                let input = match inputs.try_recv() {
+                    Ok(input) => Some(input),
+                    Err(TryRecvError::Empty) => None,
+                    Err(TryRecvError::Disconnected) => break Ok(()),
+                };
+                if let Some(input) = input {
+                    read += 1;
+                    empty_reads = 0;
+                    interpreter.queue_input(input);
+                    continue;
+                }
+                empty_reads += 1;
+                if empty_reads == waiting_after {
+                    let _ = events.send(Event::Waiting(index, read));
+                }
+                match &idle_input {
+                    Some(idle_input) => interpreter.queue_input(idle_input.clone()),
+                    None => match inputs.recv() {
+                        Ok(input) => {
+                            read += 1;
+                            empty_reads = 0;
+                            interpreter.queue_input(input);
+                        }
+                        Err(_) => break Ok(()),
+                    },
+                }
+            }
+        }
+    };
+    Machine {
+        interpreter,
+        inputs,
+        result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::assemble;
+    use crate::error::ExecutionErrorKind;
+
+    // reads a value, outputs it plus one, and halts
+    const INCREMENT: &str = "3,9,1001,9,1,9,4,9,99,0";
+    // forever reads a value and outputs it plus one
+    const INCREMENT_LOOP: &str = "3,11,1001,11,1,11,4,11,1105,1,0,0";
+
+    fn machines(code: &str, count: usize) -> Vec<Interpreter> {
+        let interpreter: Interpreter = Interpreter::from_code(code).unwrap();
+        vec![interpreter; count]
+    }
+
+    #[test]
+    fn test_chain() {
+        let mut machines = machines(INCREMENT, 5);
+        machines[0].queue_input(10);
+        let run = Network::new(machines, Topology::Chain).run().unwrap();
+        assert_eq!(run.quiescence, Quiescence::Halted);
+        assert_eq!(run.outputs, vec![15]);
+        assert!(run.waiting.is_empty());
+    }
+
+    #[test]
+    fn test_chain_deadlock() {
+        let mut machines = machines(INCREMENT_LOOP, 2);
+        machines[0].queue_input(0);
+        let mut run = Network::new(machines, Topology::Chain).run().unwrap();
+        assert_eq!(run.quiescence, Quiescence::Deadlock);
+        assert_eq!(run.outputs, vec![2]);
+        assert_eq!(run.waiting, vec![0, 1]);
+
+        // the machines resume where they stopped
+        run.machines[0].queue_input(5);
+        let run = Network::new(run.machines, Topology::Chain).run().unwrap();
+        assert_eq!(run.outputs, vec![7]);
+    }
+
+    #[test]
+    fn test_ring() {
+        let mut machines = machines(INCREMENT, 3);
+        machines[0].queue_input(0);
+        let run = Network::new(machines, Topology::Ring).run().unwrap();
+        assert_eq!(run.quiescence, Quiescence::Halted);
+        assert_eq!(run.outputs, vec![3]);
+        // the first machine halted before the last output came back to it
+        assert_eq!(run.machines[0].input_queue, vec![3]);
+    }
+
+    #[test]
+    fn test_broadcast() {
+        let mut machines = machines(INCREMENT, 3);
+        machines[1].queue_input(41);
+        let run = Network::new(machines, Topology::Broadcast).run().unwrap();
+        // 1 sends 42 to 0 and 2, which both send 43 to the two others
+        assert_eq!(run.quiescence, Quiescence::Halted);
+        assert_eq!(run.outputs, vec![42, 43, 43]);
+        assert_eq!(run.machines[1].input_queue, vec![43, 43]);
+    }
+
+    #[test]
+    fn test_addressed_idle() {
+        // sends 100 + address to the next address, then sends every value it receives to
+        // address 9
+        let source = "
+                    in [pos address]
+                    add [pos address], #1, [pos next]
+                    out [pos next]
+                    add [pos address], #100, [pos value]
+                    out [pos value]
+            loop:   in [pos value]
+                    eq [pos value], #-1, [pos idle]
+                    jt [pos idle], #loop
+                    out #9
+                    out [pos value]
+                    jt #1, #loop
+            address: .data 0
+            next:   .data 0
+            value:  .data 0
+            idle:   .data 0
+        ";
+        let mut machines = machines(&assemble(source).unwrap(), 3);
+        for (address, machine) in machines.iter_mut().enumerate() {
+            machine.queue_input(address as Value);
+        }
+        let mut network = Network::new(machines, Topology::Addressed { payload: 1 });
+        network.set_idle_input(Some(-1));
+        let run = network.run().unwrap();
+        assert_eq!(run.quiescence, Quiescence::Idle);
+        assert_eq!(run.waiting, vec![0, 1, 2]);
+        let mut packets: Vec<&[Value]> = run.outputs.chunks(2).collect();
+        packets.sort();
+        assert_eq!(packets, vec![&[3, 102], &[9, 100], &[9, 101]]);
+    }
+
+    #[test]
+    fn test_idle_machine_may_still_output() {
+        // outputs 42 after its first read of the idle input, then only reads
+        let machines = machines("3,9,104,42,3,9,1105,1,4,0", 1);
+        let mut network = Network::new(machines, Topology::Chain);
+        network.set_idle_input(Some(-1));
+        let run = network.run().unwrap();
+        assert_eq!(run.quiescence, Quiescence::Idle);
+        assert_eq!(run.outputs, vec![42]);
+    }
+
+    #[test]
+    fn test_failure_stops_the_network() {
+        let mut machines = machines(INCREMENT_LOOP, 2);
+        machines[1] = Interpreter::from_code("3,5,4,5,42,0").unwrap();
+        machines[0].queue_input(1);
+        let error = Network::new(machines, Topology::Ring).run().err().unwrap();
+        assert_eq!(error.machine, 1);
+        assert_eq!(error.error.kind(), &ExecutionErrorKind::UnknownOpcode);
+        assert_eq!(
+            error.to_string(),
+            "machine 1: unknown opcode (instruction 42 at position 4)"
+        );
+    }
+}
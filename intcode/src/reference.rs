@@ -0,0 +1,168 @@
+// A reference implementation of Intcode for the fuzzer, following the puzzles of days 2, 5 and 9
+// without anything of the interpreter: no word trait, decoder, instruction set or memory backend.
+// Its errors are those of the interpreter, which it must report the same way.
+use crate::error::ExecutionErrorKind;
+use crate::fuzz::{Outcome, Stop};
+use crate::interpreter::{Address, Value};
+use std::convert::TryFrom;
+
+type Result<T> = std::result::Result<T, ExecutionErrorKind>;
+
+struct Machine<'a> {
+    memory: Vec<Value>,
+    memory_limit: usize,
+    ip: Address,
+    relative_base: Value,
+    inputs: &'a [Value],
+    outputs: Vec<Value>,
+}
+
+// what an instruction did, when the program goes on
+enum Step {
+    Next(Address),
+    Halt,
+    MissingInput,
+}
+
+impl<'a> Machine<'a> {
+    fn read(&self, address: Address) -> Value {
+        self.memory.get(address).copied().unwrap_or(0)
+    }
+
+    fn write(&mut self, address: Address, value: Value) -> Result<()> {
+        if address >= self.memory.len() {
+            if address >= self.memory_limit {
+                return Err(ExecutionErrorKind::MemoryLimitExceeded(address));
+            }
+            self.memory.resize(address + 1, 0);
+        }
+        self.memory[address] = value;
+        Ok(())
+    }
+
+    // the mode of parameter `index` (from 1) of the current instruction
+    fn mode(&self, index: u32) -> Value {
+        self.read(self.ip) / 10i64.pow(index + 1) % 10
+    }
+
+    // the address parameter `index` refers to, in position or relative mode
+    fn address(&self, index: u32) -> Result<Address> {
+        let parameter = self.read(self.ip + index as Address);
+        let address = match self.mode(index) {
+            0 => parameter,
+            1 => return Err(ExecutionErrorKind::WriteInImmediateMode),
+            _ => parameter
+                .checked_add(self.relative_base)
+                .ok_or(ExecutionErrorKind::ArithmeticOverflow)?,
+        };
+        if address < 0 {
+            return Err(ExecutionErrorKind::NegativeAddress(address));
+        }
+        Address::try_from(address).map_err(|_| ExecutionErrorKind::AddressOutOfRange(address))
+    }
+
+    fn parameter(&self, index: u32) -> Result<Value> {
+        if self.mode(index) == 1 {
+            return Ok(self.read(self.ip + index as Address));
+        }
+        Ok(self.read(self.address(index)?))
+    }
+
+    fn step(&mut self) -> Result<Step> {
+        let instruction = self.read(self.ip);
+        let opcode = instruction % 100;
+        if instruction < 0 || !matches!(opcode, 1..=9 | 99) {
+            return Err(ExecutionErrorKind::UnknownOpcode);
+        }
+        let mut modes = instruction / 100;
+        while modes > 0 {
+            if modes % 10 > 2 {
+                return Err(ExecutionErrorKind::InvalidParameterMode);
+            }
+            modes /= 10;
+        }
+        let next = match opcode {
+            1 | 2 | 7 | 8 => {
+                let (left, right) = (self.parameter(1)?, self.parameter(2)?);
+                let result = match opcode {
+                    1 => left.checked_add(right),
+                    2 => left.checked_mul(right),
+                    7 => Some((left < right) as Value),
+                    _ => Some((left == right) as Value),
+                };
+                let result = result.ok_or(ExecutionErrorKind::ArithmeticOverflow)?;
+                let address = self.address(3)?;
+                self.write(address, result)?;
+                self.ip + 4
+            }
+            3 => match self.inputs.split_first() {
+                Some((&input, rest)) => {
+                    let address = self.address(1)?;
+                    self.write(address, input)?;
+                    self.inputs = rest;
+                    self.ip + 2
+                }
+                None => return Ok(Step::MissingInput),
+            },
+            4 => {
+                let output = self.parameter(1)?;
+                self.outputs.push(output);
+                self.ip + 2
+            }
+            5 | 6 => {
+                if (self.parameter(1)? != 0) == (opcode == 5) {
+                    let target = self.parameter(2)?;
+                    if target < 0 {
+                        return Err(ExecutionErrorKind::NegativeAddress(target));
+                    }
+                    Address::try_from(target)
+                        .map_err(|_| ExecutionErrorKind::AddressOutOfRange(target))?
+                } else {
+                    self.ip + 3
+                }
+            }
+            9 => {
+                let adjustment = self.parameter(1)?;
+                self.relative_base = self
+                    .relative_base
+                    .checked_add(adjustment)
+                    .ok_or(ExecutionErrorKind::ArithmeticOverflow)?;
+                self.ip + 2
+            }
+            _ => return Ok(Step::Halt),
+        };
+        Ok(Step::Next(next))
+    }
+}
+
+/// Runs `program` on `inputs` for at most `max_steps` instructions, without writing at or past
+/// `memory_limit`
+pub(crate) fn run(
+    program: &[Value],
+    inputs: &[Value],
+    max_steps: u64,
+    memory_limit: usize,
+) -> Outcome {
+    let mut machine = Machine {
+        memory: program.to_vec(),
+        memory_limit,
+        ip: 0,
+        relative_base: 0,
+        inputs,
+        outputs: Vec::new(),
+    };
+    let mut steps = 0;
+    let stop = loop {
+        if steps == max_steps {
+            break Stop::StepLimit;
+        }
+        match machine.step() {
+            Ok(Step::Next(ip)) => machine.ip = ip,
+            Ok(Step::Halt) => break Stop::Halted,
+            Ok(Step::MissingInput) => break Stop::MissingInput,
+            Err(kind) => break Stop::Error(kind),
+        }
+        steps += 1;
+    };
+    Outcome::new(stop, machine.outputs, machine.memory)
+}
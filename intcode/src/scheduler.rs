@@ -0,0 +1,244 @@
+use crate::interpreter::{IOAction, Interpreter, Value};
+use crate::memory::{DenseMemory, MemoryBackend};
+use crate::network::{NetworkError, Quiescence, Router, Topology};
+use crate::word::Word;
+use std::collections::VecDeque;
+
+const DEFAULT_TIME_SLICE: u64 = 1000;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum State {
+    Runnable,
+    // blocked on an input
+    Parked,
+    Halted,
+}
+
+/// Runs interpreters connected by a `Topology` on the current thread, taking turns.
+///
+/// Runnable machines wait in a FIFO queue. The machine at its front runs until it has executed
+/// its time slice, blocks on an input or halts; it then goes to the back of the queue, is parked
+/// until another machine sends it a value, or leaves the queue for good. The same machines always
+/// run in the same order, so a run can be replayed exactly.
+pub struct Scheduler<W = Value, M = DenseMemory<W>> {
+    machines: Vec<Interpreter<W, M>>,
+    states: Vec<State>,
+    run_queue: VecDeque<usize>,
+    router: Router<W>,
+    time_slice: u64,
+    slices: u64,
+}
+
+impl<W: Word, M: MemoryBackend<W>> Scheduler<W, M> {
+    pub fn new(machines: Vec<Interpreter<W, M>>, topology: Topology) -> Self {
+        let count = machines.len();
+        Scheduler {
+            machines,
+            states: vec![State::Runnable; count],
+            run_queue: (0..count).collect(),
+            router: Router::new(topology, count),
+            time_slice: DEFAULT_TIME_SLICE,
+            slices: 0,
+        }
+    }
+
+    /// The number of instructions a machine runs before letting the next one run
+    pub fn set_time_slice(&mut self, instructions: u64) {
+        self.time_slice = instructions.max(1);
+    }
+
+    /// Queues an input for a machine, waking it up if it was parked
+    pub fn send(&mut self, machine: usize, value: W) {
+        self.machines[machine].queue_input(value);
+        if self.states[machine] == State::Parked {
+            self.states[machine] = State::Runnable;
+            self.run_queue.push_back(machine);
+        }
+    }
+
+    /// Runs the machines until none of them can make progress
+    pub fn run(&mut self) -> Result<Quiescence, NetworkError<W>> {
+        while self.run_slice()? {}
+        Ok(self.quiescence())
+    }
+
+    /// Gives one time slice to the next runnable machine. Returns false, without running
+    /// anything, if every machine is parked or halted.
+    pub fn run_slice(&mut self) -> Result<bool, NetworkError<W>> {
+        let machine = match self.run_queue.pop_front() {
+            Some(machine) => machine,
+            None => return Ok(false),
+        };
+        self.slices += 1;
+        let mut deliveries = Vec::new();
+        let mut state = State::Runnable;
+        let mut executed = 0;
+        let mut result = Ok(true);
+        while executed < self.time_slice {
+            let action = match self.machines[machine].step() {
+                Ok(action) => action,
+                Err(error) => {
+                    result = Err(NetworkError { machine, error });
+                    break;
+                }
+            };
+            match action {
+                None => executed += 1,
+                Some(IOAction::Halt) => {
+                    state = State::Halted;
+                    break;
+                }
+                Some(IOAction::ProduceOutput(value)) => {
+                    executed += 1;
+                    self.router
+                        .route(machine, value, |to, value| deliveries.push((to, value)));
+                }
+                Some(_) => {
+                    state = State::Parked;
+                    break;
+                }
+            }
+        }
+
+        self.states[machine] = state;
+        if result.is_err() {
+            // the failing machine is still at the faulty instruction, and runs first next time
+            self.run_queue.push_front(machine);
+        } else if state == State::Runnable {
+            self.run_queue.push_back(machine);
+        }
+        for (to, value) in deliveries {
+            self.send(to, value);
+        }
+        result
+    }
+
+    pub fn quiescence(&self) -> Quiescence {
+        if self.states.iter().all(|&state| state == State::Halted) {
+            Quiescence::Halted
+        } else {
+            Quiescence::Deadlock
+        }
+    }
+
+    /// The values that left the network, in the order they were produced
+    pub fn outputs(&self) -> &[W] {
+        &self.router.outputs
+    }
+
+    pub fn take_outputs(&mut self) -> Vec<W> {
+        std::mem::take(&mut self.router.outputs)
+    }
+
+    /// The machines blocked on an input
+    pub fn parked(&self) -> Vec<usize> {
+        self.machines_in(State::Parked)
+    }
+
+    pub fn halted(&self) -> Vec<usize> {
+        self.machines_in(State::Halted)
+    }
+
+    /// The number of time slices given so far
+    pub fn slices(&self) -> u64 {
+        self.slices
+    }
+
+    pub fn machines(&self) -> &[Interpreter<W, M>] {
+        &self.machines
+    }
+
+    pub fn into_machines(self) -> Vec<Interpreter<W, M>> {
+        self.machines
+    }
+
+    fn machines_in(&self, state: State) -> Vec<usize> {
+        (0..self.states.len())
+            .filter(|&machine| self.states[machine] == state)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ExecutionErrorKind;
+    use crate::trace::Profiler;
+    use std::sync::{Arc, Mutex};
+
+    // forever reads a value and outputs it plus one
+    const INCREMENT_LOOP: &str = "3,11,1001,11,1,11,4,11,1105,1,0,0";
+    // outputs 0, 1, 2... forever
+    const COUNTER: &str = "4,9,1001,9,1,9,1105,1,0,0";
+
+    fn machines(code: &str, count: usize) -> Vec<Interpreter> {
+        let interpreter: Interpreter = Interpreter::from_code(code).unwrap();
+        vec![interpreter; count]
+    }
+
+    #[test]
+    fn test_parking() {
+        let mut scheduler = Scheduler::new(machines(INCREMENT_LOOP, 1000), Topology::Chain);
+        assert_eq!(scheduler.run().unwrap(), Quiescence::Deadlock);
+        assert_eq!(scheduler.parked().len(), 1000);
+        assert_eq!(scheduler.slices(), 1000);
+
+        scheduler.send(0, 1);
+        assert_eq!(scheduler.run().unwrap(), Quiescence::Deadlock);
+        assert_eq!(scheduler.outputs(), &[1001]);
+        // each machine ran once more to handle the value
+        assert_eq!(scheduler.slices(), 2000);
+    }
+
+    #[test]
+    fn test_time_slices_interleave_machines() {
+        // two counters in a ring output three values per slice each, and never read them. Only
+        // the outputs of the second one leave the ring.
+        let mut scheduler = Scheduler::new(machines(COUNTER, 2), Topology::Ring);
+        scheduler.set_time_slice(9);
+        for _ in 0..4 {
+            assert!(scheduler.run_slice().unwrap());
+        }
+        assert_eq!(scheduler.take_outputs(), vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(scheduler.machines()[0].input_queue.len(), 6);
+        assert!(scheduler.outputs().is_empty());
+    }
+
+    #[test]
+    fn test_runs_replay_identically() {
+        let run = || {
+            let profiler = Arc::new(Mutex::new(Profiler::new()));
+            let mut machines = machines(INCREMENT_LOOP, 3);
+            machines[0] = Interpreter::from_code("3,9,1001,9,1,9,4,9,99,0").unwrap();
+            machines[1].set_trace_sink(profiler.clone());
+            let mut scheduler = Scheduler::new(machines, Topology::Ring);
+            scheduler.set_time_slice(2);
+            scheduler.send(0, 10);
+            let quiescence = scheduler.run().unwrap();
+            let total = profiler.lock().unwrap().total();
+            (
+                quiescence,
+                scheduler.take_outputs(),
+                scheduler.parked(),
+                total,
+            )
+        };
+        let first = run();
+        assert_eq!(first, (Quiescence::Deadlock, vec![13], vec![1, 2], 4));
+        assert_eq!(run(), first);
+    }
+
+    #[test]
+    fn test_failure() {
+        let mut machines = machines(INCREMENT_LOOP, 2);
+        machines[1] = Interpreter::from_code("3,5,4,5,42,0").unwrap();
+        let mut scheduler = Scheduler::new(machines, Topology::Chain);
+        scheduler.send(0, 1);
+        let error = scheduler.run().unwrap_err();
+        assert_eq!(error.machine, 1);
+        assert_eq!(error.error.kind(), &ExecutionErrorKind::UnknownOpcode);
+        // the first machine output its value before the second one failed
+        assert_eq!(scheduler.machines()[1].instruction_pointer(), 4);
+        assert_eq!(scheduler.run().unwrap_err(), error);
+    }
+}
@@ -0,0 +1,263 @@
+use crate::arithmetic::ArithmeticMode;
+use crate::interpreter::{Address, Interpreter, Memory};
+use crate::memory::MemoryBackend;
+use crate::word::Word;
+use std::collections::VecDeque;
+use std::error;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+
+const HEADER: &str = "intcode-snapshot 1";
+
+// A snapshot is a text file with one field per line, e.g.:
+//
+// intcode-snapshot 1
+// instruction_pointer 4
+// instruction_count 1
+// relative_base 0
+// arithmetic_mode checked
+// memory_limit none
+// input_queue 5,-3
+// memory 0 1002,4,3,4,33
+//
+// There is one `memory` line per chunk of allocated cells, starting with the address of its
+// first cell. The trace sink, the history, the input policy, the mapped devices and the
+// instruction set are not part of the snapshot.
+impl<W: Word, M: MemoryBackend<W>> Interpreter<W, M> {
+    /// Saves the complete state of the machine, so that `restore` can resume it later, possibly
+    /// in another process
+    pub fn save(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "{}", HEADER)?;
+        writeln!(writer, "instruction_pointer {}", self.instruction_pointer)?;
+        writeln!(writer, "instruction_count {}", self.instruction_count)?;
+        writeln!(writer, "relative_base {}", self.memory.relative_base)?;
+        let arithmetic_mode = match self.arithmetic_mode {
+            ArithmeticMode::Checked => "checked",
+            ArithmeticMode::Wrapping => "wrapping",
+            ArithmeticMode::Saturating => "saturating",
+        };
+        writeln!(writer, "arithmetic_mode {}", arithmetic_mode)?;
+        match self.memory.limit {
+            Some(limit) => writeln!(writer, "memory_limit {}", limit)?,
+            None => writeln!(writer, "memory_limit none")?,
+        }
+        writeln!(writer, "input_queue {}", join(self.input_queue.iter()))?;
+        for (address, values) in self.memory.values.chunks() {
+            writeln!(writer, "memory {} {}", address, join(values.iter()))?;
+        }
+        Ok(())
+    }
+
+    pub fn restore(reader: impl BufRead) -> Result<Self, Box<dyn error::Error>> {
+        let mut lines = reader.lines().enumerate();
+        match lines.next() {
+            Some((_, Ok(line))) if line == HEADER => {}
+            Some((_, Err(err))) => return Err(Box::new(err)),
+            _ => return Err("not an Intcode snapshot".into()),
+        }
+
+        let mut memory = Memory::new(M::from_values(Vec::new()));
+        let mut instruction_pointer = 0;
+        let mut instruction_count = 0;
+        let mut input_queue = VecDeque::new();
+        let mut arithmetic_mode = ArithmeticMode::default();
+        for (index, line) in lines {
+            let line = line?;
+            let at_line = |message: String| format!("line {}: {}", index + 1, message);
+            let (field, value) = match line.find(' ') {
+                Some(space) => (&line[..space], &line[space + 1..]),
+                None => (line.as_str(), ""),
+            };
+            match field {
+                "instruction_pointer" => {
+                    instruction_pointer = value.parse().map_err(|_| at_line(invalid(value)))?
+                }
+                "instruction_count" => {
+                    instruction_count = value.parse().map_err(|_| at_line(invalid(value)))?
+                }
+                "relative_base" => {
+                    memory.relative_base =
+                        W::parse_word(value).map_err(|_| at_line(invalid(value)))?
+                }
+                "arithmetic_mode" => {
+                    arithmetic_mode = match value {
+                        "checked" => ArithmeticMode::Checked,
+                        "wrapping" => ArithmeticMode::Wrapping,
+                        "saturating" => ArithmeticMode::Saturating,
+                        _ => return Err(at_line(invalid(value)).into()),
+                    }
+                }
+                "memory_limit" => {
+                    memory.limit = match value {
+                        "none" => None,
+                        _ => Some(value.parse().map_err(|_| at_line(invalid(value)))?),
+                    }
+                }
+                "input_queue" => input_queue = split(value).map_err(at_line)?.into_iter().collect(),
+                "memory" => {
+                    let (start, values) = match value.find(' ') {
+                        Some(space) => (&value[..space], &value[space + 1..]),
+                        None => (value, ""),
+                    };
+                    let start: Address = start.parse().map_err(|_| at_line(invalid(start)))?;
+                    for (offset, word) in split(values).map_err(at_line)?.into_iter().enumerate() {
+                        let address = start
+                            .checked_add(offset)
+                            .ok_or_else(|| at_line(format!("memory past address {}", start)))?;
+                        memory
+                            .store(address, word)
+                            .map_err(|kind| at_line(kind.to_string()))?;
+                    }
+                }
+                _ => return Err(at_line(format!("unknown field {}", field)).into()),
+            }
+        }
+
+        let mut interpreter = Interpreter::from_memory(memory);
+        interpreter.instruction_pointer = instruction_pointer;
+        interpreter.instruction_count = instruction_count;
+        interpreter.input_queue = input_queue;
+        interpreter.arithmetic_mode = arithmetic_mode;
+        Ok(interpreter)
+    }
+
+    pub fn save_to_file(&self, filename: &str) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(filename)?);
+        self.save(&mut writer)?;
+        writer.flush()
+    }
+
+    pub fn restore_from_file(filename: &str) -> Result<Self, Box<dyn error::Error>> {
+        Self::restore(BufReader::new(File::open(filename)?))
+    }
+}
+
+fn join<'a, W: Word + 'a>(words: impl Iterator<Item = &'a W>) -> String {
+    words
+        .map(|word| word.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn split<W: Word>(words: &str) -> Result<Vec<W>, String> {
+    if words.is_empty() {
+        return Ok(Vec::new());
+    }
+    words
+        .split(',')
+        .map(|word| W::parse_word(word).map_err(|_| invalid(word)))
+        .collect()
+}
+
+fn invalid(value: &str) -> String {
+    format!("invalid value {}", value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::{IOAction, Value};
+    use crate::memory::SparseMemory;
+
+    #[test]
+    fn test_save() {
+        let mut interpreter: Interpreter = Interpreter::from_code("109,-3,3,0,99").unwrap();
+        interpreter.set_arithmetic_mode(ArithmeticMode::Wrapping);
+        interpreter.queue_input(5);
+        interpreter.queue_input(-3);
+        assert_eq!(interpreter.step().unwrap(), None);
+
+        let mut snapshot = Vec::new();
+        interpreter.save(&mut snapshot).unwrap();
+        let expected_snapshot = [
+            HEADER,
+            "instruction_pointer 2",
+            "instruction_count 1",
+            "relative_base -3",
+            "arithmetic_mode wrapping",
+            "memory_limit 67108864",
+            "input_queue 5,-3",
+            "memory 0 109,-3,3,0,99",
+            "",
+        ];
+        assert_eq!(
+            String::from_utf8(snapshot).unwrap(),
+            expected_snapshot.join("\n")
+        );
+    }
+
+    #[test]
+    fn test_restore_resumes_execution() {
+        let quine = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+        let mut interpreter: Interpreter = Interpreter::from_code(quine).unwrap();
+        for _ in 0..5 {
+            interpreter.run_until_block().unwrap();
+        }
+        let mut snapshot = Vec::new();
+        interpreter.save(&mut snapshot).unwrap();
+
+        let mut restored: Interpreter = Interpreter::restore(&snapshot[..]).unwrap();
+        loop {
+            let expected = interpreter.run_until_block().unwrap();
+            assert_eq!(restored.run_until_block().unwrap(), expected);
+            if expected == IOAction::Halt {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn test_restore_sparse_memory() {
+        let far_away_write = "1101,20,22,1000000000000,99";
+        let mut interpreter =
+            Interpreter::<Value, SparseMemory<Value>>::from_code(far_away_write).unwrap();
+        interpreter.set_memory_limit(Some(4096));
+        interpreter.run_until_block().unwrap();
+        let mut snapshot = Vec::new();
+        interpreter.save(&mut snapshot).unwrap();
+
+        let restored = Interpreter::<Value, SparseMemory<Value>>::restore(&snapshot[..]).unwrap();
+        assert_eq!(restored.read_memory(1000000000000), 42);
+        assert_eq!(restored.read_memory(4), 99);
+        assert_eq!(restored.memory.values, interpreter.memory.values);
+        assert_eq!(restored.memory.limit, Some(4096));
+    }
+
+    #[test]
+    fn test_restore_errors() {
+        let restore = |snapshot: &str| Interpreter::<Value>::restore(snapshot.as_bytes());
+        assert_eq!(
+            restore("1,2,3").err().unwrap().to_string(),
+            "not an Intcode snapshot"
+        );
+        let snapshot = format!("{}\ninstruction_pointer 0\nmemory 0 1,x,3", HEADER);
+        assert_eq!(
+            restore(&snapshot).err().unwrap().to_string(),
+            "line 3: invalid value x"
+        );
+        let snapshot = format!("{}\nregisters 0", HEADER);
+        assert_eq!(
+            restore(&snapshot).err().unwrap().to_string(),
+            "line 2: unknown field registers"
+        );
+        let snapshot = format!("{}\nmemory 18446744073709551615 1,2", HEADER);
+        assert_eq!(
+            Interpreter::<Value, SparseMemory<Value>>::restore(snapshot.as_bytes())
+                .err()
+                .unwrap()
+                .to_string(),
+            "line 2: memory past address 18446744073709551615"
+        );
+        let snapshot = format!("{}\nmemory_limit 4096\nmemory 1000000000000 1", HEADER);
+        assert_eq!(
+            restore(&snapshot).err().unwrap().to_string(),
+            "line 3: writing at address 1000000000000 exceeds the memory limit"
+        );
+        let snapshot = format!("{}\nmemory 1000000000000 1", HEADER);
+        assert_eq!(
+            restore(&snapshot).err().unwrap().to_string(),
+            "line 2: writing at address 1000000000000 exceeds the memory limit"
+        );
+    }
+}
@@ -0,0 +1,1049 @@
+use crate::error::{ExecutionError, ExecutionErrorKind};
+use crate::interpreter::{
+    parse_code, parse_instruction_definition, to_address, Address, InstructionMode, Opcode, Value,
+};
+use crate::memory::DENSE_MEMORY_LIMIT;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::error;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::rc::Rc;
+
+const DEFAULT_MAX_STEPS: u64 = 1_000_000;
+const DEFAULT_MAX_PATHS: usize = 64;
+const DEFAULT_MAX_SEARCH: u64 = 1 << 24;
+// Expressions are evaluated, displayed and dropped recursively: deeper ones would overflow the
+// stack
+const MAX_EXPR_DEPTH: usize = 1_000;
+
+/// A value computed from the symbols of a `Solver`. Symbol `i` is displayed as `xi`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Expr {
+    Constant(Value),
+    Symbol(usize),
+    Add(Rc<Expr>, Rc<Expr>),
+    Multiply(Rc<Expr>, Rc<Expr>),
+    /// 1 if the first value is less than the second one, 0 otherwise
+    LessThan(Rc<Expr>, Rc<Expr>),
+    /// 1 if the values are equal, 0 otherwise
+    Equals(Rc<Expr>, Rc<Expr>),
+}
+
+impl Expr {
+    /// The value of the expression for the given values of the symbols, `None` on overflow
+    pub fn evaluate(&self, symbols: &[Value]) -> Option<Value> {
+        match self {
+            Expr::Constant(value) => Some(*value),
+            Expr::Symbol(symbol) => Some(symbols[*symbol]),
+            Expr::Add(a, b) => a.evaluate(symbols)?.checked_add(b.evaluate(symbols)?),
+            Expr::Multiply(a, b) => a.evaluate(symbols)?.checked_mul(b.evaluate(symbols)?),
+            Expr::LessThan(a, b) => Some((a.evaluate(symbols)? < b.evaluate(symbols)?) as Value),
+            Expr::Equals(a, b) => Some((a.evaluate(symbols)? == b.evaluate(symbols)?) as Value),
+        }
+    }
+
+    /// The symbols the expression depends on
+    pub fn symbols(&self) -> BTreeSet<usize> {
+        let mut symbols = BTreeSet::new();
+        self.collect_symbols(&mut symbols);
+        symbols
+    }
+
+    fn collect_symbols(&self, symbols: &mut BTreeSet<usize>) {
+        match self {
+            Expr::Constant(_) => {}
+            Expr::Symbol(symbol) => {
+                symbols.insert(*symbol);
+            }
+            Expr::Add(a, b) | Expr::Multiply(a, b) | Expr::LessThan(a, b) | Expr::Equals(a, b) => {
+                a.collect_symbols(symbols);
+                b.collect_symbols(symbols);
+            }
+        }
+    }
+
+    fn is_constant(&self) -> bool {
+        matches!(self, Expr::Constant(_))
+    }
+
+    // The expression as `constant + sum(coefficient * symbol)`, None if it is not linear or
+    // overflows
+    fn linear(&self) -> Option<Linear> {
+        match self {
+            Expr::Constant(value) => Some(Linear {
+                coefficients: BTreeMap::new(),
+                constant: *value,
+            }),
+            Expr::Symbol(symbol) => Some(Linear {
+                coefficients: vec![(*symbol, 1)].into_iter().collect(),
+                constant: 0,
+            }),
+            Expr::Add(a, b) => a.linear()?.add(b.linear()?),
+            Expr::Multiply(a, b) => a.linear()?.multiply(b.linear()?),
+            Expr::LessThan(_, _) | Expr::Equals(_, _) => None,
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expr::Constant(value) => write!(f, "{}", value),
+            Expr::Symbol(symbol) => write!(f, "x{}", symbol),
+            Expr::Add(a, b) => write!(f, "({} + {})", a, b),
+            Expr::Multiply(a, b) => write!(f, "({} * {})", a, b),
+            Expr::LessThan(a, b) => write!(f, "({} < {})", a, b),
+            Expr::Equals(a, b) => write!(f, "({} == {})", a, b),
+        }
+    }
+}
+
+struct Linear {
+    // symbol => coefficient, without zeros
+    coefficients: BTreeMap<usize, Value>,
+    constant: Value,
+}
+
+// Kept out of `Expr::linear`, whose frame is on the stack once per level of the expression
+impl Linear {
+    fn add(mut self, other: Linear) -> Option<Linear> {
+        self.constant = self.constant.checked_add(other.constant)?;
+        for (symbol, coefficient) in other.coefficients {
+            let total = self.coefficients.entry(symbol).or_insert(0);
+            *total = total.checked_add(coefficient)?;
+        }
+        self.coefficients.retain(|_, coefficient| *coefficient != 0);
+        Some(self)
+    }
+
+    fn multiply(self, other: Linear) -> Option<Linear> {
+        let (mut product, factor) =
+            match (self.coefficients.is_empty(), other.coefficients.is_empty()) {
+                (_, true) => (self, other.constant),
+                (true, false) => (other, self.constant),
+                (false, false) => return None,
+            };
+        product.constant = product.constant.checked_mul(factor)?;
+        for coefficient in product.coefficients.values_mut() {
+            *coefficient = coefficient.checked_mul(factor)?;
+        }
+        product
+            .coefficients
+            .retain(|_, coefficient| *coefficient != 0);
+        Some(product)
+    }
+}
+
+/// A condition on the symbols, under which a run goes the same way
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Constraint {
+    Equals(Rc<Expr>, Value),
+    NotZero(Rc<Expr>),
+}
+
+impl Constraint {
+    pub fn holds(&self, symbols: &[Value]) -> bool {
+        match self {
+            Constraint::Equals(expr, value) => expr.evaluate(symbols) == Some(*value),
+            Constraint::NotZero(expr) => expr.evaluate(symbols).is_some_and(|value| value != 0),
+        }
+    }
+
+    fn symbols(&self) -> BTreeSet<usize> {
+        match self {
+            Constraint::Equals(expr, _) | Constraint::NotZero(expr) => expr.symbols(),
+        }
+    }
+}
+
+impl fmt::Display for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Constraint::Equals(expr, value) => write!(f, "{} = {}", expr, value),
+            Constraint::NotZero(expr) => write!(f, "{} != 0", expr),
+        }
+    }
+}
+
+/// What can go wrong while executing a program symbolically
+#[non_exhaustive]
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum SymbolicError {
+    /// The program failed with the current values of the symbols
+    Execution(ExecutionError),
+    /// The program read more inputs than it was given, at that instruction
+    MissingInput(Address),
+    /// The program did not halt within the step limit
+    StepLimitReached,
+    /// The solver tried more values of the symbols than its search limit
+    SearchLimitReached,
+    /// An expression nested more operations than the solver can handle, e.g. a sum growing
+    /// with every iteration of a loop
+    ExpressionTooLarge,
+}
+
+impl fmt::Display for SymbolicError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SymbolicError::Execution(error) => write!(f, "{}", error),
+            SymbolicError::MissingInput(address) => {
+                write!(
+                    f,
+                    "missing input for the instruction at position {}",
+                    address
+                )
+            }
+            SymbolicError::StepLimitReached => write!(f, "the program did not halt in time"),
+            SymbolicError::SearchLimitReached => write!(f, "too many values to try"),
+            SymbolicError::ExpressionTooLarge => write!(f, "an expression grew too large"),
+        }
+    }
+}
+
+impl error::Error for SymbolicError {}
+
+/// A value the solver can target
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Observation {
+    /// The memory cell at this address, once the program halted
+    Memory(Address),
+    /// The output with this index
+    Output(usize),
+}
+
+// A word, how it is computed from the symbols, and the constraints under which it is computed
+// that way (e.g. it was read at an address computed from a symbol)
+#[derive(Debug, Clone)]
+struct Cell {
+    expr: Rc<Expr>,
+    // the number of operations nested in expr
+    depth: usize,
+    value: Value,
+    depends_on: Vec<usize>,
+}
+
+impl Cell {
+    fn constant(value: Value) -> Self {
+        Cell {
+            expr: Rc::new(Expr::Constant(value)),
+            depth: 0,
+            value,
+            depends_on: Vec::new(),
+        }
+    }
+
+    // adds the constraints of `other` this cell does not depend on yet
+    fn depend_on(&mut self, other: &[usize]) {
+        for index in other {
+            if !self.depends_on.contains(index) {
+                self.depends_on.push(*index);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Input {
+    Concrete(Value),
+    Symbol(usize),
+}
+
+#[derive(Debug, Clone)]
+struct Symbol {
+    // the memory cell holding the symbol, None for inputs
+    address: Option<Address>,
+    range: RangeInclusive<Value>,
+}
+
+/// Finds the values of some memory cells or inputs (the symbols) for which a program computes a
+/// given value, without running it on every possible value.
+///
+/// The program is run concolically: on concrete values of the symbols, while recording how every
+/// word is computed from them. Jump conditions, and words used as addresses or instructions, are
+/// constraints that other values must satisfy to go the same way. The target expression is
+/// solved analytically when it is linear, and by trying every value in the ranges of the symbols
+/// otherwise. Solutions found on the wrong path, and the other sides of the branches, are then
+/// explored the same way, up to a number of paths.
+///
+/// Only `Value` words are supported, and the relative base must not depend on the symbols.
+#[derive(Debug, Clone)]
+pub struct Solver {
+    program: Vec<Value>,
+    symbols: Vec<Symbol>,
+    inputs: Vec<Input>,
+    max_steps: u64,
+    max_paths: usize,
+    max_search: u64,
+    memory_limit: Option<usize>,
+}
+
+impl Solver {
+    pub fn new(program: Vec<Value>) -> Self {
+        Solver {
+            program,
+            symbols: Vec::new(),
+            inputs: Vec::new(),
+            max_steps: DEFAULT_MAX_STEPS,
+            max_paths: DEFAULT_MAX_PATHS,
+            max_search: DEFAULT_MAX_SEARCH,
+            memory_limit: Some(DENSE_MEMORY_LIMIT),
+        }
+    }
+
+    pub fn from_code(code: &str) -> Result<Self, std::num::ParseIntError> {
+        Ok(Solver::new(parse_code(code)?))
+    }
+
+    /// Makes the memory cell at `address` a symbol taking values in `range`. Returns the index
+    /// of the symbol.
+    pub fn symbolic_cell(&mut self, address: Address, range: RangeInclusive<Value>) -> usize {
+        self.add_symbol(Some(address), range)
+    }
+
+    /// Queues an input that is a symbol taking values in `range`. Returns the index of the
+    /// symbol.
+    pub fn symbolic_input(&mut self, range: RangeInclusive<Value>) -> usize {
+        let symbol = self.add_symbol(None, range);
+        self.inputs.push(Input::Symbol(symbol));
+        symbol
+    }
+
+    /// Queues an input with a known value
+    pub fn queue_input(&mut self, value: Value) {
+        self.inputs.push(Input::Concrete(value));
+    }
+
+    /// The number of instructions a run may execute before it is given up
+    pub fn set_max_steps(&mut self, steps: u64) {
+        self.max_steps = steps;
+    }
+
+    /// The number of different paths `solve` may explore
+    pub fn set_max_paths(&mut self, paths: usize) {
+        self.max_paths = paths.max(1);
+    }
+
+    /// The number of values of the symbols `solve` may try
+    pub fn set_max_search(&mut self, candidates: u64) {
+        self.max_search = candidates;
+    }
+
+    /// Makes the runs fail when the program writes at `limit` or beyond, as the interpreter with
+    /// a memory limit does. `DENSE_MEMORY_LIMIT` by default, like the interpreter.
+    pub fn set_memory_limit(&mut self, limit: Option<usize>) {
+        self.memory_limit = limit;
+    }
+
+    fn add_symbol(&mut self, address: Option<Address>, range: RangeInclusive<Value>) -> usize {
+        assert!(!range.is_empty(), "symbols must have at least one value");
+        self.symbols.push(Symbol { address, range });
+        self.symbols.len() - 1
+    }
+
+    /// Runs the program with these values of the symbols
+    pub fn execute(&self, symbols: &[Value]) -> Result<SymbolicRun, SymbolicError> {
+        let (run, result) = self.execute_partially(symbols);
+        result.map(|()| run)
+    }
+
+    // The run up to the instruction that failed, if one did
+    pub(crate) fn execute_partially(
+        &self,
+        symbols: &[Value],
+    ) -> (SymbolicRun, Result<(), SymbolicError>) {
+        assert_eq!(symbols.len(), self.symbols.len(), "one value per symbol");
+        let mut machine = Machine {
+            memory: self
+                .program
+                .iter()
+                .map(|&value| Cell::constant(value))
+                .collect(),
+            outputs: Vec::new(),
+            constraints: Vec::new(),
+            constraint_indices: HashMap::new(),
+            path: Vec::new(),
+            on_path: HashSet::new(),
+            inputs: VecDeque::new(),
+            instruction_pointer: 0,
+            relative_base: 0,
+            memory_limit: self.memory_limit,
+        };
+        let mut result = Ok(());
+        for (index, symbol) in self.symbols.iter().enumerate() {
+            if let Some(address) = symbol.address {
+                result = result.and_then(|()| machine.set(address, symbol_cell(index, symbols)));
+            }
+        }
+        for input in &self.inputs {
+            machine.inputs.push_back(match input {
+                Input::Concrete(value) => Cell::constant(*value),
+                Input::Symbol(index) => symbol_cell(*index, symbols),
+            });
+        }
+
+        let mut steps = 0;
+        let result = result.and_then(|()| loop {
+            match machine.step() {
+                Ok(true) => break Ok(()),
+                Ok(false) => {}
+                Err(error) => break Err(error),
+            }
+            steps += 1;
+            if steps >= self.max_steps {
+                break Err(SymbolicError::StepLimitReached);
+            }
+        });
+        let run = SymbolicRun {
+            memory: machine.memory,
+            outputs: machine.outputs,
+            constraints: machine.constraints,
+            path: machine.path,
+        };
+        (run, result)
+    }
+
+    /// Values of the symbols for which the observed value is `target`, by index of symbol.
+    /// `None` if there are none, or if they were not found within the limits on paths. Fails
+    /// with `ExpressionTooLarge` as soon as a run builds an expression that is too deep.
+    pub fn solve(
+        &self,
+        observation: Observation,
+        target: Value,
+    ) -> Result<Option<Vec<Value>>, SymbolicError> {
+        let mut budget = self.max_search;
+        let mut explored = HashSet::new();
+        let mut pending: VecDeque<Vec<Value>> = VecDeque::new();
+        pending.push_back(
+            self.symbols
+                .iter()
+                .map(|symbol| *symbol.range.start())
+                .collect(),
+        );
+        while let Some(seed) = pending.pop_front() {
+            if explored.len() >= self.max_paths {
+                break;
+            }
+            if !explored.insert(seed.clone()) {
+                continue;
+            }
+            // a seed on which the program fails is a dead end, but the other sides of the branches
+            // it took before failing are still explored
+            let (run, result) = self.execute_partially(&seed);
+            // the other paths may well build the same expressions
+            if result == Err(SymbolicError::ExpressionTooLarge) {
+                return Err(SymbolicError::ExpressionTooLarge);
+            }
+            // the constraints the observed value depends on, in the order they were found
+            let mut constraints = run.path.clone();
+
+            if let (Ok(()), Some(cell)) = (result, run.observe(observation)) {
+                let on_path: HashSet<usize> = run.path.iter().copied().collect();
+                constraints.extend(
+                    cell.depends_on
+                        .iter()
+                        .filter(|index| !on_path.contains(index)),
+                );
+                let mut values = seed.clone();
+                let solved =
+                    self.search_target(&cell.expr, target, &mut values, &mut budget, |values| {
+                        if constraints
+                            .iter()
+                            .all(|&index| run.constraints[index].holds(values))
+                        {
+                            return true;
+                        }
+                        // these values take another path, where they may still be a solution
+                        if !explored.contains(values) && !pending.contains(&values.to_vec()) {
+                            pending.push_back(values.to_vec());
+                        }
+                        false
+                    })?;
+                if solved {
+                    return Ok(Some(values));
+                }
+            }
+
+            // the other side of each branch
+            for (index, &constraint) in constraints.iter().enumerate() {
+                let constraint = &run.constraints[constraint];
+                let previous = &constraints[..index];
+                let symbols: Vec<usize> = constraint.symbols().into_iter().collect();
+                let mut values = seed.clone();
+                let found =
+                    self.for_each_assignment(&symbols, &mut values, &mut budget, |values| {
+                        !constraint.holds(values)
+                            && previous
+                                .iter()
+                                .all(|&previous| run.constraints[previous].holds(values))
+                            && !explored.contains(values)
+                            && !pending.contains(values)
+                    })?;
+                if found {
+                    pending.push_back(values);
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    // Looks for values of the symbols of `expr` for which it evaluates to `target` and `accept`
+    // returns true, starting from `values`. Returns true, `values` holding the solution, if it
+    // found one.
+    fn search_target(
+        &self,
+        expr: &Expr,
+        target: Value,
+        values: &mut Vec<Value>,
+        budget: &mut u64,
+        mut accept: impl FnMut(&[Value]) -> bool,
+    ) -> Result<bool, SymbolicError> {
+        let linear = match expr.linear() {
+            Some(linear) if !linear.coefficients.is_empty() => linear,
+            _ => {
+                let symbols: Vec<usize> = expr.symbols().into_iter().collect();
+                return self.for_each_assignment(&symbols, values, budget, |values| {
+                    expr.evaluate(values) == Some(target) && accept(values)
+                });
+            }
+        };
+
+        // every symbol but one is enumerated, and the last one computed from the others
+        let (&solved, &coefficient) = linear.coefficients.iter().next_back().unwrap();
+        let others: Vec<usize> = linear
+            .coefficients
+            .keys()
+            .copied()
+            .filter(|&symbol| symbol != solved)
+            .collect();
+        let range = self.symbols[solved].range.clone();
+        self.for_each_assignment(&others, values, budget, |values| {
+            let rest =
+                others
+                    .iter()
+                    .try_fold(target.checked_sub(linear.constant), |rest, &symbol| {
+                        Some(
+                            rest?.checked_sub(
+                                linear.coefficients[&symbol].checked_mul(values[symbol])?,
+                            ),
+                        )
+                    });
+            let rest = match rest {
+                Some(Some(rest)) if rest % coefficient == 0 => rest,
+                _ => return false,
+            };
+            if !range.contains(&(rest / coefficient)) {
+                return false;
+            }
+            values[solved] = rest / coefficient;
+            accept(values)
+        })
+    }
+
+    // Tries every combination of values of `symbols` in their ranges, the other ones keeping
+    // their values, until `found` returns true
+    fn for_each_assignment(
+        &self,
+        symbols: &[usize],
+        values: &mut Vec<Value>,
+        budget: &mut u64,
+        mut found: impl FnMut(&mut Vec<Value>) -> bool,
+    ) -> Result<bool, SymbolicError> {
+        for &symbol in symbols {
+            values[symbol] = *self.symbols[symbol].range.start();
+        }
+        loop {
+            if *budget == 0 {
+                return Err(SymbolicError::SearchLimitReached);
+            }
+            *budget -= 1;
+            if found(values) {
+                return Ok(true);
+            }
+            // next combination, the last symbol changing the fastest
+            let mut index = symbols.len();
+            loop {
+                if index == 0 {
+                    return Ok(false);
+                }
+                index -= 1;
+                let symbol = symbols[index];
+                let range = &self.symbols[symbol].range;
+                if values[symbol] < *range.end() {
+                    values[symbol] += 1;
+                    break;
+                }
+                values[symbol] = *range.start();
+            }
+        }
+    }
+}
+
+fn symbol_cell(index: usize, symbols: &[Value]) -> Cell {
+    Cell {
+        expr: Rc::new(Expr::Symbol(index)),
+        depth: 0,
+        value: symbols[index],
+        depends_on: Vec::new(),
+    }
+}
+
+/// The result of `Solver::execute`: every word of the memory and every output, as expressions
+/// of the symbols. They are only valid under the constraints of the path the run took.
+#[derive(Debug, Clone)]
+pub struct SymbolicRun {
+    memory: Vec<Cell>,
+    outputs: Vec<Cell>,
+    constraints: Vec<Constraint>,
+    // the constraints every value depends on, in the order they were found
+    path: Vec<usize>,
+}
+
+impl SymbolicRun {
+    /// The final value of the memory cell at `address`
+    pub fn memory(&self, address: Address) -> Rc<Expr> {
+        match self.memory.get(address) {
+            Some(cell) => cell.expr.clone(),
+            None => Rc::new(Expr::Constant(0)),
+        }
+    }
+
+    pub fn outputs(&self) -> Vec<Rc<Expr>> {
+        self.outputs.iter().map(|cell| cell.expr.clone()).collect()
+    }
+
+    /// The conditions for another run to take the same path, such as the conditions of the
+    /// jumps
+    pub fn path_constraints(&self) -> Vec<&Constraint> {
+        self.path
+            .iter()
+            .map(|&index| &self.constraints[index])
+            .collect()
+    }
+
+    // the memory and the outputs for the values of the symbols the run used
+    pub(crate) fn concrete_values(&self) -> (Vec<Value>, Vec<Value>) {
+        (
+            self.memory.iter().map(|cell| cell.value).collect(),
+            self.outputs.iter().map(|cell| cell.value).collect(),
+        )
+    }
+
+    fn observe(&self, observation: Observation) -> Option<Cell> {
+        match observation {
+            Observation::Memory(address) => Some(
+                self.memory
+                    .get(address)
+                    .cloned()
+                    .unwrap_or_else(|| Cell::constant(0)),
+            ),
+            Observation::Output(index) => self.outputs.get(index).cloned(),
+        }
+    }
+}
+
+// The state of a run in progress
+struct Machine {
+    memory: Vec<Cell>,
+    outputs: Vec<Cell>,
+    constraints: Vec<Constraint>,
+    // the index of each constraint, by expression and value (None for NotZero): constraints
+    // keep their expression alive, so that its address is not reused
+    constraint_indices: HashMap<(*const Expr, Option<Value>), usize>,
+    path: Vec<usize>,
+    on_path: HashSet<usize>,
+    inputs: VecDeque<Cell>,
+    instruction_pointer: Address,
+    relative_base: Value,
+    memory_limit: Option<usize>,
+}
+
+impl Machine {
+    fn get(&self, address: Address) -> Cell {
+        match self.memory.get(address) {
+            Some(cell) => cell.clone(),
+            None => Cell::constant(0),
+        }
+    }
+
+    fn set(&mut self, address: Address, cell: Cell) -> Result<(), SymbolicError> {
+        if self.memory_limit.is_some_and(|limit| address >= limit) {
+            return Err(self.fail(ExecutionErrorKind::MemoryLimitExceeded(address)));
+        }
+        if address >= self.memory.len() {
+            self.memory.resize(address + 1, Cell::constant(0));
+        }
+        self.memory[address] = cell;
+        Ok(())
+    }
+
+    // The index of the constraint, which is only added once
+    fn constrain(&mut self, constraint: Constraint) -> usize {
+        let key = match &constraint {
+            Constraint::Equals(expr, value) => (Rc::as_ptr(expr), Some(*value)),
+            Constraint::NotZero(expr) => (Rc::as_ptr(expr), None),
+        };
+        let constraints = &mut self.constraints;
+        *self.constraint_indices.entry(key).or_insert_with(|| {
+            constraints.push(constraint);
+            constraints.len() - 1
+        })
+    }
+
+    // The constraints under which `cell` has its concrete value
+    fn pin(&mut self, cell: &Cell) -> Vec<usize> {
+        let mut depends_on = cell.depends_on.clone();
+        if !cell.expr.is_constant() {
+            let index = self.constrain(Constraint::Equals(cell.expr.clone(), cell.value));
+            if !depends_on.contains(&index) {
+                depends_on.push(index);
+            }
+        }
+        depends_on
+    }
+
+    // Makes the whole run depend on the concrete value of `cell`
+    fn pin_path(&mut self, cell: &Cell) {
+        let depends_on = self.pin(cell);
+        self.add_to_path(depends_on);
+    }
+
+    fn add_to_path(&mut self, depends_on: Vec<usize>) {
+        for index in depends_on {
+            if self.on_path.insert(index) {
+                self.path.push(index);
+            }
+        }
+    }
+
+    fn fail(&self, kind: ExecutionErrorKind) -> SymbolicError {
+        let instruction = self.get(self.instruction_pointer).value;
+        SymbolicError::Execution(ExecutionError::new(
+            kind,
+            self.instruction_pointer,
+            instruction,
+        ))
+    }
+
+    fn address(
+        &mut self,
+        parameter: &Cell,
+        mode: InstructionMode,
+    ) -> Result<Address, SymbolicError> {
+        let address = match mode {
+            InstructionMode::Relative => parameter
+                .value
+                .checked_add(self.relative_base)
+                .ok_or_else(|| self.fail(ExecutionErrorKind::ArithmeticOverflow))?,
+            _ => parameter.value,
+        };
+        to_address(&address).map_err(|kind| self.fail(kind))
+    }
+
+    fn read(&mut self, parameter: Cell, mode: InstructionMode) -> Result<Cell, SymbolicError> {
+        if mode == InstructionMode::Immediate {
+            return Ok(parameter);
+        }
+        let address = self.address(&parameter, mode)?;
+        let mut cell = self.get(address);
+        // another address would have been read with other values of the symbols
+        let depends_on = self.pin(&parameter);
+        cell.depend_on(&depends_on);
+        Ok(cell)
+    }
+
+    fn write(
+        &mut self,
+        parameter: Cell,
+        mode: InstructionMode,
+        cell: Cell,
+    ) -> Result<(), SymbolicError> {
+        if mode == InstructionMode::Immediate {
+            return Err(self.fail(ExecutionErrorKind::WriteInImmediateMode));
+        }
+        let address = self.address(&parameter, mode)?;
+        self.set(address, cell)?;
+        self.pin_path(&parameter);
+        Ok(())
+    }
+
+    // Executes one instruction. Returns true if it halted.
+    fn step(&mut self) -> Result<bool, SymbolicError> {
+        let instruction = self.get(self.instruction_pointer);
+        self.pin_path(&instruction);
+        let definition =
+            parse_instruction_definition(instruction.value).map_err(|kind| self.fail(kind))?;
+        let modes = definition.instruction_modes;
+        let parameter =
+            |machine: &Machine, index: usize| machine.get(machine.instruction_pointer + 1 + index);
+        let next = self.instruction_pointer + definition.length;
+
+        match definition.opcode {
+            Opcode::Addition | Opcode::Multiplication | Opcode::LessThan | Opcode::Equals => {
+                let a = self.read(parameter(self, 0), modes[0])?;
+                let b = self.read(parameter(self, 1), modes[1])?;
+                let value = match definition.opcode {
+                    Opcode::Addition => a.value.checked_add(b.value),
+                    Opcode::Multiplication => a.value.checked_mul(b.value),
+                    Opcode::LessThan => Some((a.value < b.value) as Value),
+                    _ => Some((a.value == b.value) as Value),
+                };
+                let value =
+                    value.ok_or_else(|| self.fail(ExecutionErrorKind::ArithmeticOverflow))?;
+                let (expr, depth) = combine(definition.opcode, &a, &b, value);
+                if depth > MAX_EXPR_DEPTH {
+                    return Err(SymbolicError::ExpressionTooLarge);
+                }
+                let mut result = Cell {
+                    expr,
+                    depth,
+                    value,
+                    depends_on: a.depends_on,
+                };
+                result.depend_on(&b.depends_on);
+                self.write(parameter(self, 2), modes[2], result)?;
+            }
+            Opcode::Input => {
+                let input = match self.inputs.pop_front() {
+                    Some(input) => input,
+                    None => return Err(SymbolicError::MissingInput(self.instruction_pointer)),
+                };
+                self.write(parameter(self, 0), modes[0], input)?;
+            }
+            Opcode::Output => {
+                let output = self.read(parameter(self, 0), modes[0])?;
+                self.outputs.push(output);
+            }
+            Opcode::JumpIfTrue | Opcode::JumpIfFalse => {
+                let condition = self.read(parameter(self, 0), modes[0])?;
+                let mut depends_on = condition.depends_on.clone();
+                if !condition.expr.is_constant() {
+                    depends_on.push(self.constrain(if condition.value != 0 {
+                        Constraint::NotZero(condition.expr.clone())
+                    } else {
+                        Constraint::Equals(condition.expr.clone(), 0)
+                    }));
+                }
+                self.add_to_path(depends_on);
+                if (condition.value != 0) == (definition.opcode == Opcode::JumpIfTrue) {
+                    let target = self.read(parameter(self, 1), modes[1])?;
+                    self.pin_path(&target);
+                    self.instruction_pointer =
+                        to_address(&target.value).map_err(|kind| self.fail(kind))?;
+                    return Ok(false);
+                }
+            }
+            Opcode::AdjustRelativeBase => {
+                let offset = self.read(parameter(self, 0), modes[0])?;
+                self.pin_path(&offset);
+                self.relative_base = self
+                    .relative_base
+                    .checked_add(offset.value)
+                    .ok_or_else(|| self.fail(ExecutionErrorKind::ArithmeticOverflow))?;
+            }
+            Opcode::Halt => return Ok(true),
+            Opcode::Registered(_) => unreachable!("the solver only decodes built-in opcodes"),
+        }
+        self.instruction_pointer = next;
+        Ok(false)
+    }
+}
+
+// The expression of an arithmetic or comparison instruction and its depth, folding constants
+fn combine(opcode: Opcode, a: &Cell, b: &Cell, value: Value) -> (Rc<Expr>, usize) {
+    if a.expr.is_constant() && b.expr.is_constant() {
+        return (Rc::new(Expr::Constant(value)), 0);
+    }
+    let (x, y) = (a.expr.clone(), b.expr.clone());
+    let expr = match opcode {
+        Opcode::Addition if *x == Expr::Constant(0) => return (y, b.depth),
+        Opcode::Addition if *y == Expr::Constant(0) => return (x, a.depth),
+        Opcode::Addition => Expr::Add(x, y),
+        Opcode::Multiplication if *x == Expr::Constant(0) || *y == Expr::Constant(0) => {
+            return (Rc::new(Expr::Constant(0)), 0)
+        }
+        Opcode::Multiplication if *x == Expr::Constant(1) => return (y, b.depth),
+        Opcode::Multiplication if *y == Expr::Constant(1) => return (x, a.depth),
+        Opcode::Multiplication => Expr::Multiply(x, y),
+        Opcode::LessThan => Expr::LessThan(x, y),
+        _ => Expr::Equals(x, y),
+    };
+    (Rc::new(expr), a.depth.max(b.depth) + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::assemble;
+
+    #[test]
+    fn test_linear_memory() {
+        // memory[0] = (memory[9] + memory[10]) * memory[11]
+        let mut solver = Solver::from_code("1,9,10,3,2,3,11,0,99,30,40,50").unwrap();
+        let a = solver.symbolic_cell(9, 0..=100);
+        let b = solver.symbolic_cell(10, 0..=100);
+        let run = solver.execute(&[30, 40]).unwrap();
+        assert_eq!(run.memory(0).to_string(), "((x0 + x1) * 50)");
+        assert!(run.path_constraints().is_empty());
+
+        let solution = solver.solve(Observation::Memory(0), 3500).unwrap().unwrap();
+        assert_eq!((solution[a], solution[b]), (0, 70));
+        assert_eq!(solver.solve(Observation::Memory(0), 3501).unwrap(), None);
+    }
+
+    #[test]
+    fn test_symbolic_addresses() {
+        // memory[0] = memory[memory[1]] + 1: the read address depends on the symbol, but only
+        // the values it reads do
+        let mut solver = Solver::from_code("1001,0,1,0,99,7,8").unwrap();
+        solver.symbolic_cell(1, 0..=6);
+        let run = solver.execute(&[5]).unwrap();
+        assert_eq!(*run.memory(0), Expr::Constant(8));
+        assert!(run.path_constraints().is_empty());
+        // 6 is read at address 4 on another path
+        assert_eq!(
+            solver.solve(Observation::Memory(0), 9).unwrap(),
+            Some(vec![6])
+        );
+        assert_eq!(
+            solver.solve(Observation::Memory(0), 100).unwrap(),
+            Some(vec![4])
+        );
+        assert_eq!(solver.solve(Observation::Memory(0), 3).unwrap(), None);
+    }
+
+    #[test]
+    fn test_branches() {
+        // outputs the square of its input below 10, and the input plus 100 otherwise
+        let program = assemble(
+            "
+                        in [pos x]
+                        lt [pos x], #10, [pos small]
+                        jt [pos small], #square
+                        add [pos x], #100, [pos y]
+                        out [pos y]
+                        hlt
+                square: mul [pos x], [pos x], [pos y]
+                        out [pos y]
+                        hlt
+                x:      .data 0
+                small:  .data 0
+                y:      .data 0
+            ",
+        )
+        .unwrap();
+        let mut solver = Solver::from_code(&program).unwrap();
+        solver.symbolic_input(0..=99);
+        let run = solver.execute(&[3]).unwrap();
+        assert_eq!(run.outputs()[0].to_string(), "(x0 * x0)");
+        let constraints: Vec<String> = run
+            .path_constraints()
+            .iter()
+            .map(|constraint| constraint.to_string())
+            .collect();
+        assert_eq!(constraints, vec!["(x0 < 10) != 0"]);
+
+        assert_eq!(
+            solver.solve(Observation::Output(0), 64).unwrap(),
+            Some(vec![8])
+        );
+        assert_eq!(
+            solver.solve(Observation::Output(0), 149).unwrap(),
+            Some(vec![49])
+        );
+        assert_eq!(solver.solve(Observation::Output(0), 105).unwrap(), None);
+    }
+
+    #[test]
+    fn test_limits() {
+        let mut solver = Solver::from_code("3,7,1105,1,0,99,0,0").unwrap();
+        solver.queue_input(1);
+        assert_eq!(
+            solver.execute(&[]).unwrap_err(),
+            SymbolicError::MissingInput(0)
+        );
+        let mut solver = Solver::from_code("1105,1,0").unwrap();
+        solver.set_max_steps(100);
+        assert_eq!(
+            solver.execute(&[]).unwrap_err(),
+            SymbolicError::StepLimitReached
+        );
+
+        // a single symbol with too many values to try
+        let mut solver = Solver::from_code("2,5,5,0,99,0").unwrap();
+        solver.symbolic_cell(5, 0..=1_000_000);
+        solver.set_max_search(1000);
+        assert_eq!(
+            solver.solve(Observation::Memory(0), 2),
+            Err(SymbolicError::SearchLimitReached)
+        );
+
+        let mut solver = Solver::from_code("99").unwrap();
+        solver.symbolic_cell(1 << 40, 0..=1);
+        match solver.execute(&[0]).unwrap_err() {
+            SymbolicError::Execution(error) => assert_eq!(
+                error.kind(),
+                &ExecutionErrorKind::MemoryLimitExceeded(1 << 40)
+            ),
+            error => panic!("unexpected error {}", error),
+        }
+    }
+
+    #[test]
+    fn test_long_loops() {
+        // adds the symbol to cell 20 as many times as the last cell says, then outputs it
+        let program = |iterations: Value| {
+            format!(
+                "1,20,21,20,1001,22,-1,22,1005,22,0,4,20,99,0,0,0,0,0,0,0,0,{}",
+                iterations
+            )
+        };
+        let mut solver = Solver::from_code(&program(1000)).unwrap();
+        solver.symbolic_cell(21, 0..=10);
+        assert_eq!(
+            solver.solve(Observation::Output(0), 3000).unwrap(),
+            Some(vec![3])
+        );
+        let mut solver = Solver::from_code(&program(100_000)).unwrap();
+        solver.symbolic_cell(21, 0..=10);
+        assert_eq!(
+            solver.solve(Observation::Output(0), 3000),
+            Err(SymbolicError::ExpressionTooLarge)
+        );
+
+        // the same symbolic address is read on every iteration, under a single constraint
+        let mut solver =
+            Solver::from_code("1,21,20,20,1001,22,-1,22,1005,22,0,4,20,99,0,0,0,0,0,0,0,1,1000")
+                .unwrap();
+        solver.symbolic_cell(1, 0..=30);
+        let run = solver.execute(&[21]).unwrap();
+        assert_eq!(run.concrete_values().1, vec![1000]);
+        assert_eq!(run.constraints.len(), 1);
+    }
+
+    #[test]
+    fn test_failing_paths() {
+        // fails on inputs below 10, and outputs the input plus 100 otherwise
+        let program = assemble(
+            "
+                        in [pos x]
+                        lt [pos x], #10, [pos small]
+                        jf [pos small], #large
+                        .data 42
+                large:  add [pos x], #100, [pos y]
+                        out [pos y]
+                        hlt
+                x:      .data 0
+                small:  .data 0
+                y:      .data 0
+            ",
+        )
+        .unwrap();
+        let mut solver = Solver::from_code(&program).unwrap();
+        solver.symbolic_input(0..=99);
+        assert!(solver.execute(&[0]).is_err());
+        assert_eq!(
+            solver.solve(Observation::Output(0), 150).unwrap(),
+            Some(vec![50])
+        );
+    }
+}
@@ -0,0 +1,236 @@
+use crate::disassembler::Operand;
+use crate::interpreter::{Address, Value};
+use crate::word::Word;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+/// One executed instruction.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TraceEvent<W = Value> {
+    pub instruction_pointer: Address,
+    pub mnemonic: &'static str,
+    pub operands: Vec<Operand<W>>,
+    /// The address each operand refers to, once the relative base is applied. `None` for
+    /// immediate operands.
+    pub addresses: Vec<Option<Address>>,
+    /// The cell the instruction wrote, and its new value
+    pub write: Option<(Address, W)>,
+}
+
+/// Receives every instruction the interpreter executes, see `Interpreter::set_trace_sink`.
+pub trait TraceSink<W> {
+    fn record(&mut self, event: &TraceEvent<W>);
+}
+
+// Lets the caller keep a handle on the sink given to the interpreter
+impl<W, T: TraceSink<W>> TraceSink<W> for Arc<Mutex<T>> {
+    fn record(&mut self, event: &TraceEvent<W>) {
+        self.lock().unwrap().record(event);
+    }
+}
+
+/// Counts executed instructions per address and per opcode.
+#[derive(Debug, Default, Clone)]
+pub struct Profiler {
+    total: u64,
+    // address => (mnemonic, count); the mnemonic is the last one seen, in case of self-modification
+    by_address: BTreeMap<Address, (&'static str, u64)>,
+    by_opcode: BTreeMap<&'static str, u64>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    pub fn count_at(&self, address: Address) -> u64 {
+        self.by_address.get(&address).map_or(0, |&(_, count)| count)
+    }
+
+    pub fn count_of(&self, mnemonic: &str) -> u64 {
+        self.by_opcode.get(mnemonic).copied().unwrap_or(0)
+    }
+
+    /// The `limit` most executed addresses, most executed first
+    pub fn hot_spots(&self, limit: usize) -> Vec<(Address, &'static str, u64)> {
+        let mut hot_spots: Vec<(Address, &'static str, u64)> = self
+            .by_address
+            .iter()
+            .map(|(&address, &(mnemonic, count))| (address, mnemonic, count))
+            .collect();
+        hot_spots.sort_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(&b.0)));
+        hot_spots.truncate(limit);
+        hot_spots
+    }
+
+    /// Human readable summary: counts per opcode, then the `limit` hottest addresses
+    pub fn report(&self, limit: usize) -> String {
+        let percentage = |count: u64| 100.0 * count as f64 / self.total.max(1) as f64;
+        let mut report = String::new();
+        writeln!(report, "{} instructions executed", self.total).unwrap();
+        writeln!(report, "by opcode:").unwrap();
+        let mut by_opcode: Vec<(&str, u64)> =
+            self.by_opcode.iter().map(|(&m, &c)| (m, c)).collect();
+        by_opcode.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        for (mnemonic, count) in by_opcode {
+            let percent = percentage(count);
+            writeln!(report, "  {:<4} {:>12} {:>6.2}%", mnemonic, count, percent).unwrap();
+        }
+        writeln!(report, "hot spots:").unwrap();
+        for (address, mnemonic, count) in self.hot_spots(limit) {
+            let percent = percentage(count);
+            writeln!(
+                report,
+                "  {:>6}: {:<4} {:>12} {:>6.2}%",
+                address, mnemonic, count, percent
+            )
+            .unwrap();
+        }
+        report
+    }
+}
+
+impl<W> TraceSink<W> for Profiler {
+    fn record(&mut self, event: &TraceEvent<W>) {
+        self.total += 1;
+        let entry = self
+            .by_address
+            .entry(event.instruction_pointer)
+            .or_insert((event.mnemonic, 0));
+        entry.0 = event.mnemonic;
+        entry.1 += 1;
+        *self.by_opcode.entry(event.mnemonic).or_insert(0) += 1;
+    }
+}
+
+/// Writes the trace as JSON Lines, one object per instruction:
+///
+/// ```text
+/// {"ip":0,"opcode":"add","operands":[{"mode":"pos","value":9},...],"addresses":[9,null,3],"write":{"address":3,"value":42}}
+/// ```
+///
+/// Writing stops at the first IO error, which `finish` returns.
+#[derive(Debug)]
+pub struct JsonLinesTrace<T> {
+    writer: T,
+    error: Option<io::Error>,
+}
+
+impl<T: Write> JsonLinesTrace<T> {
+    pub fn new(writer: T) -> Self {
+        JsonLinesTrace {
+            writer,
+            error: None,
+        }
+    }
+
+    /// Flushes the writer and gives it back
+    pub fn finish(mut self) -> io::Result<T> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+impl<W: Word, T: Write> TraceSink<W> for JsonLinesTrace<T> {
+    fn record(&mut self, event: &TraceEvent<W>) {
+        if self.error.is_some() {
+            return;
+        }
+        if let Err(error) = writeln!(self.writer, "{}", to_json(event)) {
+            self.error = Some(error);
+        }
+    }
+}
+
+// Words are integers, so they can be written as JSON numbers whatever their size
+fn to_json<W: Word>(event: &TraceEvent<W>) -> String {
+    let operands: Vec<String> = event
+        .operands
+        .iter()
+        .map(|operand| {
+            let (mode, value) = match operand {
+                Operand::Position(value) => ("pos", value),
+                Operand::Immediate(value) => ("imm", value),
+                Operand::Relative(value) => ("rel", value),
+            };
+            format!("{{\"mode\":\"{}\",\"value\":{}}}", mode, value)
+        })
+        .collect();
+    let addresses: Vec<String> = event
+        .addresses
+        .iter()
+        .map(|address| match address {
+            Some(address) => address.to_string(),
+            None => String::from("null"),
+        })
+        .collect();
+    let write = match &event.write {
+        Some((address, value)) => format!("{{\"address\":{},\"value\":{}}}", address, value),
+        None => String::from("null"),
+    };
+    format!(
+        "{{\"ip\":{},\"opcode\":\"{}\",\"operands\":[{}],\"addresses\":[{}],\"write\":{}}}",
+        event.instruction_pointer,
+        event.mnemonic,
+        operands.join(","),
+        addresses.join(","),
+        write
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::{IOAction, Interpreter};
+
+    #[test]
+    fn test_profiler() {
+        let quine = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+        let profiler = Arc::new(Mutex::new(Profiler::new()));
+        let mut interpreter: Interpreter = Interpreter::from_code(quine).unwrap();
+        interpreter.set_trace_sink(profiler.clone());
+        while interpreter.run_until_block().unwrap() != IOAction::Halt {}
+
+        let profiler = profiler.lock().unwrap();
+        // 16 iterations of 5 instructions, then halt
+        assert_eq!(profiler.total(), 81);
+        assert_eq!(profiler.count_at(0), 16);
+        assert_eq!(profiler.count_at(15), 1);
+        assert_eq!(profiler.count_of("arb"), 16);
+        assert_eq!(profiler.count_of("jf"), 16);
+        assert_eq!(profiler.hot_spots(2), vec![(0, "arb", 16), (2, "out", 16)]);
+        assert!(profiler
+            .report(3)
+            .contains("      4: add            16  19.75%"));
+    }
+
+    #[test]
+    fn test_json_lines_trace() {
+        let code = "109,2,21101,20,22,3,99";
+        let trace = Arc::new(Mutex::new(JsonLinesTrace::new(Vec::new())));
+        let mut interpreter: Interpreter = Interpreter::from_code(code).unwrap();
+        interpreter.set_trace_sink(trace.clone());
+        assert_eq!(interpreter.run_until_block().unwrap(), IOAction::Halt);
+        drop(interpreter);
+
+        let trace = Arc::try_unwrap(trace).unwrap().into_inner().unwrap();
+        let lines = String::from_utf8(trace.finish().unwrap()).unwrap();
+        let expected_lines = [
+            r#"{"ip":0,"opcode":"arb","operands":[{"mode":"imm","value":2}],"addresses":[null],"write":null}"#,
+            r#"{"ip":2,"opcode":"add","operands":[{"mode":"imm","value":20},{"mode":"imm","value":22},{"mode":"rel","value":3}],"addresses":[null,null,5],"write":{"address":5,"value":42}}"#,
+            r#"{"ip":6,"opcode":"hlt","operands":[],"addresses":[],"write":null}"#,
+            "",
+        ];
+        assert_eq!(lines, expected_lines.join("\n"));
+    }
+}
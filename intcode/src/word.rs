@@ -0,0 +1,166 @@
+use crate::interpreter::Address;
+use num_bigint::{BigInt, Sign};
+use std::convert::TryFrom;
+use std::error;
+use std::fmt;
+use std::num;
+
+/// A memory cell of an Intcode machine.
+///
+/// The interpreter only needs a handful of integer operations, so that programs can run on
+/// fixed-size words (`i64`, `i128`) as well as on arbitrary-precision integers (`BigInt`).
+pub trait Word: Clone + PartialEq + PartialOrd + fmt::Debug + fmt::Display {
+    type ParseError: error::Error + 'static;
+
+    fn parse_word(s: &str) -> Result<Self, Self::ParseError>;
+    fn from_i64(value: i64) -> Self;
+    /// `None` if the word does not fit in an `i64` (it can not be a valid instruction then)
+    fn to_i64(&self) -> Option<i64>;
+    /// `None` if the word is negative or too large to be an address on this platform
+    fn to_address(&self) -> Option<Address>;
+    fn is_negative(&self) -> bool;
+    fn is_zero(&self) -> bool;
+
+    fn checked_add(&self, other: &Self) -> Option<Self>;
+    fn wrapping_add(&self, other: &Self) -> Self;
+    fn saturating_add(&self, other: &Self) -> Self;
+    fn checked_mul(&self, other: &Self) -> Option<Self>;
+    fn wrapping_mul(&self, other: &Self) -> Self;
+    fn saturating_mul(&self, other: &Self) -> Self;
+}
+
+macro_rules! impl_word_for_primitive {
+    ($type:ty) => {
+        impl Word for $type {
+            type ParseError = num::ParseIntError;
+
+            fn parse_word(s: &str) -> Result<Self, Self::ParseError> {
+                s.parse::<$type>()
+            }
+
+            fn from_i64(value: i64) -> Self {
+                value.into()
+            }
+
+            fn to_i64(&self) -> Option<i64> {
+                i64::try_from(*self).ok()
+            }
+
+            fn to_address(&self) -> Option<Address> {
+                Address::try_from(*self).ok()
+            }
+
+            fn is_negative(&self) -> bool {
+                *self < 0
+            }
+
+            fn is_zero(&self) -> bool {
+                *self == 0
+            }
+
+            fn checked_add(&self, other: &Self) -> Option<Self> {
+                <$type>::checked_add(*self, *other)
+            }
+
+            fn wrapping_add(&self, other: &Self) -> Self {
+                <$type>::wrapping_add(*self, *other)
+            }
+
+            fn saturating_add(&self, other: &Self) -> Self {
+                <$type>::saturating_add(*self, *other)
+            }
+
+            fn checked_mul(&self, other: &Self) -> Option<Self> {
+                <$type>::checked_mul(*self, *other)
+            }
+
+            fn wrapping_mul(&self, other: &Self) -> Self {
+                <$type>::wrapping_mul(*self, *other)
+            }
+
+            fn saturating_mul(&self, other: &Self) -> Self {
+                <$type>::saturating_mul(*self, *other)
+            }
+        }
+    };
+}
+
+impl_word_for_primitive!(i64);
+impl_word_for_primitive!(i128);
+
+// Arbitrary-precision words never overflow, so every arithmetic mode behaves the same
+impl Word for BigInt {
+    type ParseError = num_bigint::ParseBigIntError;
+
+    fn parse_word(s: &str) -> Result<Self, Self::ParseError> {
+        s.parse::<BigInt>()
+    }
+
+    fn from_i64(value: i64) -> Self {
+        value.into()
+    }
+
+    fn to_i64(&self) -> Option<i64> {
+        i64::try_from(self).ok()
+    }
+
+    fn to_address(&self) -> Option<Address> {
+        Address::try_from(self).ok()
+    }
+
+    fn is_negative(&self) -> bool {
+        self.sign() == Sign::Minus
+    }
+
+    fn is_zero(&self) -> bool {
+        self.sign() == Sign::NoSign
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        Some(self + other)
+    }
+
+    fn wrapping_add(&self, other: &Self) -> Self {
+        self + other
+    }
+
+    fn saturating_add(&self, other: &Self) -> Self {
+        self + other
+    }
+
+    fn checked_mul(&self, other: &Self) -> Option<Self> {
+        Some(self * other)
+    }
+
+    fn wrapping_mul(&self, other: &Self) -> Self {
+        self * other
+    }
+
+    fn saturating_mul(&self, other: &Self) -> Self {
+        self * other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_address() {
+        assert_eq!(12i64.to_address(), Some(12));
+        assert_eq!((-1i64).to_address(), None);
+        assert_eq!(i128::MAX.to_address(), None);
+        assert_eq!(BigInt::from(12).to_address(), Some(12));
+        assert_eq!(BigInt::from(-12).to_address(), None);
+    }
+
+    #[test]
+    fn test_big_int_never_overflows() {
+        let max = BigInt::from(i64::MAX);
+        let expected = BigInt::parse_word("85070591730234615847396907784232501249").unwrap();
+        assert_eq!(max.checked_mul(&max), Some(expected.clone()));
+        assert_eq!(max.saturating_mul(&max), expected);
+        assert_eq!(max.to_i64(), Some(i64::MAX));
+        assert_eq!(expected.to_i64(), None);
+    }
+}
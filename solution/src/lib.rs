@@ -0,0 +1,79 @@
+//! Common interface implemented by every day, so that the `aoc` runner can
+//! dispatch to any of them.
+
+use std::convert::TryFrom;
+use std::fmt;
+
+/// The answer to one part of a puzzle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Answer {
+    Integer(i64),
+    // some puzzles want us to read letters drawn on a grid
+    Text(String),
+    /// A part that has no solution yet
+    Unsolved,
+}
+
+impl fmt::Display for Answer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Answer::Integer(value) => write!(f, "{}", value),
+            Answer::Text(text) => write!(f, "{}", text),
+            Answer::Unsolved => write!(f, "not solved"),
+        }
+    }
+}
+
+impl From<i64> for Answer {
+    fn from(value: i64) -> Self {
+        Answer::Integer(value)
+    }
+}
+
+impl From<i32> for Answer {
+    fn from(value: i32) -> Self {
+        Answer::Integer(value.into())
+    }
+}
+
+impl From<u32> for Answer {
+    fn from(value: u32) -> Self {
+        Answer::Integer(value.into())
+    }
+}
+
+impl From<isize> for Answer {
+    fn from(value: isize) -> Self {
+        Answer::Integer(i64::try_from(value).expect("answer does not fit in an i64"))
+    }
+}
+
+impl From<usize> for Answer {
+    fn from(value: usize) -> Self {
+        Answer::Integer(i64::try_from(value).expect("answer does not fit in an i64"))
+    }
+}
+
+impl From<String> for Answer {
+    fn from(text: String) -> Self {
+        Answer::Text(text)
+    }
+}
+
+/// A day of the calendar. Both parts receive the content of the puzzle input.
+pub trait Solution {
+    fn part_1(&self, input: &str) -> Answer;
+    fn part_2(&self, input: &str) -> Answer;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_answer_display() {
+        assert_eq!(Answer::from(-42).to_string(), "-42");
+        assert_eq!(Answer::from(String::from("ABC")).to_string(), "ABC");
+        assert_eq!(Answer::Unsolved.to_string(), "not solved");
+    }
+}